@@ -1,25 +1,122 @@
 use bevy::{
-    asset::Handle,
-    audio::AudioSource,
     math::Vec2,
-    prelude::{Component, Deref, Event, Resource},
+    prelude::{Component, Event},
 };
 
+pub const PLAYFIELD_WIDTH: f32 = 480.0;
+pub const PLAYFIELD_HEIGHT: f32 = 640.0;
+pub const FIXED_TICK_SECONDS: f64 = 1.0 / 60.0;
+pub const HEADLESS_DEFAULT_TICKS: u32 = 3600;
+pub const REPLAY_FILE_VERSION: u32 = 2;
+pub const REPLAY_SPEEDS: [f64; 4] = [1.0, 2.0, 4.0, 8.0];
+// how far a stick has to be pushed to count as a direction
+pub const GAMEPAD_DEADZONE: f32 = 0.5;
+pub const REPLAY_MENU_ENTRIES: usize = 8;
+pub const HIGH_SCORE_FILE: &str = "highscores.ron";
+pub const SETTINGS_FILE: &str = "settings.ron";
+pub const HIGH_SCORE_FILE_VERSION: u32 = 1;
+pub const HIGH_SCORE_ENTRIES: usize = 10;
+pub const HIGH_SCORE_NAME_LENGTH: usize = 12;
+pub const TUNING_FILE: &str = "tuning/game.tuning.ron";
 pub const JET_SQUARE_BOX_LENGTH: f32 = 50.0;
-pub const BULLET_CREATE_TIMER_SECONDS: f32 = 0.5;
 pub const BULLET_CIRCLE_RADIUS: f32 = 5.0;
-pub const BULLET_VELOCITY: f32 = 5.0;
-pub const JET_TRAVEL_DISTANCE: f32 = 5.0;
 pub const ENEMY_SQUARE_BOX_LENGTH: f32 = 100.0;
-pub const ENEMY_SPAWN_HEALTH: i32 = 20;
+pub const ASSET_MANIFEST: &str = "preload.manifest.ron";
+pub const MAX_PLAYERS: usize = 2;
+pub const JET_SPRITE_NAMES: [&str; MAX_PLAYERS] = ["DurrrSpaceShip.png", "jet1.png"];
 pub const ENEMY_SPACE_SPRITE_NAME: &str = "spaceship_small.png";
 pub const ENEMY_OBJECT_SCALE: Vec2 = Vec2::new(1., 1.);
+pub const ENEMY_BULLET_RADIUS: f32 = 4.0;
+pub const JET_HITBOX_RADIUS: f32 = 12.0;
+// a team-mate this close to a downed jet for this long brings it back
+pub const REVIVE_RADIUS: f32 = 60.0;
+pub const REVIVE_SECONDS: f32 = 2.0;
+pub const GRAZE_POINTS: usize = 10;
+pub const COMBO_KILLS_PER_STEP: u32 = 3;
+pub const MAX_COMBO_MULTIPLIER: u32 = 8;
+pub const WAVE_KILLS: u32 = 10;
+pub const ACCURACY_BONUS_POINTS: usize = 2000;
+pub const NO_DAMAGE_BONUS_POINTS: usize = 1000;
+pub const HUD_FONT: &str = "fonts/CascadiaMonoItalic.ttf";
+// past this many lives or bombs the HUD shows a count instead of more icons
+pub const HUD_MAX_ICONS: u32 = 5;
+// how quickly the shown score catches up with the real one, per second
+pub const SCORE_ROLL_RATE: f32 = 8.0;
+// the settings volume runs from 0 (muted) to this
+pub const MAX_VOLUME: u32 = 9;
+// how long one song takes to fade into the next
+pub const MUSIC_CROSSFADE_SECONDS: f32 = 1.5;
+// music is turned down to this share of its volume under the pause menu
+pub const MUSIC_DUCK: f32 = 0.35;
+// sound from this far past the playfield edge plays at a quarter volume
+pub const SFX_FALLOFF_DISTANCE: f32 = 200.0;
+// every this many kills in a row in versus sends something over to the rival
+pub const VERSUS_GARBAGE_CHAIN: u32 = 3;
+pub const VERSUS_VOLLEY_BULLETS: usize = 5;
+pub const VERSUS_ROUNDS_TO_WIN: u32 = 2;
+// a score attack run notes the score this often
+pub const SCORE_ATTACK_SPLIT_SECONDS: u32 = 30;
+pub const NET_DEFAULT_PORT: u16 = 7777;
+// local inputs take effect this many ticks late, so they usually reach the other side in time
+pub const NET_INPUT_DELAY_TICKS: u32 = 2;
+// an online run waits rather than guess the other side's input further ahead than this
+pub const NET_MAX_PREDICTION_TICKS: u32 = 8;
+pub const NET_MAX_INPUTS_PER_PACKET: usize = 64;
+pub const NET_PACKET_BYTES: usize = 2048;
+pub const NET_HELLO_SECONDS: f32 = 0.25;
+pub const NET_TIMEOUT_SECONDS: f32 = 5.0;
+pub const MAX_ADDRESS_LENGTH: usize = 64;
+pub const TOAST_SECONDS: f32 = 4.0;
+pub const TOAST_LIMIT: usize = 4;
+pub const MAX_PARTICLES: usize = 800;
+pub const STAR_SCROLL_SPEED: f32 = 40.0;
+// each wave cleared scrolls the starfield this much faster, up to the cap
+pub const SCROLL_SPEED_PER_WAVE: f32 = 0.15;
+pub const SCROLL_SPEED_MAX_FACTOR: f32 = 2.5;
+// how quickly the scroll speed eases towards a new wave's speed
+pub const SCROLL_SPEED_EASE_RATE: f32 = 1.5;
+pub const BACKDROP_Z: f32 = -100.0;
+pub const SET_PIECE_Z: f32 = -40.0;
+// how far past the playfield edge a set piece starts and ends, enough for the largest sprite
+pub const SET_PIECE_MARGIN: f32 = 400.0;
+pub const LEVEL_FILES: [&str; 3] = [
+    "levels/01_outer_rim.level.ron",
+    "levels/02_red_expanse.level.ron",
+    "levels/03_graveyard.level.ron",
+];
 
-#[derive(Component)]
-pub struct Bullet;
+#[derive(Component, Clone)]
+pub struct Bullet {
+    // the player whose jet fired it
+    pub player: usize,
+}
 
 #[derive(Event, Default)]
-pub struct CollisionEvent;
+pub struct CollisionEvent {
+    pub position: Vec2,
+}
 
-#[derive(Resource, Deref)]
-pub struct CollisionSound(pub Handle<AudioSource>);
+#[derive(Event)]
+pub struct EnemyDestroyedEvent {
+    pub position: Vec2,
+    pub points: usize,
+    pub player: usize,
+}
+
+#[derive(Component, Clone)]
+pub struct EnemyBullet;
+
+#[derive(Event)]
+pub struct ShotFiredEvent;
+
+#[derive(Event)]
+pub struct PlayerHitEvent {
+    pub position: Vec2,
+    pub player: usize,
+}
+
+#[derive(Event)]
+pub struct GrazeEvent {
+    pub position: Vec2,
+    pub player: usize,
+}
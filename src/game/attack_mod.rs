@@ -0,0 +1,259 @@
+use bevy::{
+    app::{App, FixedPostUpdate, Plugin},
+    asset::AssetServer,
+    prelude::{
+        in_state, resource_equals, Commands, Condition, IntoSystemConfigs, NextState, OnEnter,
+        Query, Res, ResMut, Resource, Transform, With,
+    },
+};
+
+use crate::{
+    constants::{ENEMY_SPACE_SPRITE_NAME, FIXED_TICK_SECONDS, SCORE_ATTACK_SPLIT_SECONDS},
+    GameState,
+};
+
+use super::{
+    eneymy_mod::{get_enemy_bundle, random_enemy_position, EnemyKind},
+    gameplay_running,
+    net_mod::NetSession,
+    playfield_mod::Playfield,
+    replay_mod::FixedTick,
+    rng_mod::GameRng,
+    rollback_mod::RollbackApp,
+    tuning_mod::GameTuning,
+    Difficulty, GameMode, Score,
+};
+
+pub struct AttackPlugin;
+
+// The waves a time attack run has to clear, in order.
+pub const TIME_ATTACK_WAVES: &[&[EnemyKind]] = &[
+    &[EnemyKind::Scout, EnemyKind::Scout, EnemyKind::Scout],
+    &[EnemyKind::Fighter, EnemyKind::Scout, EnemyKind::Fighter],
+    &[
+        EnemyKind::Heavy,
+        EnemyKind::Scout,
+        EnemyKind::Scout,
+        EnemyKind::Scout,
+    ],
+    &[
+        EnemyKind::Fighter,
+        EnemyKind::Fighter,
+        EnemyKind::Heavy,
+        EnemyKind::Fighter,
+    ],
+    &[
+        EnemyKind::Heavy,
+        EnemyKind::Fighter,
+        EnemyKind::Heavy,
+        EnemyKind::Fighter,
+        EnemyKind::Heavy,
+    ],
+];
+
+// Where a run stood at some point, for the results screen.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Split {
+    pub ticks: u32,
+    pub score: usize,
+}
+
+// Progress of a score or time attack run.
+#[derive(Resource, Default, Clone, Debug)]
+pub struct AttackRun {
+    // time attack waves brought in so far
+    pub wave: usize,
+    // the end of each time attack wave, or every so often in score attack
+    pub splits: Vec<Split>,
+    // ticks the run took, once the clock ran out or the last wave went down
+    pub finished: Option<u32>,
+}
+
+fn seconds_to_ticks(seconds: u32) -> u32 {
+    (seconds as f64 / FIXED_TICK_SECONDS).round() as u32
+}
+
+// Ticks as m:ss.cc
+pub fn format_ticks(ticks: u32) -> String {
+    let hundredths = (ticks as f64 * FIXED_TICK_SECONDS * 100.).round() as u32;
+    format!(
+        "{}:{:02}.{:02}",
+        hundredths / 6000,
+        hundredths / 100 % 60,
+        hundredths % 100
+    )
+}
+
+fn reset_run(mut run: ResMut<AttackRun>) {
+    *run = AttackRun::default();
+}
+
+// Brings in the next wave once the last one is gone, and stops the clock
+// when there are none left.
+#[allow(clippy::too_many_arguments)]
+fn next_time_attack_wave(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    enemies: Query<(), With<EnemyKind>>,
+    mut run: ResMut<AttackRun>,
+    mut rng: ResMut<GameRng>,
+    playfield: Res<Playfield>,
+    difficulty: Res<Difficulty>,
+    tuning: Res<GameTuning>,
+    score: Res<Score>,
+    tick: Res<FixedTick>,
+) {
+    if run.finished.is_some() || !enemies.is_empty() {
+        return;
+    }
+    let ticks = tick.0 + 1;
+    if run.wave > 0 {
+        run.splits.push(Split {
+            ticks,
+            score: score.0,
+        });
+    }
+    let Some(wave) = TIME_ATTACK_WAVES.get(run.wave) else {
+        println!("time attack cleared in {}", format_ticks(ticks));
+        run.finished = Some(ticks);
+        return;
+    };
+    let lane = (-playfield.half_width(), playfield.half_width());
+    for kind in wave.iter() {
+        commands.spawn((
+            get_enemy_bundle(
+                asset_server.load(ENEMY_SPACE_SPRITE_NAME),
+                *kind,
+                *difficulty,
+                &tuning,
+            ),
+            Transform::from_translation(random_enemy_position(&mut rng, &playfield, lane)),
+        ));
+    }
+    run.wave += 1;
+}
+
+// Notes the score every so often and stops the clock at the mode's limit.
+fn score_attack_clock(
+    mode: Res<GameMode>,
+    mut run: ResMut<AttackRun>,
+    score: Res<Score>,
+    tick: Res<FixedTick>,
+) {
+    let Some(limit) = mode.time_limit_seconds() else {
+        return;
+    };
+    if run.finished.is_some() {
+        return;
+    }
+    let ticks = tick.0 + 1;
+    if ticks.is_multiple_of(seconds_to_ticks(SCORE_ATTACK_SPLIT_SECONDS)) {
+        run.splits.push(Split {
+            ticks,
+            score: score.0,
+        });
+    }
+    if ticks >= seconds_to_ticks(limit) {
+        println!("time up, {} points", score.0);
+        run.finished = Some(ticks);
+    }
+}
+
+fn end_attack(
+    run: Res<AttackRun>,
+    net: Option<Res<NetSession>>,
+    tick: Res<FixedTick>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    if run.finished.is_none() {
+        return;
+    }
+    // online only once no late input can undo it
+    if net.is_some_and(|net| !net.confirmed_through(tick.0 + 1)) {
+        return;
+    }
+    game_state.set(GameState::GameOver);
+}
+
+impl Plugin for AttackPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AttackRun>();
+        app.rollback_resource::<AttackRun>();
+        app.add_systems(OnEnter(GameState::Game), reset_run);
+        app.add_systems(
+            FixedPostUpdate,
+            (
+                next_time_attack_wave.run_if(resource_equals(GameMode::TimeAttack)),
+                score_attack_clock.run_if(
+                    resource_equals(GameMode::ScoreAttack2)
+                        .or(resource_equals(GameMode::ScoreAttack5)),
+                ),
+                end_attack,
+            )
+                .chain()
+                .run_if(in_state(GameState::Game).and(gameplay_running)),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        app::App,
+        prelude::{State, With},
+    };
+
+    use crate::{
+        game::{eneymy_mod::EnemyKind, replay_mod::FixedTick, GameMode},
+        test_support::{entities, step, step_until, TestApp},
+        GameState,
+    };
+
+    use super::{format_ticks, AttackRun, TIME_ATTACK_WAVES};
+
+    fn state(app: &App) -> GameState {
+        *app.world().resource::<State<GameState>>().get()
+    }
+
+    #[test]
+    fn time_attack_stops_the_clock_after_the_last_wave() {
+        let mut app = TestApp::default().mode(GameMode::TimeAttack).build();
+        for wave in TIME_ATTACK_WAVES {
+            let arrived = step_until(&mut app, 10, |app| {
+                entities::<With<EnemyKind>>(app).len() == wave.len()
+            });
+            assert!(arrived);
+            step(&mut app, 5);
+            for enemy in entities::<With<EnemyKind>>(&mut app) {
+                app.world_mut().despawn(enemy);
+            }
+        }
+        step(&mut app, 3);
+        assert_eq!(state(&app), GameState::GameOver);
+        let run = app.world().resource::<AttackRun>();
+        assert_eq!(run.splits.len(), TIME_ATTACK_WAVES.len());
+        let last = run.splits.last().unwrap().ticks;
+        assert_eq!(run.finished, Some(last));
+        assert!(run
+            .splits
+            .windows(2)
+            .all(|pair| pair[0].ticks < pair[1].ticks));
+    }
+
+    #[test]
+    fn score_attack_takes_splits_until_time_is_up() {
+        let mut app = TestApp::default().mode(GameMode::ScoreAttack2).build();
+        // skip ahead to just before the first split
+        app.world_mut().resource_mut::<FixedTick>().0 = 30 * 60 - 5;
+        step(&mut app, 10);
+        assert_eq!(app.world().resource::<AttackRun>().splits.len(), 1);
+        assert_eq!(state(&app), GameState::Game);
+
+        app.world_mut().resource_mut::<FixedTick>().0 = 120 * 60 - 5;
+        step(&mut app, 10);
+        assert_eq!(state(&app), GameState::GameOver);
+        let run = app.world().resource::<AttackRun>();
+        assert_eq!(run.finished, Some(120 * 60));
+        assert_eq!(format_ticks(run.finished.unwrap()), "2:00.00");
+    }
+}
@@ -0,0 +1,276 @@
+use bevy::{
+    app::{App, Plugin, Update},
+    asset::{AssetEvent, AssetServer, Assets, Handle},
+    color::Color,
+    image::Image,
+    math::{Vec2, Vec3},
+    prelude::{
+        default, Commands, Component, DetectChanges, Entity, EventReader, IntoSystemConfigs, Query,
+        Res, ResMut, Resource, Transform, With,
+    },
+    sprite::{Sprite, SpriteImageMode},
+    time::{Time, Timer, TimerMode},
+};
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::constants::{BACKDROP_Z, SET_PIECE_MARGIN, SET_PIECE_Z, STAR_SCROLL_SPEED};
+
+use super::{
+    level_mod::{CurrentLevel, Level, LevelHandles},
+    playfield_mod::Playfield,
+    rng_mod::{GameRng, RngStream},
+    space_point_plugin_mod::ScrollSpeed,
+};
+
+pub struct BackgroundPlugin;
+
+// One playfield-sized layer of a level backdrop, listed back to front in the level file.
+// Tiled layers repeat the image at its native size and scroll; untiled layers are
+// stretched over the playfield and stay put.
+#[derive(Deserialize, Clone)]
+pub struct BackgroundLayerDef {
+    pub image: String,
+    #[serde(default)]
+    pub scroll_speed: f32,
+    #[serde(default = "default_tiling")]
+    pub tiling: bool,
+    #[serde(default = "default_tint")]
+    pub tint: [f32; 4],
+}
+
+// A large sprite (planet, wreckage) that now and then drifts through behind the action.
+#[derive(Deserialize, Clone)]
+pub struct SetPieceDef {
+    pub image: String,
+    pub scroll_speed: f32,
+    // seconds between two appearances, picked at random in this range
+    pub interval: (f32, f32),
+    #[serde(default = "default_scale")]
+    pub scale: (f32, f32),
+    #[serde(default = "default_tint")]
+    pub tint: [f32; 4],
+}
+
+fn default_tiling() -> bool {
+    true
+}
+
+fn default_tint() -> [f32; 4] {
+    [1., 1., 1., 1.]
+}
+
+fn default_scale() -> (f32, f32) {
+    (1., 1.)
+}
+
+fn tint_color(tint: [f32; 4]) -> Color {
+    Color::srgba(tint[0], tint[1], tint[2], tint[3])
+}
+
+#[derive(Component)]
+struct BackdropLayer {
+    scroll_speed: f32,
+    tiling: bool,
+    offset: f32,
+}
+
+#[derive(Component)]
+struct SetPiece {
+    scroll_speed: f32,
+}
+
+#[derive(Resource, Default)]
+struct SetPieceSpawner {
+    pieces: Vec<(SetPieceDef, Timer)>,
+}
+
+fn random_interval(rng: &mut GameRng, def: &SetPieceDef) -> Timer {
+    let seconds = rng
+        .stream(RngStream::Backdrop)
+        .gen_range(def.interval.0..=def.interval.1);
+    Timer::from_seconds(seconds, TimerMode::Once)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn rebuild_backdrop(
+    mut commands: Commands,
+    mut level_events: EventReader<AssetEvent<Level>>,
+    current_level: Res<CurrentLevel>,
+    handles: Option<Res<LevelHandles>>,
+    levels: Res<Assets<Level>>,
+    asset_server: Res<AssetServer>,
+    mut spawner: ResMut<SetPieceSpawner>,
+    mut rng: ResMut<GameRng>,
+    layers: Query<Entity, With<BackdropLayer>>,
+    pieces: Query<Entity, With<SetPiece>>,
+) {
+    let Some(handles) = handles else {
+        return;
+    };
+    let Some(current_handle) = current_level.handle(&handles) else {
+        return;
+    };
+    let level_reloaded = level_events.read().any(|event| match event {
+        AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => {
+            *id == current_handle.id()
+        }
+        _ => false,
+    });
+    if !level_reloaded && !current_level.is_changed() {
+        return;
+    }
+    let Some(level) = levels.get(current_handle) else {
+        return;
+    };
+
+    println!("building backdrop for level {}", level.name);
+    for entity in layers.iter().chain(pieces.iter()) {
+        commands.entity(entity).despawn();
+    }
+    for (index, layer) in level.backdrop.iter().enumerate() {
+        commands.spawn((
+            BackdropLayer {
+                scroll_speed: layer.scroll_speed,
+                tiling: layer.tiling,
+                offset: 0.,
+            },
+            Sprite {
+                image: asset_server.load(&layer.image),
+                color: tint_color(layer.tint),
+                ..default()
+            },
+            Transform::from_xyz(0., 0., BACKDROP_Z + index as f32),
+        ));
+    }
+    spawner.pieces = level
+        .set_pieces
+        .iter()
+        .map(|def| (def.clone(), random_interval(&mut rng, def)))
+        .collect();
+}
+
+// Sizes each layer to cover the playfield once its image has loaded.
+fn fit_backdrop_layers(
+    mut layers: Query<(&BackdropLayer, &mut Sprite)>,
+    images: Res<Assets<Image>>,
+    playfield: Res<Playfield>,
+) {
+    let playfield_size = playfield.size();
+    for (layer, mut sprite) in &mut layers {
+        let size = if layer.tiling {
+            let Some(image) = images.get(&sprite.image) else {
+                continue;
+            };
+            // a whole number of tiles plus one spare row and column, so scrolling never shows a seam
+            let tile = image.size_f32();
+            ((playfield_size / tile).ceil() + Vec2::ONE) * tile
+        } else {
+            playfield_size
+        };
+        if sprite.custom_size != Some(size) {
+            sprite.custom_size = Some(size);
+            if layer.tiling {
+                sprite.image_mode = SpriteImageMode::Tiled {
+                    tile_x: true,
+                    tile_y: true,
+                    stretch_value: 1.,
+                };
+            }
+        }
+    }
+}
+
+fn scroll_backdrop_layers(
+    mut layers: Query<(&mut BackdropLayer, &mut Transform, &Sprite)>,
+    images: Res<Assets<Image>>,
+    time: Res<Time>,
+    scroll_speed: Res<ScrollSpeed>,
+) {
+    let speed_factor = scroll_speed.0 / STAR_SCROLL_SPEED;
+    for (mut layer, mut transform, sprite) in &mut layers {
+        if !layer.tiling {
+            continue;
+        }
+        let Some(image) = images.get(&sprite.image) else {
+            continue;
+        };
+        let tile_height = image.size_f32().y;
+        layer.offset =
+            (layer.offset + layer.scroll_speed * speed_factor * time.delta_secs()) % tile_height;
+        transform.translation.y = layer.offset - tile_height / 2.;
+    }
+}
+
+fn spawn_set_pieces(
+    mut commands: Commands,
+    mut spawner: ResMut<SetPieceSpawner>,
+    asset_server: Res<AssetServer>,
+    time: Res<Time>,
+    playfield: Res<Playfield>,
+    mut rng: ResMut<GameRng>,
+) {
+    let half = playfield.size() / 2.;
+    for (def, timer) in &mut spawner.pieces {
+        if !timer.tick(time.delta()).finished() {
+            continue;
+        }
+        let image: Handle<Image> = asset_server.load(&def.image);
+        commands.spawn((
+            SetPiece {
+                scroll_speed: def.scroll_speed,
+            },
+            Sprite {
+                image,
+                color: tint_color(def.tint),
+                ..default()
+            },
+            Transform {
+                translation: Vec3::new(
+                    rng.stream(RngStream::Backdrop).gen_range(-half.x..half.x),
+                    -half.y - SET_PIECE_MARGIN,
+                    SET_PIECE_Z,
+                ),
+                scale: Vec3::splat(
+                    rng.stream(RngStream::Backdrop)
+                        .gen_range(def.scale.0..=def.scale.1),
+                ),
+                ..default()
+            },
+        ));
+        *timer = random_interval(&mut rng, def);
+    }
+}
+
+fn move_set_pieces(
+    mut commands: Commands,
+    mut pieces: Query<(Entity, &SetPiece, &mut Transform)>,
+    time: Res<Time>,
+    scroll_speed: Res<ScrollSpeed>,
+    playfield: Res<Playfield>,
+) {
+    let speed_factor = scroll_speed.0 / STAR_SCROLL_SPEED;
+    for (entity, piece, mut transform) in &mut pieces {
+        transform.translation.y += piece.scroll_speed * speed_factor * time.delta_secs();
+        if transform.translation.y > playfield.half_height() + SET_PIECE_MARGIN {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+impl Plugin for BackgroundPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SetPieceSpawner>();
+        app.add_systems(
+            Update,
+            (
+                rebuild_backdrop,
+                fit_backdrop_layers,
+                scroll_backdrop_layers,
+                spawn_set_pieces,
+                move_set_pieces,
+            )
+                .chain(),
+        );
+    }
+}
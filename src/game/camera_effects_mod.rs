@@ -0,0 +1,438 @@
+use std::{fmt, fs, io::ErrorKind, path::PathBuf};
+
+use bevy::{
+    app::{App, Plugin, Startup, Update},
+    color::{Alpha, Color},
+    core_pipeline::core_2d::Camera2d,
+    math::Quat,
+    prelude::{
+        default, in_state, BackgroundColor, Commands, Component, DetectChanges, Event, EventReader,
+        EventWriter, GlobalZIndex, IntoSystemConfigs, Node, OnEnter, OnExit,
+        OrthographicProjection, PositionType, Query, Res, ResMut, Resource, Transform, Val, With,
+    },
+    time::{Real, Time, Virtual},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    constants::{CollisionEvent, EnemyDestroyedEvent, PlayerHitEvent, SETTINGS_FILE},
+    user_data::user_data_dir,
+    GameState,
+};
+
+use super::{
+    highscore_mod::backup_path, net_mod::NetSession, player_jet_mod::GameEntity,
+    toast_mod::ErrorToast,
+};
+
+const MAX_SHAKE_OFFSET: f32 = 24.0;
+const MAX_SHAKE_ANGLE: f32 = 0.05;
+const TRAUMA_DECAY_PER_SECOND: f32 = 1.5;
+const FLASH_FADE_PER_SECOND: f32 = 3.0;
+const ZOOM_RECOVERY_PER_SECOND: f32 = 0.4;
+
+pub struct CameraEffectsPlugin;
+
+// Accessibility setting. `intensity` scales every effect (0 turns them all
+// off) and `flashes` can be disabled on its own for photosensitive players.
+// Changes are saved to `path`, without one they only last the session, as
+// in headless runs and tests.
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct CameraEffectsSettings {
+    pub intensity: f32,
+    pub flashes: bool,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl Default for CameraEffectsSettings {
+    fn default() -> Self {
+        CameraEffectsSettings {
+            intensity: 1.0,
+            flashes: true,
+            path: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(std::io::Error),
+    Write(ron::Error),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Io(error) => write!(f, "could not access settings file: {error}"),
+            SettingsError::Write(error) => write!(f, "could not write settings file: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+impl From<std::io::Error> for SettingsError {
+    fn from(error: std::io::Error) -> Self {
+        SettingsError::Io(error)
+    }
+}
+
+impl From<ron::Error> for SettingsError {
+    fn from(error: ron::Error) -> Self {
+        SettingsError::Write(error)
+    }
+}
+
+impl CameraEffectsSettings {
+    // Full, then reduced, then off.
+    pub fn next_intensity(&mut self) {
+        self.intensity = if self.intensity > 0.5 {
+            0.5
+        } else if self.intensity > 0.0 {
+            0.0
+        } else {
+            1.0
+        };
+    }
+
+    pub fn intensity_name(&self) -> &'static str {
+        if self.intensity >= 1.0 {
+            "Full"
+        } else if self.intensity > 0.0 {
+            "Reduced"
+        } else {
+            "Off"
+        }
+    }
+
+    // Reads the settings at `path`. A missing file gives the defaults. A file
+    // that can't be parsed is moved aside, like a corrupt high score table, so
+    // the next save doesn't overwrite it, and the reason comes back for a toast.
+    pub fn load_or_backup(path: PathBuf) -> (CameraEffectsSettings, Option<String>) {
+        let error = match fs::read(&path) {
+            Ok(bytes) => match ron::de::from_bytes::<CameraEffectsSettings>(&bytes) {
+                Ok(settings) => {
+                    let settings = CameraEffectsSettings {
+                        path: Some(path),
+                        ..settings
+                    };
+                    return (settings, None);
+                }
+                Err(error) => error,
+            },
+            Err(error) if error.kind() == ErrorKind::NotFound => {
+                let settings = CameraEffectsSettings {
+                    path: Some(path),
+                    ..Default::default()
+                };
+                return (settings, None);
+            }
+            Err(error) => {
+                // unreadable but maybe fine, keep it and don't save over it
+                println!("{}, settings won't be saved", SettingsError::Io(error));
+                return (CameraEffectsSettings::default(), None);
+            }
+        };
+
+        let backup = backup_path(&path);
+        match fs::rename(&path, &backup) {
+            Ok(()) => {
+                println!(
+                    "could not parse settings file: {error}, moved it to {}",
+                    backup.display()
+                );
+                let settings = CameraEffectsSettings {
+                    path: Some(path),
+                    ..Default::default()
+                };
+                let notice = format!("{SETTINGS_FILE} was corrupt, moved it aside");
+                (settings, Some(notice))
+            }
+            Err(rename_error) => {
+                println!("could not parse settings file: {error} and it could not be moved aside ({rename_error}), settings won't be saved");
+                let notice = format!("{SETTINGS_FILE} was corrupt, settings won't be saved");
+                (CameraEffectsSettings::default(), Some(notice))
+            }
+        }
+    }
+
+    // Writes to a temporary file first so a crash mid-write can't corrupt it.
+    pub fn save(&self) -> Result<(), SettingsError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let temporary = path.with_extension("ron.tmp");
+        let pretty = ron::ser::PrettyConfig::default();
+        fs::write(&temporary, ron::ser::to_string_pretty(self, pretty)?)?;
+        fs::rename(&temporary, path)?;
+        Ok(())
+    }
+}
+
+#[derive(Event, Clone, Copy)]
+pub enum CameraEffect {
+    Shake(f32),
+    HitStop(f32),
+    Flash(Color),
+    ZoomPunch(f32),
+}
+
+#[derive(Resource, Default)]
+struct CameraEffectsState {
+    trauma: f32,
+    shake_time: f32,
+    hit_stop_remaining: f32,
+    paused_by_hit_stop: bool,
+    zoom: f32,
+}
+
+#[derive(Component)]
+struct FlashOverlay;
+
+fn setup_flash_overlay(mut commands: Commands) {
+    commands.spawn((
+        GameEntity,
+        FlashOverlay,
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            ..default()
+        },
+        BackgroundColor(Color::NONE),
+        GlobalZIndex(i32::MAX),
+    ));
+}
+
+fn trigger_effects_from_gameplay(
+    mut collision_events: EventReader<CollisionEvent>,
+    mut destroyed_events: EventReader<EnemyDestroyedEvent>,
+    mut hit_events: EventReader<PlayerHitEvent>,
+    mut effects: EventWriter<CameraEffect>,
+    net: Option<Res<NetSession>>,
+) {
+    for _ in collision_events.read() {
+        effects.send(CameraEffect::Shake(0.08));
+    }
+    for _ in destroyed_events.read() {
+        effects.send(CameraEffect::Shake(0.5));
+        // online the other player would have to wait out the freeze
+        if net.is_none() {
+            effects.send(CameraEffect::HitStop(0.06));
+        }
+        effects.send(CameraEffect::Flash(Color::srgba(1.0, 0.9, 0.7, 0.35)));
+        effects.send(CameraEffect::ZoomPunch(0.04));
+    }
+    for _ in hit_events.read() {
+        effects.send(CameraEffect::Shake(0.8));
+        effects.send(CameraEffect::Flash(Color::srgba(1.0, 0.2, 0.2, 0.45)));
+    }
+}
+
+fn apply_effects(
+    mut effect_events: EventReader<CameraEffect>,
+    settings: Res<CameraEffectsSettings>,
+    mut state: ResMut<CameraEffectsState>,
+    mut overlay: Query<&mut BackgroundColor, With<FlashOverlay>>,
+) {
+    let intensity = settings.intensity.clamp(0.0, 1.0);
+    for effect in effect_events.read() {
+        if intensity == 0.0 {
+            continue;
+        }
+        match *effect {
+            CameraEffect::Shake(amount) => {
+                state.trauma = (state.trauma + amount * intensity).min(1.0);
+            }
+            CameraEffect::HitStop(seconds) => {
+                state.hit_stop_remaining = state.hit_stop_remaining.max(seconds * intensity);
+            }
+            CameraEffect::Flash(color) => {
+                if !settings.flashes {
+                    continue;
+                }
+                for mut background in &mut overlay {
+                    background.0 = color.with_alpha(color.alpha() * intensity);
+                }
+            }
+            CameraEffect::ZoomPunch(amount) => {
+                state.zoom = state.zoom.max(amount * intensity);
+            }
+        }
+    }
+}
+
+fn save_settings(settings: Res<CameraEffectsSettings>, mut toasts: EventWriter<ErrorToast>) {
+    if !settings.is_changed() || settings.is_added() {
+        return;
+    }
+    if let Err(error) = settings.save() {
+        println!("{error}");
+        toasts.send(ErrorToast(error.to_string()));
+    }
+}
+
+// Runs on real time so the camera keeps settling while virtual time is frozen by a hit-stop.
+fn update_camera_effects(
+    real_time: Res<Time<Real>>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    mut state: ResMut<CameraEffectsState>,
+    mut camera: Query<(&mut Transform, &mut OrthographicProjection), With<Camera2d>>,
+    mut overlay: Query<&mut BackgroundColor, With<FlashOverlay>>,
+) {
+    let delta = real_time.delta_secs();
+
+    if state.hit_stop_remaining > 0.0 {
+        state.hit_stop_remaining -= delta;
+        if !virtual_time.is_paused() {
+            virtual_time.pause();
+            state.paused_by_hit_stop = true;
+        }
+    } else if state.paused_by_hit_stop {
+        virtual_time.unpause();
+        state.paused_by_hit_stop = false;
+    }
+
+    state.shake_time += delta;
+    state.trauma = (state.trauma - TRAUMA_DECAY_PER_SECOND * delta).max(0.0);
+    state.zoom = (state.zoom - ZOOM_RECOVERY_PER_SECOND * delta).max(0.0);
+
+    // trauma is squared so small hits barely move the camera while kills shake it hard
+    let shake = state.trauma * state.trauma;
+    let t = state.shake_time;
+    for (mut transform, mut projection) in &mut camera {
+        transform.translation.x = MAX_SHAKE_OFFSET * shake * (t * 37.0).sin() * (t * 11.0).cos();
+        transform.translation.y = MAX_SHAKE_OFFSET * shake * (t * 41.0).cos() * (t * 13.0).sin();
+        transform.rotation = Quat::from_rotation_z(MAX_SHAKE_ANGLE * shake * (t * 29.0).sin());
+        projection.scale = 1.0 - state.zoom;
+    }
+
+    for mut background in &mut overlay {
+        let alpha = (background.0.alpha() - FLASH_FADE_PER_SECOND * delta).max(0.0);
+        background.0.set_alpha(alpha);
+    }
+}
+
+fn reset_camera_effects(
+    mut virtual_time: ResMut<Time<Virtual>>,
+    mut state: ResMut<CameraEffectsState>,
+    mut camera: Query<(&mut Transform, &mut OrthographicProjection), With<Camera2d>>,
+) {
+    if state.paused_by_hit_stop {
+        virtual_time.unpause();
+    }
+    *state = CameraEffectsState::default();
+    for (mut transform, mut projection) in &mut camera {
+        transform.translation.x = 0.0;
+        transform.translation.y = 0.0;
+        transform.rotation = Quat::IDENTITY;
+        projection.scale = 1.0;
+    }
+}
+
+// Why the settings file was set aside while loading, shown once the toasts are up.
+#[derive(Resource)]
+struct SettingsNotice(String);
+
+fn report_settings_notice(
+    mut commands: Commands,
+    notice: Option<Res<SettingsNotice>>,
+    mut toasts: EventWriter<ErrorToast>,
+) {
+    if let Some(notice) = notice {
+        toasts.send(ErrorToast(notice.0.clone()));
+        commands.remove_resource::<SettingsNotice>();
+    }
+}
+
+impl Plugin for CameraEffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<CameraEffect>();
+        if !app.world().contains_resource::<CameraEffectsSettings>() {
+            let (settings, notice) =
+                CameraEffectsSettings::load_or_backup(user_data_dir().join(SETTINGS_FILE));
+            app.insert_resource(settings);
+            if let Some(notice) = notice {
+                app.insert_resource(SettingsNotice(notice));
+            }
+        }
+        app.add_systems(Startup, report_settings_notice);
+        app.init_resource::<CameraEffectsState>();
+        app.add_systems(OnEnter(GameState::Game), setup_flash_overlay);
+        app.add_systems(
+            Update,
+            (
+                trigger_effects_from_gameplay,
+                apply_effects,
+                update_camera_effects,
+            )
+                .chain()
+                .run_if(in_state(GameState::Game)),
+        );
+        app.add_systems(OnExit(GameState::Game), reset_camera_effects);
+        app.add_systems(Update, save_settings);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::CameraEffectsSettings;
+
+    #[test]
+    fn settings_are_saved_and_read_back() {
+        let dir = std::env::temp_dir().join(format!("space_fight_settings_{}", std::process::id()));
+        let path = dir.join("settings.ron");
+        let (mut settings, notice) = CameraEffectsSettings::load_or_backup(path.clone());
+        assert!(notice.is_none());
+        assert_eq!(settings.intensity_name(), "Full");
+        settings.next_intensity();
+        settings.flashes = false;
+        settings.save().unwrap();
+
+        let (reloaded, _) = CameraEffectsSettings::load_or_backup(path);
+        assert_eq!(reloaded, settings);
+        assert_eq!(reloaded.intensity_name(), "Reduced");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn corrupt_settings_are_backed_up_instead_of_overwritten() {
+        let dir = std::env::temp_dir().join(format!(
+            "space_fight_corrupt_settings_{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("settings.ron");
+        fs::write(&path, "not settings").unwrap();
+
+        let (mut settings, notice) = CameraEffectsSettings::load_or_backup(path.clone());
+        assert!(notice.is_some());
+        assert_eq!(settings.intensity_name(), "Full");
+        assert!(settings.flashes);
+        let backups: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().contains("corrupt"))
+            .collect();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(
+            fs::read_to_string(backups[0].path()).unwrap(),
+            "not settings"
+        );
+
+        settings.flashes = false;
+        settings.save().unwrap();
+        let (reloaded, notice) = CameraEffectsSettings::load_or_backup(path);
+        assert!(notice.is_none());
+        assert!(!reloaded.flashes);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
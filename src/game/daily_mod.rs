@@ -0,0 +1,220 @@
+use bevy::{
+    app::{App, Plugin},
+    prelude::{Commands, OnEnter, OnExit, Res, ResMut, Resource},
+};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::GameState;
+
+use super::{
+    highscore_mod::{today, DailyResult, HighScores},
+    net_mod::NetSession,
+    replay_mod::ReplayPlayback,
+    rng_mod::{fnv1a, GameRng},
+    toast_mod::ErrorToast,
+    Difficulty, GameMode, PlayerCount, Score,
+};
+
+pub struct DailyPlugin;
+
+// A twist on the rules for a day's challenge.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Modifier {
+    // enemy bullets fly twice as fast
+    FastEnemyBullets,
+    NoBombs,
+    OneLife,
+}
+
+impl Modifier {
+    const ALL: [Modifier; 3] = [
+        Modifier::FastEnemyBullets,
+        Modifier::NoBombs,
+        Modifier::OneLife,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Modifier::FastEnemyBullets => "Fast enemy bullets",
+            Modifier::NoBombs => "No bombs",
+            Modifier::OneLife => "One life",
+        }
+    }
+}
+
+// The modifiers the current run is played with, none outside a daily
+// challenge. They follow from the mode and seed alone, so replays and
+// online peers end up with the same ones.
+#[derive(Resource, Default, Clone, Debug, PartialEq)]
+pub struct DailyModifiers(pub Vec<Modifier>);
+
+impl DailyModifiers {
+    pub fn roll(mode: GameMode, seed: u64) -> Self {
+        if mode != GameMode::Daily {
+            return DailyModifiers::default();
+        }
+        let mut rng = StdRng::seed_from_u64(seed);
+        let count = rng.gen_range(1..=2);
+        DailyModifiers(
+            Modifier::ALL
+                .choose_multiple(&mut rng, count)
+                .copied()
+                .collect(),
+        )
+    }
+
+    pub fn has(&self, modifier: Modifier) -> bool {
+        self.0.contains(&modifier)
+    }
+
+    pub fn enemy_bullet_speed(&self, speed: f32) -> f32 {
+        if self.has(Modifier::FastEnemyBullets) {
+            speed * 2.
+        } else {
+            speed
+        }
+    }
+
+    pub fn names(&self) -> String {
+        let names: Vec<&str> = self.0.iter().map(|modifier| modifier.name()).collect();
+        names.join(", ")
+    }
+}
+
+// Date of the daily challenge this run is the scored attempt at. Any other
+// run on the daily seed is practice.
+#[derive(Resource, Default)]
+pub struct DailyAttempt(pub Option<String>);
+
+// Hashed from the date, so everyone gets the same seed on the same day.
+pub fn daily_seed(date: &str) -> u64 {
+    fnv1a(date.as_bytes())
+}
+
+fn save(commands: &mut Commands, high_scores: &HighScores) {
+    if let Err(error) = high_scores.save() {
+        println!("{error}");
+        commands.send_event(ErrorToast(error.to_string()));
+    }
+}
+
+// The first run on today's seed counts. It goes on record straight away, so
+// quitting part way through still uses up the day's attempt.
+#[allow(clippy::too_many_arguments)]
+pub fn start_attempt(
+    mut commands: Commands,
+    mode: Res<GameMode>,
+    rng: Res<GameRng>,
+    mut modifiers: ResMut<DailyModifiers>,
+    playback: Option<Res<ReplayPlayback>>,
+    net: Option<Res<NetSession>>,
+    difficulty: Res<Difficulty>,
+    players: Res<PlayerCount>,
+    mut high_scores: ResMut<HighScores>,
+    mut attempt: ResMut<DailyAttempt>,
+) {
+    *modifiers = DailyModifiers::roll(*mode, rng.seed());
+    attempt.0 = None;
+    if *mode != GameMode::Daily || playback.is_some() || net.is_some() {
+        return;
+    }
+    let date = today();
+    if rng.seed() != daily_seed(&date) || high_scores.daily(&date).is_some() {
+        println!("daily challenge practice run");
+        return;
+    }
+    println!("daily challenge {date}: {}", modifiers.names());
+    high_scores.record_daily(DailyResult {
+        date: date.clone(),
+        seed: rng.seed(),
+        modifiers: modifiers.0.clone(),
+        score: 0,
+        difficulty: *difficulty,
+        players: players.0,
+    });
+    save(&mut commands, &high_scores);
+    attempt.0 = Some(date);
+}
+
+fn finish_attempt(
+    mut commands: Commands,
+    attempt: Res<DailyAttempt>,
+    score: Res<Score>,
+    mut high_scores: ResMut<HighScores>,
+) {
+    let Some(date) = &attempt.0 else {
+        return;
+    };
+    let Some(mut result) = high_scores.daily(date).cloned() else {
+        return;
+    };
+    result.score = score.0;
+    high_scores.record_daily(result);
+    save(&mut commands, &high_scores);
+}
+
+impl Plugin for DailyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DailyModifiers>();
+        app.init_resource::<DailyAttempt>();
+        app.add_systems(OnEnter(GameState::Game), start_attempt);
+        app.add_systems(OnExit(GameState::Game), finish_attempt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::NextState;
+
+    use crate::{
+        game::{highscore_mod::today, highscore_mod::HighScores, GameMode, Score},
+        test_support::{step, TestApp},
+        GameState,
+    };
+
+    use super::{daily_seed, DailyAttempt, DailyModifiers};
+
+    #[test]
+    fn the_date_picks_the_seed_and_the_seed_picks_the_modifiers() {
+        let seed = daily_seed("2026-10-19");
+        assert_eq!(seed, daily_seed("2026-10-19"));
+        assert_ne!(seed, daily_seed("2026-10-20"));
+        let modifiers = DailyModifiers::roll(GameMode::Daily, seed);
+        assert!(!modifiers.0.is_empty());
+        assert_eq!(modifiers, DailyModifiers::roll(GameMode::Daily, seed));
+        assert!(DailyModifiers::roll(GameMode::Campaign, seed).0.is_empty());
+    }
+
+    #[test]
+    fn only_the_first_run_of_the_day_is_scored() {
+        let mut app = TestApp::default()
+            .mode(GameMode::Daily)
+            .players(2)
+            .seed(daily_seed(&today()))
+            .build();
+        for (run, score) in [(0, 1234), (1, 99999)] {
+            let scored = app.world().resource::<DailyAttempt>().0.is_some();
+            assert_eq!(scored, run == 0);
+            app.world_mut().resource_mut::<Score>().0 = score;
+            app.world_mut()
+                .resource_mut::<NextState<GameState>>()
+                .set(GameState::GameOver);
+            step(&mut app, 2);
+            app.world_mut()
+                .resource_mut::<NextState<GameState>>()
+                .set(GameState::Game);
+            step(&mut app, 2);
+        }
+        let result = app
+            .world()
+            .resource::<HighScores>()
+            .daily(&today())
+            .cloned()
+            .unwrap();
+        assert_eq!(result.score, 1234);
+        assert_eq!(result.seed, daily_seed(&today()));
+        assert_eq!(result.modifiers, app.world().resource::<DailyModifiers>().0);
+        assert_eq!(result.played_on(), "Normal, 2P");
+    }
+}
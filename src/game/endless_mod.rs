@@ -0,0 +1,130 @@
+use bevy::{
+    app::{App, FixedPostUpdate, Plugin},
+    asset::AssetServer,
+    prelude::{
+        in_state, resource_equals, Commands, Condition, IntoSystemConfigs, OnEnter, Query, Res,
+        ResMut, Resource, Transform, With,
+    },
+    time::Time,
+};
+use rand::Rng;
+
+use crate::{constants::ENEMY_SPACE_SPRITE_NAME, GameState};
+
+use super::{
+    eneymy_mod::{ramped_enemy_bundle, random_enemy_position, EnemyKind},
+    gameplay_running,
+    playfield_mod::Playfield,
+    replay_mod::FixedTick,
+    rng_mod::{GameRng, RngStream},
+    rollback_mod::RollbackApp,
+    tuning_mod::{EnemyMix, GameTuning},
+    Difficulty, GameMode,
+};
+
+pub struct EndlessPlugin;
+
+// Time until the next enemy joins an endless run.
+#[derive(Resource, Default, Clone)]
+struct EndlessSpawner {
+    seconds_left: f32,
+}
+
+fn reset_spawner(mut spawner: ResMut<EndlessSpawner>) {
+    // the first enemy turns up straight away
+    spawner.seconds_left = 0.;
+}
+
+fn pick_kind(rng: &mut GameRng, mix: EnemyMix) -> EnemyKind {
+    let total = mix.scout + mix.fighter + mix.heavy;
+    let roll = rng.stream(RngStream::EnemySpawn).gen_range(0.0..total);
+    if roll < mix.scout {
+        EnemyKind::Scout
+    } else if roll < mix.scout + mix.fighter {
+        EnemyKind::Fighter
+    } else {
+        EnemyKind::Heavy
+    }
+}
+
+// Brings in enemies on the clock of the tuning's endless curve, as long as
+// there is room for another one. An empty screen doesn't wait for the clock.
+#[allow(clippy::too_many_arguments)]
+fn spawn_endless_enemies(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut spawner: ResMut<EndlessSpawner>,
+    enemies: Query<(), With<EnemyKind>>,
+    mut rng: ResMut<GameRng>,
+    playfield: Res<Playfield>,
+    difficulty: Res<Difficulty>,
+    tuning: Res<GameTuning>,
+    tick: Res<FixedTick>,
+    time: Res<Time>,
+) {
+    spawner.seconds_left -= time.delta_secs();
+    if spawner.seconds_left > 0. && !enemies.is_empty() {
+        return;
+    }
+    let step = tuning.endless_at(tick.seconds());
+    spawner.seconds_left = step.spawn_seconds;
+    if enemies.iter().count() >= step.max_enemies as usize {
+        return;
+    }
+    let kind = pick_kind(&mut rng, step.mix);
+    let lane = (-playfield.half_width(), playfield.half_width());
+    commands.spawn((
+        ramped_enemy_bundle(
+            asset_server.load(ENEMY_SPACE_SPRITE_NAME),
+            kind,
+            *difficulty,
+            &tuning,
+            step.health,
+            step.fire_rate,
+        ),
+        Transform::from_translation(random_enemy_position(&mut rng, &playfield, lane)),
+    ));
+}
+
+impl Plugin for EndlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EndlessSpawner>();
+        app.rollback_resource::<EndlessSpawner>();
+        app.add_systems(OnEnter(GameState::Game), reset_spawner);
+        app.add_systems(
+            FixedPostUpdate,
+            spawn_endless_enemies.run_if(
+                in_state(GameState::Game)
+                    .and(resource_equals(GameMode::Endless))
+                    .and(gameplay_running),
+            ),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::With;
+
+    use crate::{
+        game::{eneymy_mod::EnemyKind, tuning_mod::GameTuning, GameMode},
+        test_support::{entities, step_until, TestApp},
+    };
+
+    #[test]
+    fn enemies_keep_coming_up_to_the_curve_limit() {
+        let mut app = TestApp::default().mode(GameMode::Endless).build();
+        let limit = app.world().resource::<GameTuning>().endless[0].max_enemies as usize;
+        let mut most = 0;
+        let filled = step_until(&mut app, 600, |app| {
+            let count = entities::<With<EnemyKind>>(app).len();
+            most = most.max(count);
+            count == limit
+        });
+        assert!(filled, "only {most} enemies turned up");
+        let overfilled = step_until(&mut app, 300, |app| {
+            entities::<With<EnemyKind>>(app).len() > limit
+        });
+        assert!(!overfilled);
+    }
+}
@@ -1,45 +1,114 @@
 use std::usize;
 
 use bevy::{
-    app::{App, Plugin, Startup, Update},
+    app::{App, FixedUpdate, Plugin, Startup},
     asset::{AssetServer, Assets, Handle},
     color::Color,
     image,
-    math::{
-        bounding::{Aabb2d, BoundingCircle},
-        Vec2, Vec3,
-    },
+    math::Vec3,
     prelude::{
-        default, in_state, Bundle, Circle, Commands, Component, Deref, Entity, EventWriter, Image,
-        IntoSystemConfigs, Mesh, Mesh2d, OnEnter, Query, Rectangle, Res, ResMut, Single, Text,
-        TextUiWriter, Transform, With,
+        default, in_state, Bundle, Circle, Commands, Component, Condition, Deref, Entity,
+        EventWriter, Has, Image, IntoSystemConfigs, Mesh, Mesh2d, OnEnter, Query, Rectangle, Res,
+        ResMut, Resource, Transform, With,
     },
+    render::render_resource::TextureFormat,
     sprite::{ColorMaterial, MeshMaterial2d, Sprite},
     text::{Text2dWriter, TextSpan},
-    window::{PrimaryWindow, Window},
+    time::{Time, Timer, TimerMode},
 };
-use rand::{thread_rng, Rng};
+use rand::Rng;
 
 use crate::{
     constants::{
-        Bullet, CollisionEvent, ENEMY_OBJECT_SCALE, ENEMY_SPACE_SPRITE_NAME, ENEMY_SPAWN_HEALTH,
-        ENEMY_SQUARE_BOX_LENGTH,
+        Bullet, CollisionEvent, EnemyBullet, EnemyDestroyedEvent, ENEMY_BULLET_RADIUS,
+        ENEMY_OBJECT_SCALE, ENEMY_SPACE_SPRITE_NAME, ENEMY_SQUARE_BOX_LENGTH, MAX_PLAYERS,
     },
-    utils::ball_collision,
     GameState,
 };
 
 use super::{
-    player_jet_mod::{GameEntity, Jet},
-    LevelText, Score,
+    daily_mod::DailyModifiers,
+    gameplay_running,
+    player_jet_mod::GameEntity,
+    playfield_mod::Playfield,
+    rng_mod::{GameRng, RngStream},
+    rollback_mod::{Rollback, RollbackApp},
+    tuning_mod::GameTuning,
+    versus_mod::{lane, lane_owner},
+    Difficulty, GameMode,
 };
 
 pub struct EnemyPlugin;
 
-#[derive(Component)]
+#[derive(Component, Clone)]
 struct Enemy;
 
-#[derive(Component, Deref)]
+// What an enemy is worth and how hard it fights back.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EnemyKind {
+    Scout,
+    Fighter,
+    Heavy,
+}
+
+impl EnemyKind {
+    pub fn points(self) -> usize {
+        match self {
+            EnemyKind::Scout => 50,
+            EnemyKind::Fighter => 100,
+            EnemyKind::Heavy => 250,
+        }
+    }
+
+    fn health(self, difficulty: Difficulty, tuning: &GameTuning) -> i32 {
+        let health = difficulty.enemy_health(tuning.enemy_health);
+        match self {
+            EnemyKind::Scout => (health / 2).max(1),
+            EnemyKind::Fighter => health,
+            EnemyKind::Heavy => health * 2,
+        }
+    }
+
+    fn fire_seconds(self, tuning: &GameTuning) -> f32 {
+        match self {
+            EnemyKind::Scout => tuning.enemy_fire_seconds.scout,
+            EnemyKind::Fighter => tuning.enemy_fire_seconds.fighter,
+            EnemyKind::Heavy => tuning.enemy_fire_seconds.heavy,
+        }
+    }
+
+    fn tint(self) -> Color {
+        match self {
+            EnemyKind::Scout => Color::srgb(0.7, 1.0, 0.7),
+            EnemyKind::Fighter => Color::WHITE,
+            EnemyKind::Heavy => Color::srgb(1.0, 0.6, 0.4),
+        }
+    }
+
+    pub fn random(rng: &mut GameRng) -> Self {
+        match rng.stream(RngStream::EnemySpawn).gen_range(0..20) {
+            0..10 => EnemyKind::Scout,
+            10..17 => EnemyKind::Fighter,
+            _ => EnemyKind::Heavy,
+        }
+    }
+}
+
+#[derive(Component, Clone)]
+struct EnemyGun(Timer);
+
+// Sent over by the rival in versus. It isn't replaced when it dies.
+#[derive(Component, Clone)]
+pub struct GarbageEnemy;
+
+// One mesh and material shared by every enemy bullet.
+#[derive(Resource)]
+pub struct EnemyBulletHandles {
+    mesh: Handle<Mesh>,
+    material: Handle<ColorMaterial>,
+}
+
+#[derive(Component, Deref, Clone)]
 struct XP(i32);
 
 #[derive(Bundle)]
@@ -48,143 +117,152 @@ struct EnemyObjectBundle {
     sprite: Sprite,
 }
 
+// Somewhere in the top half of the lane running from `left` to `right`.
+pub fn random_enemy_position(
+    rng: &mut GameRng,
+    playfield: &Playfield,
+    (left, right): (f32, f32),
+) -> Vec3 {
+    let rng = rng.stream(RngStream::EnemySpawn);
+    let x = rng
+        .gen_range(left + (ENEMY_SQUARE_BOX_LENGTH / 2.0)..right - (ENEMY_SQUARE_BOX_LENGTH / 2.0));
+    let y = rng.gen_range(0.0..playfield.half_height() - (ENEMY_SQUARE_BOX_LENGTH / 2.0));
+    Vec3::new(x, y, 0.)
+}
+
 fn create_space_enemy_objects(
     asset_server: Res<AssetServer>,
     mut commands: Commands,
-    window_query: Query<&Window, With<PrimaryWindow>>,
+    playfield: Res<Playfield>,
+    mut rng: ResMut<GameRng>,
+    difficulty: Res<Difficulty>,
+    tuning: Res<GameTuning>,
+    mode: Res<GameMode>,
 ) {
-    let window = window_query.get_single().unwrap();
-    let window_width = window.width();
-    let window_height = window.height();
-
-    let width = window_width;
-    let height = window_height;
-    let mut rng = thread_rng();
-    let x = rng.gen_range(
-        (-1. * width / 2.) + (ENEMY_SQUARE_BOX_LENGTH / 2.0)
-            ..(width / 2.) - (ENEMY_SQUARE_BOX_LENGTH / 2.0),
-    );
-    let y = rng.gen_range(0.0..(height / 2.) - (ENEMY_SQUARE_BOX_LENGTH / 2.0));
-    commands.spawn((
-        get_enemy_bundle(
-            asset_server.load(ENEMY_SPACE_SPRITE_NAME),
-            window_width,
-            window_height,
-        ),
-        Transform {
-            translation: Vec3::new(x, y, 0.),
-            scale: ENEMY_OBJECT_SCALE.extend(1.),
-            ..default()
-        },
-    ));
+    if !mode.keeps_one_enemy_up() {
+        return;
+    }
+    // in versus each player starts with an enemy of their own
+    let lanes = if *mode == GameMode::Versus {
+        MAX_PLAYERS
+    } else {
+        1
+    };
+    for player in 0..lanes {
+        let kind = EnemyKind::random(&mut rng);
+        commands.spawn((
+            get_enemy_bundle(
+                asset_server.load(ENEMY_SPACE_SPRITE_NAME),
+                kind,
+                *difficulty,
+                &tuning,
+            ),
+            Transform {
+                translation: random_enemy_position(
+                    &mut rng,
+                    &playfield,
+                    lane(*mode, &playfield, player),
+                ),
+                scale: ENEMY_OBJECT_SCALE.extend(1.),
+                ..default()
+            },
+        ));
+    }
 }
 
-fn get_enemy_bundle(image_handle: Handle<Image>, width: f32, height: f32) -> impl Bundle {
-    let mut rng = thread_rng();
-    let x = rng.gen_range(
-        (-1. * width / 2.) + (ENEMY_SQUARE_BOX_LENGTH / 2.0)
-            ..(width / 2.) - (ENEMY_SQUARE_BOX_LENGTH / 2.0),
-    );
-    let y = rng.gen_range(0.0..(height / 2.) - (ENEMY_SQUARE_BOX_LENGTH / 2.0));
+pub fn get_enemy_bundle(
+    image_handle: Handle<Image>,
+    kind: EnemyKind,
+    difficulty: Difficulty,
+    tuning: &GameTuning,
+) -> impl Bundle {
+    ramped_enemy_bundle(image_handle, kind, difficulty, tuning, 1., 1.)
+}
+
+// An enemy with its health and rate of fire multiplied, for endless mode.
+pub fn ramped_enemy_bundle(
+    image_handle: Handle<Image>,
+    kind: EnemyKind,
+    difficulty: Difficulty,
+    tuning: &GameTuning,
+    health: f32,
+    fire_rate: f32,
+) -> impl Bundle {
+    let health = (kind.health(difficulty, tuning) as f32 * health).round() as i32;
     return (
         GameEntity,
+        Rollback,
         Enemy,
+        kind,
+        EnemyGun(Timer::from_seconds(
+            kind.fire_seconds(tuning) / fire_rate,
+            TimerMode::Repeating,
+        )),
         EnemyObjectBundle {
-            xp: XP(ENEMY_SPAWN_HEALTH),
-            // sprite: SpriteBundle {
-            //     texture: image_handle,
-            //     sprite: Sprite {
-            //         custom_size: Some(Vec2::splat(ENEMY_SQUARE_BOX_LENGTH)),
-            //         ..default()
-            //     },
-            //     transform: Transform {
-            //         translation: Vec3::new(x, y, 0.),
-            //         scale: Vec3::new(0.5, 0.5, 1.0),
-            //         ..default()
-            //     },
-            //     ..default()
-            // },
+            xp: XP(health.max(1)),
             sprite: Sprite {
                 image: image_handle,
-                // custom_size: Some(Vec2::splat(ENEMY_SQUARE_BOX_LENGTH)),
+                color: kind.tint(),
                 ..default()
             },
         },
     );
 }
 
-fn check_for_collision(
-    mut enemy_object: Query<(Entity, &Transform, &Sprite, &mut XP), With<Enemy>>,
-    mut bullets: Query<(Entity, &Transform), With<Bullet>>,
+fn setup_enemy_bullets(
     mut commands: Commands,
-    mut collision_events: EventWriter<CollisionEvent>,
-    asset_server: Res<AssetServer>,
-    window_query: Query<&Window, With<PrimaryWindow>>,
-    mut score: ResMut<Score>,
-    score_root: Single<Entity, (With<LevelText>, With<Text>)>,
-    mut writer: TextUiWriter,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
 ) {
-    let (enemy_entity, enemy_object_transform, sprite, mut xp) = enemy_object.single_mut();
-    let image_dimensions = sprite.custom_size.unwrap();
-    let image_dims_in_vec2 = Vec2::new(image_dimensions.x as f32, image_dimensions.y as f32);
-    let scaled_image_dimension = image_dims_in_vec2 * enemy_object_transform.scale.truncate();
-    // let bounding_box = Rect::from_center_size(enemy_object_transform.translation.truncate(), scaled_image_dimension);
-    for (bullet_entity, bullet_transform) in &mut bullets {
-        // println!("bullet found:{}",enemy_object_transform.translation.truncate());
-        let collision = ball_collision(
-            BoundingCircle::new(bullet_transform.translation.truncate(), 5.),
-            Aabb2d::new(
-                enemy_object_transform.translation.truncate(),
-                scaled_image_dimension / 2.,
-            ),
-        );
+    commands.insert_resource(EnemyBulletHandles {
+        mesh: meshes.add(Circle::new(ENEMY_BULLET_RADIUS)),
+        material: materials.add(Color::srgb(1.0, 0.35, 0.3)),
+    });
+}
 
-        if collision {
-            println!("collision happened");
-            commands.entity(bullet_entity).despawn();
-            collision_events.send_default();
-            xp.0 = xp.0 - 1;
-            if xp.0 == 0 {
-                let window = window_query.get_single().unwrap();
-                let window_width = window.width();
-                let window_height = window.height();
-
-                commands.entity(enemy_entity).despawn();
-                let width = window_width;
-                let height = window_height;
-
-                let mut rng = thread_rng();
-                let x = rng.gen_range(
-                    (-1. * width / 2.) + (ENEMY_SQUARE_BOX_LENGTH / 2.0)
-                        ..(width / 2.) - (ENEMY_SQUARE_BOX_LENGTH / 2.0),
-                );
-                let y = rng.gen_range(0.0..(height / 2.) - (ENEMY_SQUARE_BOX_LENGTH / 2.0));
-
-                commands.spawn((
-                    get_enemy_bundle(
-                        asset_server.load(ENEMY_SPACE_SPRITE_NAME),
-                        window_width,
-                        window_height,
-                    ),
-                    Transform {
-                        translation: Vec3::new(x, y, 0.),
-                        scale: Vec3::new(0.5, 0.5, 1.0),
-                        ..default()
-                    },
-                ));
-
-                score.0 += 1;
-                *writer.text(*score_root, 1) = score.0.to_string();
-                // // for mut text in text_query.iter_mut() {
-                // //     text.sections[0].value = format!("Score {}", score.0);
-                // //     text.text
-                // // }
-                // for mut text_entity in text_query.iter_mut() {
-                //     println!("value found in query");
-                //     **text_entity = format!("Score {}", score.0);
-                // }
-                println!("now just updating the score value");
-            }
+pub fn enemy_bullet(handles: &EnemyBulletHandles, translation: Vec3) -> impl Bundle {
+    (
+        GameEntity,
+        Rollback,
+        EnemyBullet,
+        Mesh2d(handles.mesh.clone()),
+        MeshMaterial2d(handles.material.clone()),
+        Transform::from_translation(translation),
+    )
+}
+
+fn fire_enemy_bullets(
+    mut commands: Commands,
+    mut enemies: Query<(&Transform, &mut EnemyGun), With<Enemy>>,
+    handles: Res<EnemyBulletHandles>,
+    time: Res<Time>,
+) {
+    for (transform, mut gun) in &mut enemies {
+        if gun.0.tick(time.delta()).just_finished() {
+            commands.spawn(enemy_bullet(
+                &handles,
+                Vec3::new(
+                    transform.translation.x,
+                    transform.translation.y - (ENEMY_SQUARE_BOX_LENGTH / 2.),
+                    0.,
+                ),
+            ));
+        }
+    }
+}
+
+fn update_enemy_bullets(
+    mut commands: Commands,
+    mut bullets: Query<(Entity, &mut Transform), With<EnemyBullet>>,
+    playfield: Res<Playfield>,
+    tuning: Res<GameTuning>,
+    modifiers: Res<DailyModifiers>,
+) {
+    let speed = modifiers.enemy_bullet_speed(tuning.enemy_bullet_speed);
+    for (entity, mut transform) in &mut bullets {
+        transform.translation.y -= speed;
+        if transform.translation.y < -playfield.half_height() {
+            commands.entity(entity).despawn();
         }
     }
 }
@@ -221,24 +299,53 @@ fn check_for_collision(
 //     }
 // }
 
+// Whether the pixel `x` across and `y` down from the top left is drawn. Only
+// 8-bit RGBA can be read per pixel, any other format is hit on its box.
+fn opaque_at(image: &Image, x: usize, y: usize) -> bool {
+    if !matches!(
+        image.texture_descriptor.format,
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb
+    ) {
+        return true;
+    }
+    let idx = (y * image.width() as usize + x) * 4;
+    image.data.get(idx + 3).is_some_and(|alpha| *alpha > 0)
+}
+
+// What a player bullet needs to know about an enemy it might hit.
+type EnemyTarget<'a> = (
+    Entity,
+    &'a Transform,
+    &'a Sprite,
+    &'a mut XP,
+    &'a EnemyKind,
+    Has<GarbageEnemy>,
+);
+
+#[allow(clippy::too_many_arguments)]
 fn check_for_collision_3(
-    mut enemy_object_query: Query<(Entity, &Transform, &Sprite, &mut XP), With<Enemy>>,
-    mut bullets: Query<(Entity, &Transform), With<Bullet>>,
+    mut enemy_object_query: Query<EnemyTarget, With<Enemy>>,
+    mut bullets: Query<(Entity, &Transform, &Bullet)>,
     images: Res<Assets<Image>>,
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-
     mut collision_events: EventWriter<CollisionEvent>,
+    mut destroyed_events: EventWriter<EnemyDestroyedEvent>,
     asset_server: Res<AssetServer>,
-    window_query: Query<&Window, With<PrimaryWindow>>,
-    mut score: ResMut<Score>,
-    score_root: Single<Entity, (With<LevelText>, With<Text>)>,
-    mut writer: TextUiWriter,
+    playfield: Res<Playfield>,
+    mut rng: ResMut<GameRng>,
+    difficulty: Res<Difficulty>,
+    tuning: Res<GameTuning>,
+    mode: Res<GameMode>,
 ) {
-    for (bullet_entity, bullet_transform) in &mut bullets {
+    for (bullet_entity, bullet_transform, bullet) in &mut bullets {
         // println!("bullet pos:{}", bullet_transform.translation);
-        for (enemy_entity, enemy_object_transform, sprite, mut xp) in &mut enemy_object_query {
+        for (enemy_entity, enemy_object_transform, sprite, mut xp, kind, garbage) in
+            &mut enemy_object_query
+        {
+            // already shot down earlier this tick
+            if xp.0 <= 0 {
+                continue;
+            }
             let image_ref = &sprite.image;
             if let Some(enemy_image) = images.get(image_ref) {
                 let enemy_size_f32 = enemy_image.size_f32();
@@ -285,49 +392,46 @@ fn check_for_collision_3(
                     let pos_x = (top_left_enemy_x - bullet_center_x).abs();
 
                     // println!("position in square:{},{}", pos_x, pos_y);
-                    let idx = (pos_y as usize * enemy_size_f32.x as usize + pos_x as usize) * 4;
-                    let alpha = enemy_image.data[idx as usize];
                     // println!(
                     //     "collision happened pixel cords {},{},{}",
                     //     pos_x, pos_y, alpha
                     // );
-                    if alpha > 0 {
+                    if opaque_at(enemy_image, pos_x as usize, pos_y as usize) {
                         println!("collision happened");
                         commands.entity(bullet_entity).despawn();
-                        collision_events.send_default();
+                        collision_events.send(CollisionEvent {
+                            position: bullet_transform.translation.truncate(),
+                        });
                         xp.0 = xp.0 - 1;
                         println!("xp now is {}", xp.0);
                         if xp.0 == 0 {
-                            let window = window_query.get_single().unwrap();
-                            let window_width = window.width();
-                            let window_height = window.height();
-
                             commands.entity(enemy_entity).despawn();
-                            let width = window_width;
-                            let height = window_height;
-
-                            let mut rng = thread_rng();
-                            let x = rng.gen_range(
-                                (-1. * width / 2.) + (ENEMY_SQUARE_BOX_LENGTH / 2.0)
-                                    ..(width / 2.) - (ENEMY_SQUARE_BOX_LENGTH / 2.0),
-                            );
-                            let y =
-                                rng.gen_range(0.0..(height / 2.) - (ENEMY_SQUARE_BOX_LENGTH / 2.0));
-
-                            commands.spawn((
-                                get_enemy_bundle(
-                                    asset_server.load(ENEMY_SPACE_SPRITE_NAME),
-                                    window_width,
-                                    window_height,
-                                ),
-                                Transform {
-                                    translation: Vec3::new(x, y, 0.),
-                                    ..default()
-                                },
-                            ));
-
-                            score.0 += 1;
-                            *writer.text(*score_root, 1) = score.0.to_string();
+                            destroyed_events.send(EnemyDestroyedEvent {
+                                position: enemy_object_transform.translation.truncate(),
+                                points: kind.points(),
+                                player: bullet.player,
+                            });
+                            // the replacement turns up on the same side in versus
+                            let side = lane_owner(enemy_object_transform.translation.x);
+                            if !garbage && mode.keeps_one_enemy_up() {
+                                commands.spawn((
+                                    get_enemy_bundle(
+                                        asset_server.load(ENEMY_SPACE_SPRITE_NAME),
+                                        EnemyKind::random(&mut rng),
+                                        *difficulty,
+                                        &tuning,
+                                    ),
+                                    Transform {
+                                        translation: random_enemy_position(
+                                            &mut rng,
+                                            &playfield,
+                                            lane(*mode, &playfield, side),
+                                        ),
+                                        ..default()
+                                    },
+                                ));
+                            }
+
                             // // for mut text in text_query.iter_mut() {
                             // //     text.sections[0].value = format!("Score {}", score.0);
                             // //     text.text
@@ -338,6 +442,8 @@ fn check_for_collision_3(
                             // }
                             println!("now just updating the score value");
                         }
+                        // the bullet is spent, it can't hit anything behind
+                        break;
                     } else {
                         // println!("cool");
                     }
@@ -376,10 +482,23 @@ impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
         println!("This is the build process now");
         app.add_event::<CollisionEvent>();
+        app.add_event::<EnemyDestroyedEvent>();
+        app.rollback_component::<Enemy>()
+            .rollback_component::<EnemyKind>()
+            .rollback_component::<EnemyGun>()
+            .rollback_component::<XP>()
+            .rollback_component::<GarbageEnemy>()
+            .rollback_component::<EnemyBullet>();
+        app.add_systems(Startup, setup_enemy_bullets);
         app.add_systems(OnEnter(GameState::Game), create_space_enemy_objects);
         app.add_systems(
-            Update,
-            check_for_collision_3.run_if(in_state(GameState::Game)),
+            FixedUpdate,
+            (
+                check_for_collision_3,
+                fire_enemy_bullets,
+                update_enemy_bullets,
+            )
+                .run_if(in_state(GameState::Game).and(gameplay_running)),
         );
         // app.add_systems(
         //     Update,
@@ -387,3 +506,114 @@ impl Plugin for EnemyPlugin {
         // );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        asset::AssetServer,
+        ecs::event::Events,
+        math::Vec3,
+        prelude::{Transform, With},
+    };
+
+    use crate::{
+        constants::{Bullet, CollisionEvent, EnemyDestroyedEvent, ENEMY_SPACE_SPRITE_NAME},
+        game::{
+            playfield_mod::Playfield, rng_mod::GameRng, tuning_mod::GameTuning, Difficulty, Score,
+        },
+        headless::headless_app,
+        test_support::{entities, step, step_until, test_app},
+    };
+
+    use super::{get_enemy_bundle, Enemy, EnemyKind, XP};
+
+    #[test]
+    fn a_fighter_dies_after_its_health_in_hits_and_is_worth_its_points() {
+        let mut app = test_app();
+        let health = app.world().resource::<GameTuning>().enemy_health;
+        let world = app.world_mut();
+        // line the enemy up with the jet's guns
+        let (mut enemy, mut kind, mut xp) = world
+            .query_filtered::<(&mut Transform, &mut EnemyKind, &mut XP), With<Enemy>>()
+            .single_mut(world);
+        enemy.translation.x = 0.;
+        enemy.translation.y = 200.;
+        *kind = EnemyKind::Fighter;
+        xp.0 = health;
+        let mut hit_cursor = world.resource::<Events<CollisionEvent>>().get_cursor();
+        let mut kill_cursor = world.resource::<Events<EnemyDestroyedEvent>>().get_cursor();
+
+        let mut hits = 0;
+        let mut points = None;
+        let killed = step_until(&mut app, 2000, |app| {
+            let world = app.world();
+            hits += hit_cursor
+                .read(world.resource::<Events<CollisionEvent>>())
+                .count();
+            points = kill_cursor
+                .read(world.resource::<Events<EnemyDestroyedEvent>>())
+                .map(|destroyed| destroyed.points)
+                .next();
+            points.is_some()
+        });
+
+        assert!(killed, "enemy survived, {hits} hits landed");
+        assert_eq!(hits, health as usize);
+        assert_eq!(points, Some(EnemyKind::Fighter.points()));
+        assert!(app.world().resource::<Score>().0 >= EnemyKind::Fighter.points());
+        // a replacement enemy is spawned straight away
+        assert_eq!(entities::<With<Enemy>>(&mut app).len(), 1);
+    }
+
+    #[test]
+    fn one_bullet_only_hits_one_of_two_overlapping_enemies() {
+        let mut app = test_app();
+        let world = app.world_mut();
+        let image = world
+            .resource::<AssetServer>()
+            .load(ENEMY_SPACE_SPRITE_NAME);
+        let tuning = world.resource::<GameTuning>().clone();
+        world.spawn((
+            get_enemy_bundle(image, EnemyKind::Scout, Difficulty::Normal, &tuning),
+            Transform::default(),
+        ));
+        for (mut enemy, mut xp) in world
+            .query_filtered::<(&mut Transform, &mut XP), With<Enemy>>()
+            .iter_mut(world)
+        {
+            enemy.translation = Vec3::new(0., 200., 0.);
+            xp.0 = 1;
+        }
+        world.spawn((Bullet { player: 0 }, Transform::from_xyz(0., 200., 0.)));
+        let mut hit_cursor = world.resource::<Events<CollisionEvent>>().get_cursor();
+        let mut kill_cursor = world.resource::<Events<EnemyDestroyedEvent>>().get_cursor();
+
+        step(&mut app, 1);
+        let world = app.world();
+        let hits = hit_cursor
+            .read(world.resource::<Events<CollisionEvent>>())
+            .count();
+        let kills = kill_cursor
+            .read(world.resource::<Events<EnemyDestroyedEvent>>())
+            .count();
+        assert_eq!((hits, kills), (1, 1));
+        // the other one is still there, next to the replacement
+        assert_eq!(entities::<With<Enemy>>(&mut app).len(), 2);
+    }
+
+    #[test]
+    fn same_seed_spawns_the_first_enemy_in_the_same_place() {
+        let first_enemy = |seed| {
+            let mut app = headless_app(Playfield::default());
+            app.insert_resource(GameRng::new(seed));
+            app.update();
+            let world = app.world_mut();
+            world
+                .query_filtered::<&Transform, With<Enemy>>()
+                .single(world)
+                .translation
+        };
+        assert_eq!(first_enemy(1234), first_enemy(1234));
+        assert_ne!(first_enemy(1234), first_enemy(4321));
+    }
+}
@@ -0,0 +1,431 @@
+use std::{
+    fmt, fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{
+    app::{App, Plugin},
+    prelude::Resource,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    constants::{HIGH_SCORE_ENTRIES, HIGH_SCORE_FILE, HIGH_SCORE_FILE_VERSION},
+    user_data::user_data_dir,
+};
+
+use super::{daily_mod::Modifier, Difficulty, GameMode};
+
+pub struct HighScorePlugin;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HighScoreEntry {
+    pub name: String,
+    pub score: usize,
+    // local date the run was played on, YYYY-MM-DD
+    pub date: String,
+    pub seed: u64,
+    pub stage: usize,
+    // file name of the run's replay in the replay directory
+    #[serde(default)]
+    pub replay: Option<String>,
+    // ticks a time attack run took to clear every wave
+    #[serde(default)]
+    pub ticks: Option<u32>,
+}
+
+impl HighScoreEntry {
+    // Whether this run belongs above `other` on `mode`'s table.
+    fn beats(&self, other: &HighScoreEntry, mode: GameMode) -> bool {
+        if mode.ranks_by_time() {
+            match (self.ticks, other.ticks) {
+                (Some(ticks), Some(other)) => ticks < other,
+                (Some(_), None) => true,
+                (None, _) => false,
+            }
+        } else {
+            self.score > other.score
+        }
+    }
+}
+
+// How a day's challenge went, the first attempt only.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DailyResult {
+    pub date: String,
+    pub seed: u64,
+    pub modifiers: Vec<Modifier>,
+    pub score: usize,
+    // what the attempt was played on, the menu's choice at the time
+    #[serde(default)]
+    pub difficulty: Difficulty,
+    #[serde(default = "one_player")]
+    pub players: usize,
+}
+
+impl DailyResult {
+    // e.g. "Normal, 1P"
+    pub fn played_on(&self) -> String {
+        format!("{}, {}P", self.difficulty.name(), self.players)
+    }
+}
+
+fn one_player() -> usize {
+    1
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct HighScoreTable {
+    mode: GameMode,
+    difficulty: Difficulty,
+    entries: Vec<HighScoreEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct HighScoreFile {
+    version: u32,
+    // prefilled the next time a name is asked for
+    last_name: String,
+    tables: Vec<HighScoreTable>,
+    #[serde(default)]
+    daily: Vec<DailyResult>,
+}
+
+impl Default for HighScoreFile {
+    fn default() -> Self {
+        HighScoreFile {
+            version: HIGH_SCORE_FILE_VERSION,
+            last_name: String::new(),
+            tables: Vec::new(),
+            daily: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum HighScoreError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    Write(ron::Error),
+    Version(u32),
+}
+
+impl fmt::Display for HighScoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HighScoreError::Io(error) => write!(f, "could not access high score file: {error}"),
+            HighScoreError::Parse(error) => write!(f, "could not parse high score file: {error}"),
+            HighScoreError::Write(error) => write!(f, "could not write high score file: {error}"),
+            HighScoreError::Version(version) => {
+                write!(f, "unsupported high score file version {version}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for HighScoreError {}
+
+impl From<std::io::Error> for HighScoreError {
+    fn from(error: std::io::Error) -> Self {
+        HighScoreError::Io(error)
+    }
+}
+
+impl From<ron::error::SpannedError> for HighScoreError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        HighScoreError::Parse(error)
+    }
+}
+
+impl From<ron::Error> for HighScoreError {
+    fn from(error: ron::Error) -> Self {
+        HighScoreError::Write(error)
+    }
+}
+
+// The best runs for every mode and difficulty. Without a `path` the table
+// only lives in memory, as in headless runs and tests.
+#[derive(Resource, Default)]
+pub struct HighScores {
+    file: HighScoreFile,
+    path: Option<PathBuf>,
+}
+
+impl HighScores {
+    pub fn entries(&self, mode: GameMode, difficulty: Difficulty) -> &[HighScoreEntry] {
+        self.file
+            .tables
+            .iter()
+            .find(|table| table.mode == mode && table.difficulty == difficulty)
+            .map_or(&[], |table| table.entries.as_slice())
+    }
+
+    pub fn best(&self, mode: GameMode, difficulty: Difficulty) -> Option<usize> {
+        self.entries(mode, difficulty)
+            .first()
+            .map(|entry| entry.score)
+    }
+
+    // The quickest clear on a time attack table, in ticks.
+    pub fn best_ticks(&self, mode: GameMode, difficulty: Difficulty) -> Option<u32> {
+        self.entries(mode, difficulty)
+            .first()
+            .and_then(|entry| entry.ticks)
+    }
+
+    pub fn qualifies(
+        &self,
+        mode: GameMode,
+        difficulty: Difficulty,
+        entry: &HighScoreEntry,
+    ) -> bool {
+        let entries = self.entries(mode, difficulty);
+        let counts = if mode.ranks_by_time() {
+            entry.ticks.is_some()
+        } else {
+            entry.score > 0
+        };
+        counts
+            && (entries.len() < HIGH_SCORE_ENTRIES
+                || entries.last().is_some_and(|last| entry.beats(last, mode)))
+    }
+
+    pub fn daily(&self, date: &str) -> Option<&DailyResult> {
+        self.file.daily.iter().find(|result| result.date == date)
+    }
+
+    // Keeps one result per day, a later one for the same date replaces it.
+    pub fn record_daily(&mut self, result: DailyResult) {
+        self.file
+            .daily
+            .retain(|existing| existing.date != result.date);
+        self.file.daily.push(result);
+    }
+
+    pub fn last_name(&self) -> &str {
+        &self.file.last_name
+    }
+
+    // Adds the entry behind any equal results and returns its 1-based rank, or
+    // `None` if it didn't make the table.
+    pub fn insert(
+        &mut self,
+        mode: GameMode,
+        difficulty: Difficulty,
+        entry: HighScoreEntry,
+    ) -> Option<usize> {
+        self.file.last_name = entry.name.clone();
+        let index = match self
+            .file
+            .tables
+            .iter()
+            .position(|table| table.mode == mode && table.difficulty == difficulty)
+        {
+            Some(index) => index,
+            None => {
+                self.file.tables.push(HighScoreTable {
+                    mode,
+                    difficulty,
+                    entries: Vec::new(),
+                });
+                self.file.tables.len() - 1
+            }
+        };
+        let entries = &mut self.file.tables[index].entries;
+        let rank = entries
+            .iter()
+            .position(|existing| entry.beats(existing, mode))
+            .unwrap_or(entries.len());
+        if rank >= HIGH_SCORE_ENTRIES {
+            return None;
+        }
+        entries.insert(rank, entry);
+        entries.truncate(HIGH_SCORE_ENTRIES);
+        Some(rank + 1)
+    }
+
+    // Reads the table at `path`. A missing file gives an empty table. A file
+    // that can't be parsed is moved aside so the next save doesn't overwrite it.
+    pub fn load_or_backup(path: PathBuf) -> HighScores {
+        let error = match fs::read(&path) {
+            Ok(bytes) => match parse(&bytes) {
+                Ok(file) => {
+                    return HighScores {
+                        file,
+                        path: Some(path),
+                    }
+                }
+                Err(error) => error,
+            },
+            Err(error) if error.kind() == ErrorKind::NotFound => {
+                return HighScores {
+                    file: HighScoreFile::default(),
+                    path: Some(path),
+                }
+            }
+            Err(error) => {
+                // unreadable but maybe fine, keep it and don't save over it
+                println!("{}, high scores won't be saved", HighScoreError::Io(error));
+                return HighScores::default();
+            }
+        };
+
+        let backup = backup_path(&path);
+        match fs::rename(&path, &backup) {
+            Ok(()) => {
+                println!("{error}, moved it to {}", backup.display());
+                HighScores {
+                    file: HighScoreFile::default(),
+                    path: Some(path),
+                }
+            }
+            Err(rename_error) => {
+                println!("{error} and it could not be moved aside ({rename_error}), high scores won't be saved");
+                HighScores::default()
+            }
+        }
+    }
+
+    // Writes to a temporary file first so a crash mid-write can't corrupt the table.
+    pub fn save(&self) -> Result<(), HighScoreError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let temporary = path.with_extension("ron.tmp");
+        let pretty = ron::ser::PrettyConfig::default();
+        fs::write(&temporary, ron::ser::to_string_pretty(&self.file, pretty)?)?;
+        fs::rename(&temporary, path)?;
+        Ok(())
+    }
+}
+
+fn parse(bytes: &[u8]) -> Result<HighScoreFile, HighScoreError> {
+    let file: HighScoreFile = ron::de::from_bytes(bytes)?;
+    if file.version != HIGH_SCORE_FILE_VERSION {
+        return Err(HighScoreError::Version(file.version));
+    }
+    Ok(file)
+}
+
+pub fn backup_path(path: &Path) -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    path.with_extension(format!("ron.corrupt-{timestamp}"))
+}
+
+pub fn today() -> String {
+    chrono::Local::now().format("%Y-%m-%d").to_string()
+}
+
+impl Plugin for HighScorePlugin {
+    fn build(&self, app: &mut App) {
+        if !app.world().contains_resource::<HighScores>() {
+            let high_scores = HighScores::load_or_backup(user_data_dir().join(HIGH_SCORE_FILE));
+            app.insert_resource(high_scores);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        constants::HIGH_SCORE_ENTRIES,
+        game::{Difficulty, GameMode},
+    };
+
+    use super::{HighScoreEntry, HighScores};
+
+    fn entry(name: &str, score: usize) -> HighScoreEntry {
+        HighScoreEntry {
+            name: name.to_string(),
+            score,
+            date: "2026-01-01".to_string(),
+            seed: 1,
+            stage: 1,
+            replay: None,
+            ticks: None,
+        }
+    }
+
+    #[test]
+    fn table_keeps_the_best_scores_in_order() {
+        let mut high_scores = HighScores::default();
+        let (mode, difficulty) = (GameMode::Campaign, Difficulty::Normal);
+        for score in 1..=HIGH_SCORE_ENTRIES {
+            assert!(high_scores.qualifies(mode, difficulty, &entry("AAA", score)));
+            high_scores.insert(mode, difficulty, entry("AAA", score));
+        }
+        assert!(!high_scores.qualifies(mode, difficulty, &entry("AAA", 1)));
+        assert_eq!(
+            high_scores.insert(mode, difficulty, entry("BBB", 5)),
+            Some(HIGH_SCORE_ENTRIES - 3)
+        );
+
+        let entries = high_scores.entries(mode, difficulty);
+        assert_eq!(entries.len(), HIGH_SCORE_ENTRIES);
+        assert!(entries
+            .windows(2)
+            .all(|pair| pair[0].score >= pair[1].score));
+        assert_eq!(entries.last().unwrap().score, 2);
+        assert!(high_scores.entries(mode, Difficulty::Hard).is_empty());
+        assert_eq!(high_scores.last_name(), "BBB");
+    }
+
+    #[test]
+    fn time_attack_table_puts_the_quickest_clear_first() {
+        let mut high_scores = HighScores::default();
+        let (mode, difficulty) = (GameMode::TimeAttack, Difficulty::Normal);
+        let clear = |name, ticks| HighScoreEntry {
+            ticks: Some(ticks),
+            ..entry(name, 100)
+        };
+        // a run that didn't clear every wave has no time to rank by
+        assert!(!high_scores.qualifies(mode, difficulty, &entry("AAA", 5000)));
+        high_scores.insert(mode, difficulty, clear("BBB", 900));
+        assert_eq!(
+            high_scores.insert(mode, difficulty, clear("CCC", 600)),
+            Some(1)
+        );
+        assert_eq!(high_scores.best_ticks(mode, difficulty), Some(600));
+    }
+
+    #[test]
+    fn corrupt_file_is_backed_up_instead_of_overwritten() {
+        let dir = std::env::temp_dir().join(format!("space_fight_scores_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("highscores.ron");
+        fs::write(&path, "not a high score table").unwrap();
+
+        let mut high_scores = HighScores::load_or_backup(path.clone());
+        assert!(high_scores
+            .entries(GameMode::Campaign, Difficulty::Normal)
+            .is_empty());
+        let backups: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().contains("corrupt"))
+            .collect();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(
+            fs::read_to_string(backups[0].path()).unwrap(),
+            "not a high score table"
+        );
+
+        high_scores.insert(GameMode::Campaign, Difficulty::Normal, entry("CCC", 9));
+        high_scores.save().unwrap();
+        let reloaded = HighScores::load_or_backup(path);
+        assert_eq!(
+            reloaded.entries(GameMode::Campaign, Difficulty::Normal),
+            &[entry("CCC", 9)]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
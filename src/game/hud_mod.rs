@@ -0,0 +1,589 @@
+use bevy::{
+    app::{App, Plugin, Update},
+    asset::{AssetServer, Handle},
+    color::{Alpha, Color},
+    prelude::{
+        in_state, BuildChildren, Bundle, ChildBuild, ChildBuilder, Commands, Component,
+        DespawnRecursiveExt, Display, Entity, EventReader, FlexDirection, ImageNode,
+        IntoSystemConfigs, JustifyContent, Node, OnEnter, PositionType, Query, Res, ResMut,
+        Resource, Text, TextColor, TextFont, Val, With, Without,
+    },
+    text::{Font, JustifyText, TextLayout},
+    time::{Time, Timer, TimerMode},
+    ui::{AlignItems, BackgroundColor, BorderRadius},
+};
+
+use crate::{
+    constants::{HUD_FONT, HUD_MAX_ICONS, JET_SPRITE_NAMES, SCORE_ROLL_RATE},
+    GameState,
+};
+
+use super::{
+    attack_mod::{format_ticks, AttackRun, TIME_ATTACK_WAVES},
+    highscore_mod::HighScores,
+    level_mod::CurrentLevel,
+    player_jet_mod::{GameEntity, Players},
+    replay_mod::FixedTick,
+    scoring_mod::{Combo, Wave, WaveClearedEvent},
+    tuning_mod::GameTuning,
+    versus_mod::VersusMatch,
+    Difficulty, GameMode, PlayerCount, Score, MY_ORANGE,
+};
+
+pub struct HudPlugin;
+
+// Shown across the top while a boss is on screen, hidden otherwise.
+// Placeholder: there are no bosses yet and nothing inserts this, so the bar
+// stays hidden until a boss system does.
+#[derive(Resource)]
+pub struct BossBar {
+    pub name: String,
+    pub health: i32,
+    pub max_health: i32,
+}
+
+// The score on the HUD counts up towards the real one rather than jumping.
+#[derive(Resource, Default)]
+struct RollingScore(usize);
+
+#[derive(Component)]
+enum HudText {
+    Score,
+    HighScore,
+    Stage,
+    // time survived in endless, left in score attack, taken in time attack
+    Time,
+    Weapon,
+    Combo,
+    BossName,
+    // a player's own share of the score, only shown in co-op
+    PlayerScore(usize),
+}
+
+#[derive(Clone, Copy)]
+enum IconKind {
+    Life,
+    Bomb,
+}
+
+// A row of icons, rebuilt whenever the count it shows goes stale.
+#[derive(Component)]
+struct HudIcons {
+    kind: IconKind,
+    player: usize,
+    shown: Option<u32>,
+}
+
+#[derive(Component)]
+struct ComboMeterFill;
+
+#[derive(Component)]
+struct BossBarRoot;
+
+#[derive(Component)]
+struct BossBarFill;
+
+#[derive(Component)]
+struct WaveBanner(Timer);
+
+fn hud_text(font: &Handle<Font>, font_size: f32) -> impl Bundle {
+    (
+        Text::default(),
+        TextFont {
+            font: font.clone(),
+            font_size,
+            ..Default::default()
+        },
+    )
+}
+
+fn bar(parent: &mut ChildBuilder, width: Val, height: f32, color: Color, fill: impl Component) {
+    parent
+        .spawn((
+            Node {
+                width,
+                height: Val::Px(height),
+                ..Default::default()
+            },
+            BackgroundColor(Color::srgba(1.0, 1.0, 1.0, 0.15)),
+        ))
+        .with_child((
+            fill,
+            Node {
+                width: Val::Percent(0.),
+                height: Val::Percent(100.),
+                ..Default::default()
+            },
+            BackgroundColor(color),
+        ));
+}
+
+fn setup_hud(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut rolling: ResMut<RollingScore>,
+    player_count: Res<PlayerCount>,
+    mode: Res<GameMode>,
+) {
+    rolling.0 = 0;
+    let font = asset_server.load(HUD_FONT);
+    let column = |top: bool, left: bool| Node {
+        position_type: PositionType::Absolute,
+        flex_direction: FlexDirection::Column,
+        align_items: if left {
+            AlignItems::Start
+        } else {
+            AlignItems::End
+        },
+        row_gap: Val::Px(4.),
+        top: if top { Val::Px(6.) } else { Val::Auto },
+        bottom: if top { Val::Auto } else { Val::Px(6.) },
+        left: if left { Val::Px(8.) } else { Val::Auto },
+        right: if left { Val::Auto } else { Val::Px(8.) },
+        ..Default::default()
+    };
+
+    // score, high score and combo in the top left
+    commands
+        .spawn((GameEntity, column(true, true)))
+        .with_children(|parent| {
+            parent.spawn((HudText::Score, hud_text(&font, 28.)));
+            parent.spawn((HudText::HighScore, hud_text(&font, 16.)));
+            parent.spawn((HudText::Combo, hud_text(&font, 16.)));
+            bar(parent, Val::Px(120.), 6., MY_ORANGE, ComboMeterFill);
+        });
+
+    // stage and weapon in the top right
+    commands
+        .spawn((GameEntity, column(true, false)))
+        .with_children(|parent| {
+            parent.spawn((HudText::Stage, hud_text(&font, 18.)));
+            if *mode == GameMode::Endless
+                || mode.time_limit_seconds().is_some()
+                || mode.ranks_by_time()
+            {
+                parent.spawn((HudText::Time, hud_text(&font, 18.)));
+            }
+            parent.spawn((HudText::Weapon, hud_text(&font, 16.)));
+        });
+
+    // lives and bombs in the bottom left, the second player's in the bottom right
+    for player in 0..player_count.0 {
+        commands
+            .spawn((GameEntity, column(false, player == 0)))
+            .with_children(|parent| {
+                if player_count.0 > 1 {
+                    parent.spawn((HudText::PlayerScore(player), hud_text(&font, 16.)));
+                }
+                for kind in [IconKind::Life, IconKind::Bomb] {
+                    parent.spawn((
+                        HudIcons {
+                            kind,
+                            player,
+                            shown: None,
+                        },
+                        Node {
+                            column_gap: Val::Px(4.),
+                            align_items: AlignItems::Center,
+                            ..Default::default()
+                        },
+                    ));
+                }
+            });
+    }
+
+    // boss health across the top
+    commands
+        .spawn((
+            GameEntity,
+            BossBarRoot,
+            Node {
+                display: Display::None,
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(2.),
+                top: Val::Px(40.),
+                left: Val::Percent(20.),
+                width: Val::Percent(60.),
+                ..Default::default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn((HudText::BossName, hud_text(&font, 16.)));
+            bar(
+                parent,
+                Val::Percent(100.),
+                8.,
+                Color::srgb(0.9, 0.2, 0.2),
+                BossBarFill,
+            );
+        });
+
+    // wave results in the middle of the screen
+    commands
+        .spawn((
+            GameEntity,
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Percent(35.),
+                width: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+        ))
+        .with_child((
+            WaveBanner(Timer::from_seconds(3., TimerMode::Once)),
+            hud_text(&font, 24.),
+            TextLayout::new_with_justify(JustifyText::Center),
+            TextColor(Color::NONE),
+        ));
+}
+
+fn set_text(text: &mut Text, value: String) {
+    if text.0 != value {
+        text.0 = value;
+    }
+}
+
+fn roll_score(score: Res<Score>, mut rolling: ResMut<RollingScore>, time: Res<Time>) {
+    if rolling.0 >= score.0 {
+        // a new run starts back at zero
+        rolling.0 = score.0;
+        return;
+    }
+    let gap = (score.0 - rolling.0) as f32;
+    let step = (gap * (SCORE_ROLL_RATE * time.delta_secs()).min(1.)).ceil() as usize;
+    rolling.0 = (rolling.0 + step.max(1)).min(score.0);
+}
+
+fn update_score_text(
+    rolling: Res<RollingScore>,
+    score: Res<Score>,
+    high_scores: Res<HighScores>,
+    mode: Res<GameMode>,
+    difficulty: Res<Difficulty>,
+    mut texts: Query<(&HudText, &mut Text)>,
+) {
+    let best = if mode.ranks_by_time() {
+        match high_scores.best_ticks(*mode, *difficulty) {
+            Some(ticks) => format!("BEST {}", format_ticks(ticks)),
+            None => "BEST --:--.--".to_string(),
+        }
+    } else {
+        let best = high_scores
+            .best(*mode, *difficulty)
+            .unwrap_or(0)
+            .max(score.0);
+        format!("HI {best:08}")
+    };
+    for (field, mut text) in &mut texts {
+        match field {
+            HudText::Score => set_text(&mut text, format!("{:08}", rolling.0)),
+            HudText::HighScore => set_text(&mut text, best.clone()),
+            _ => {}
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_progress_text(
+    level: Res<CurrentLevel>,
+    wave: Res<Wave>,
+    players: Res<Players>,
+    mode: Res<GameMode>,
+    versus: Res<VersusMatch>,
+    tick: Res<FixedTick>,
+    attack: Res<AttackRun>,
+    mut texts: Query<(&HudText, &mut Text)>,
+) {
+    for (field, mut text) in &mut texts {
+        match field {
+            HudText::Stage if *mode == GameMode::Versus => set_text(
+                &mut text,
+                format!(
+                    "ROUND {}  {} - {}",
+                    versus.round, versus.wins[0], versus.wins[1]
+                ),
+            ),
+            HudText::Stage if mode.ranks_by_time() => set_text(
+                &mut text,
+                format!("WAVE {}/{}", attack.wave, TIME_ATTACK_WAVES.len()),
+            ),
+            HudText::Stage => set_text(
+                &mut text,
+                format!("STAGE {}  WAVE {}", level.0 + 1, wave.number),
+            ),
+            HudText::Time if mode.ranks_by_time() => {
+                let ticks = attack.finished.unwrap_or(tick.0);
+                set_text(&mut text, format!("TIME {}", format_ticks(ticks)));
+            }
+            HudText::Time => {
+                // score attack counts down, rounding up so it ends on 00:00
+                let seconds = match mode.time_limit_seconds() {
+                    Some(limit) => (limit as f32 - tick.seconds()).max(0.).ceil() as u32,
+                    None => tick.seconds() as u32,
+                };
+                set_text(
+                    &mut text,
+                    format!("TIME {:02}:{:02}", seconds / 60, seconds % 60),
+                );
+            }
+            // nothing raises the weapon level yet, so it stays hidden while
+            // every jet is still on the starting level
+            HudText::Weapon if players.0.iter().all(|stats| stats.weapon_level <= 1) => {
+                set_text(&mut text, String::new())
+            }
+            HudText::Weapon => {
+                let levels: Vec<String> = players
+                    .0
+                    .iter()
+                    .map(|stats| stats.weapon_level.to_string())
+                    .collect();
+                set_text(&mut text, format!("PWR {}", levels.join(" | ")));
+            }
+            HudText::PlayerScore(player) => {
+                let value = match players.0.get(*player) {
+                    Some(stats) if stats.lives == 0 => format!("P{} DOWN", player + 1),
+                    Some(stats) => format!("P{} {:08}", player + 1, stats.score),
+                    None => String::new(),
+                };
+                set_text(&mut text, value);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn update_combo(
+    combo: Res<Combo>,
+    tuning: Res<GameTuning>,
+    mut texts: Query<(&HudText, &mut Text)>,
+    mut meter: Query<&mut Node, With<ComboMeterFill>>,
+) {
+    for (field, mut text) in &mut texts {
+        if let HudText::Combo = field {
+            let value = if combo.chain == 0 {
+                String::new()
+            } else {
+                format!("x{} COMBO {}", combo.multiplier(), combo.chain)
+            };
+            set_text(&mut text, value);
+        }
+    }
+    let fraction = if combo.chain == 0 {
+        0.
+    } else {
+        (combo.seconds_left / tuning.combo_window_seconds).clamp(0., 1.)
+    };
+    for mut node in &mut meter {
+        node.width = Val::Percent(fraction * 100.);
+    }
+}
+
+fn update_boss_bar(
+    boss: Option<Res<BossBar>>,
+    mut root: Query<&mut Node, (With<BossBarRoot>, Without<BossBarFill>)>,
+    mut fill: Query<&mut Node, (With<BossBarFill>, Without<BossBarRoot>)>,
+    mut texts: Query<(&HudText, &mut Text)>,
+) {
+    for mut node in &mut root {
+        node.display = if boss.is_some() {
+            Display::Flex
+        } else {
+            Display::None
+        };
+    }
+    let Some(boss) = boss else {
+        return;
+    };
+    let fraction = (boss.health as f32 / boss.max_health.max(1) as f32).clamp(0., 1.);
+    for mut node in &mut fill {
+        node.width = Val::Percent(fraction * 100.);
+    }
+    for (field, mut text) in &mut texts {
+        if let HudText::BossName = field {
+            set_text(&mut text, boss.name.clone());
+        }
+    }
+}
+
+fn update_icons(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    players: Res<Players>,
+    mut rows: Query<(Entity, &mut HudIcons)>,
+) {
+    for (entity, mut icons) in &mut rows {
+        let Some(stats) = players.0.get(icons.player) else {
+            continue;
+        };
+        let count = match icons.kind {
+            IconKind::Life => stats.lives,
+            IconKind::Bomb => stats.bombs,
+        };
+        if icons.shown == Some(count) {
+            continue;
+        }
+        icons.shown = Some(count);
+        let (kind, player) = (icons.kind, icons.player);
+        commands
+            .entity(entity)
+            .despawn_descendants()
+            .with_children(|parent| {
+                let spawn_icon = |parent: &mut ChildBuilder| match kind {
+                    IconKind::Life => {
+                        parent.spawn((
+                            ImageNode::new(asset_server.load(JET_SPRITE_NAMES[player])),
+                            Node {
+                                width: Val::Px(20.),
+                                height: Val::Px(20.),
+                                ..Default::default()
+                            },
+                        ));
+                    }
+                    IconKind::Bomb => {
+                        parent.spawn((
+                            Node {
+                                width: Val::Px(12.),
+                                height: Val::Px(12.),
+                                ..Default::default()
+                            },
+                            BorderRadius::MAX,
+                            BackgroundColor(MY_ORANGE),
+                        ));
+                    }
+                };
+                if count > HUD_MAX_ICONS {
+                    spawn_icon(parent);
+                    parent.spawn((
+                        Text::new(format!("x{count}")),
+                        TextFont {
+                            font: asset_server.load(HUD_FONT),
+                            font_size: 16.,
+                            ..Default::default()
+                        },
+                    ));
+                } else {
+                    for _ in 0..count {
+                        spawn_icon(parent);
+                    }
+                }
+            });
+    }
+}
+
+fn show_wave_bonus(
+    mut wave_events: EventReader<WaveClearedEvent>,
+    mut banners: Query<(&mut WaveBanner, &mut Text, &mut TextColor)>,
+    time: Res<Time>,
+) {
+    for (mut banner, mut text, mut color) in &mut banners {
+        for cleared in wave_events.read() {
+            let mut lines = vec![
+                format!("WAVE {} CLEAR", cleared.wave),
+                format!(
+                    "accuracy {:.0}%  +{}",
+                    cleared.accuracy * 100.,
+                    cleared.accuracy_bonus
+                ),
+            ];
+            if cleared.no_damage_bonus > 0 {
+                lines.push(format!("no damage  +{}", cleared.no_damage_bonus));
+            }
+            text.0 = lines.join("\n");
+            banner.0.reset();
+        }
+        banner.0.tick(time.delta());
+        color.0 = Color::WHITE.with_alpha(1. - banner.0.fraction());
+    }
+}
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RollingScore>();
+        app.add_systems(OnEnter(GameState::Game), setup_hud);
+        app.add_systems(
+            Update,
+            (
+                (roll_score, update_score_text).chain(),
+                update_progress_text,
+                update_combo,
+                update_boss_bar,
+                update_icons,
+                show_wave_bonus,
+            )
+                .chain()
+                .run_if(in_state(GameState::Game)),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::{Children, Text};
+
+    use crate::{
+        game::{player_jet_mod::Players, Score},
+        test_support::{step, test_app},
+    };
+
+    use super::{HudIcons, HudText, IconKind};
+
+    fn hud_text(app: &mut bevy::app::App, wanted: fn(&HudText) -> bool) -> String {
+        let world = app.world_mut();
+        world
+            .query::<(&HudText, &Text)>()
+            .iter(world)
+            .find(|(field, _)| wanted(field))
+            .map(|(_, text)| text.0.clone())
+            .unwrap()
+    }
+
+    #[test]
+    fn score_rolls_up_to_the_new_total() {
+        let mut app = test_app();
+        let target = app.world().resource::<Score>().0 + 12_345;
+        app.world_mut().resource_mut::<Score>().0 = target;
+
+        step(&mut app, 1);
+        let shown = hud_text(&mut app, |field| matches!(field, HudText::Score));
+        assert_ne!(
+            shown,
+            format!("{target:08}"),
+            "score jumped straight to the total"
+        );
+
+        step(&mut app, 60);
+        let shown = hud_text(&mut app, |field| matches!(field, HudText::Score));
+        assert_eq!(shown, format!("{:08}", app.world().resource::<Score>().0));
+    }
+
+    #[test]
+    fn life_icons_follow_the_lives_left() {
+        let mut app = test_app();
+        app.world_mut().resource_mut::<Players>().0[0].lives = 2;
+        step(&mut app, 2);
+
+        let world = app.world_mut();
+        let icons = world
+            .query::<(&HudIcons, &Children)>()
+            .iter(world)
+            .find(|(icons, _)| matches!(icons.kind, IconKind::Life))
+            .map(|(_, children)| children.len());
+        assert_eq!(icons, Some(2));
+    }
+
+    #[test]
+    fn weapon_level_stays_hidden_until_something_raises_it() {
+        let mut app = test_app();
+        step(&mut app, 2);
+        let shown = hud_text(&mut app, |field| matches!(field, HudText::Weapon));
+        assert_eq!(shown, "");
+
+        app.world_mut().resource_mut::<Players>().0[0].weapon_level = 2;
+        step(&mut app, 2);
+        let shown = hud_text(&mut app, |field| matches!(field, HudText::Weapon));
+        assert_eq!(shown, "PWR 2");
+    }
+}
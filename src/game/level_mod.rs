@@ -0,0 +1,191 @@
+use std::fmt;
+
+use bevy::{
+    app::{App, Plugin, Startup},
+    asset::{io::Reader, Asset, AssetApp, AssetLoader, AssetServer, Handle, LoadContext},
+    prelude::{Commands, Res, Resource},
+    reflect::TypePath,
+};
+use serde::Deserialize;
+
+use crate::constants::LEVEL_FILES;
+
+use super::{
+    background_mod::{BackgroundLayerDef, SetPieceDef},
+    music_mod::Song,
+};
+
+pub struct LevelPlugin;
+
+// A level file, e.g. `assets/levels/01_outer_rim.level.ron`.
+#[derive(Asset, TypePath, Deserialize)]
+pub struct Level {
+    pub name: String,
+    #[serde(default)]
+    pub backdrop: Vec<BackgroundLayerDef>,
+    #[serde(default)]
+    pub set_pieces: Vec<SetPieceDef>,
+    // played instead of the default game music while the level is on
+    #[serde(default)]
+    pub music: Option<Song>,
+}
+
+impl Level {
+    // Set pieces pick their interval and scale with `gen_range`, which panics on a
+    // reversed or empty range, so bad ranges are refused when the file is loaded.
+    pub fn validate(&self) -> Result<(), LevelLoaderError> {
+        for (index, piece) in self.set_pieces.iter().enumerate() {
+            positive_range(format!("set_pieces[{index}].interval"), piece.interval)?;
+            positive_range(format!("set_pieces[{index}].scale"), piece.scale)?;
+        }
+        Ok(())
+    }
+}
+
+fn positive_range(field: String, (low, high): (f32, f32)) -> Result<(), LevelLoaderError> {
+    if !(low.is_finite() && high.is_finite() && low > 0.0) {
+        return Err(LevelLoaderError::Invalid {
+            field,
+            reason: format!("must be numbers above 0, got ({low}, {high})"),
+        });
+    }
+    if low > high {
+        return Err(LevelLoaderError::Invalid {
+            field,
+            reason: format!("the low end comes first, got ({low}, {high})"),
+        });
+    }
+    Ok(())
+}
+
+#[derive(Resource)]
+pub struct LevelHandles(pub Vec<Handle<Level>>);
+
+// Index into `LevelHandles` of the level being played.
+#[derive(Resource, Default)]
+pub struct CurrentLevel(pub usize);
+
+impl CurrentLevel {
+    pub fn handle<'a>(&self, handles: &'a LevelHandles) -> Option<&'a Handle<Level>> {
+        handles.0.get(self.0)
+    }
+}
+
+#[derive(Debug)]
+pub enum LevelLoaderError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+    Invalid { field: String, reason: String },
+}
+
+impl fmt::Display for LevelLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelLoaderError::Io(error) => write!(f, "could not read level file: {error}"),
+            LevelLoaderError::Ron(error) => write!(f, "could not parse level file: {error}"),
+            LevelLoaderError::Invalid { field, reason } => {
+                write!(f, "invalid level file, {field} {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for LevelLoaderError {}
+
+impl From<std::io::Error> for LevelLoaderError {
+    fn from(error: std::io::Error) -> Self {
+        LevelLoaderError::Io(error)
+    }
+}
+
+impl From<ron::error::SpannedError> for LevelLoaderError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        LevelLoaderError::Ron(error)
+    }
+}
+
+#[derive(Default)]
+struct LevelLoader;
+
+impl AssetLoader for LevelLoader {
+    type Asset = Level;
+    type Settings = ();
+    type Error = LevelLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Level, LevelLoaderError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let level = ron::de::from_bytes::<Level>(&bytes)?;
+        level.validate()?;
+        Ok(level)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level.ron"]
+    }
+}
+
+fn load_levels(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let handles = LEVEL_FILES
+        .iter()
+        .map(|path| asset_server.load(*path))
+        .collect();
+    commands.insert_resource(LevelHandles(handles));
+}
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Level>();
+        app.init_asset_loader::<LevelLoader>();
+        app.init_resource::<CurrentLevel>();
+        app.add_systems(Startup, load_levels);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level_with_set_piece(interval: &str, scale: &str) -> Level {
+        ron::de::from_str(&format!(
+            r#"(
+                name: "Test",
+                set_pieces: [
+                    (image: "backgrounds/debris.png", scroll_speed: 10.0, interval: {interval}, scale: {scale}),
+                ],
+            )"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn shipped_levels_are_valid() {
+        for path in LEVEL_FILES {
+            let text = std::fs::read_to_string(format!("assets/{path}")).unwrap();
+            let level: Level = ron::de::from_str(&text).unwrap();
+            assert!(level.validate().is_ok(), "{path} should be valid");
+        }
+    }
+
+    #[test]
+    fn set_piece_ranges_that_would_panic_are_refused() {
+        assert!(level_with_set_piece("(2.0, 6.0)", "(0.8, 1.6)")
+            .validate()
+            .is_ok());
+        for (interval, scale, bad_field) in [
+            ("(6.0, 2.0)", "(0.8, 1.6)", "set_pieces[0].interval"),
+            ("(-1.0, 6.0)", "(0.8, 1.6)", "set_pieces[0].interval"),
+            ("(2.0, 6.0)", "(1.6, 0.8)", "set_pieces[0].scale"),
+        ] {
+            match level_with_set_piece(interval, scale).validate() {
+                Err(LevelLoaderError::Invalid { field, .. }) => assert_eq!(field, bad_field),
+                _ => panic!("interval {interval} and scale {scale} should be refused"),
+            }
+        }
+    }
+}
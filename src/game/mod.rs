@@ -1,23 +1,62 @@
+pub mod attack_mod;
+mod background_mod;
+pub mod camera_effects_mod;
+pub mod daily_mod;
+mod endless_mod;
 mod eneymy_mod;
-mod event_handler_mod;
-mod player_jet_mod;
+pub mod highscore_mod;
+mod hud_mod;
+pub mod level_mod;
+mod music_mod;
+pub mod net_mod;
+mod particle_mod;
+pub mod pause_mod;
+pub mod player_jet_mod;
+pub mod playfield_mod;
+pub mod replay_mod;
+pub mod rng_mod;
+pub mod rollback_mod;
+pub mod scoring_mod;
+pub mod sfx_mod;
 mod space_point_plugin_mod;
+pub mod toast_mod;
+pub mod tuning_mod;
+pub mod versus_mod;
 
 use std::default;
 
-use crate::GameState;
+use crate::{constants::MAX_PLAYERS, GameState};
+use attack_mod::AttackPlugin;
+use background_mod::BackgroundPlugin;
 use bevy::{log::Level, prelude::*};
+use camera_effects_mod::CameraEffectsPlugin;
+use daily_mod::DailyPlugin;
+use endless_mod::EndlessPlugin;
 use eneymy_mod::EnemyPlugin;
-use event_handler_mod::EventHandlerPlugin;
+use highscore_mod::HighScorePlugin;
+use hud_mod::HudPlugin;
+use level_mod::LevelPlugin;
+use music_mod::MusicPlugin;
+use net_mod::{NetPlugin, NetSession};
+use particle_mod::ParticlePlugin;
+use pause_mod::PausePlugin;
 use player_jet_mod::{GameEntity, JetPlugin};
+use playfield_mod::PlayfieldPlugin;
+use replay_mod::{FixedTick, ReplayPlayback, ReplayPlugin};
+use rng_mod::RngPlugin;
+use rollback_mod::{RollbackApp, RollbackPlugin};
+use scoring_mod::ScoringPlugin;
+use serde::{Deserialize, Serialize};
+use sfx_mod::SfxPlugin;
+use space_point_plugin_mod::SpacePointPlugin;
+use toast_mod::ToastPlugin;
+use tuning_mod::TuningPlugin;
+use versus_mod::VersusPlugin;
 
 pub const MY_ORANGE: Color = Color::srgb(222.0 / 255.0, 112.0 / 255.0, 40.0 / 255.0);
 
-#[derive(Component)]
-pub struct LevelText;
-
-#[derive(Resource)]
-pub struct Score(usize);
+#[derive(Resource, Clone)]
+pub struct Score(pub usize);
 
 impl Default for Score {
     fn default() -> Self {
@@ -25,21 +64,182 @@ impl Default for Score {
     }
 }
 
+// How many jets take part in a run, one per player on the same screen.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PlayerCount(pub usize);
+
+impl Default for PlayerCount {
+    fn default() -> Self {
+        PlayerCount(1)
+    }
+}
+
+impl PlayerCount {
+    pub fn next(self) -> Self {
+        PlayerCount(self.0 % MAX_PLAYERS + 1)
+    }
+}
+
+// The rules a run is played with. High scores are kept per mode and difficulty.
+#[derive(Resource, Clone, Copy, Default, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum GameMode {
+    #[default]
+    Campaign,
+    // enemies keep coming, faster and tougher the longer the run lasts
+    Endless,
+    // as many points as possible before the clock runs out
+    ScoreAttack2,
+    ScoreAttack5,
+    // a fixed list of waves, cleared as fast as possible
+    TimeAttack,
+    // two players side by side, each on their own half of the playfield
+    Versus,
+    // campaign rules on a seed and modifiers picked by the date
+    Daily,
+}
+
+impl GameMode {
+    pub fn next(self) -> Self {
+        match self {
+            GameMode::Campaign => GameMode::Endless,
+            GameMode::Endless => GameMode::ScoreAttack2,
+            GameMode::ScoreAttack2 => GameMode::ScoreAttack5,
+            GameMode::ScoreAttack5 => GameMode::TimeAttack,
+            GameMode::TimeAttack => GameMode::Versus,
+            GameMode::Versus | GameMode::Daily => GameMode::Campaign,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GameMode::Campaign => "Campaign",
+            GameMode::Endless => "Endless",
+            GameMode::ScoreAttack2 => "Score Attack 2:00",
+            GameMode::ScoreAttack5 => "Score Attack 5:00",
+            GameMode::TimeAttack => "Time Attack",
+            GameMode::Versus => "Versus",
+            GameMode::Daily => "Daily Challenge",
+        }
+    }
+
+    // A versus score only counts against the rival, not on a table. A daily
+    // challenge keeps its own result per day instead.
+    pub fn has_high_scores(self) -> bool {
+        !matches!(self, GameMode::Versus | GameMode::Daily)
+    }
+
+    // Time attack is won by the quickest clear rather than the most points.
+    pub fn ranks_by_time(self) -> bool {
+        self == GameMode::TimeAttack
+    }
+
+    // How long a score attack run lasts.
+    pub fn time_limit_seconds(self) -> Option<u32> {
+        match self {
+            GameMode::ScoreAttack2 => Some(120),
+            GameMode::ScoreAttack5 => Some(300),
+            _ => None,
+        }
+    }
+
+    // The original loop of one enemy at a time, replaced as soon as it dies.
+    // The other modes bring their enemies in themselves.
+    pub fn keeps_one_enemy_up(self) -> bool {
+        !matches!(self, GameMode::Endless | GameMode::TimeAttack)
+    }
+}
+
+#[derive(Resource, Clone, Copy, Default, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn next(self) -> Self {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Easy,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+        }
+    }
+
+    pub fn enemy_health(self, base: i32) -> i32 {
+        match self {
+            Difficulty::Easy => base / 2,
+            Difficulty::Normal => base,
+            Difficulty::Hard => base * 3 / 2,
+        }
+    }
+}
+
 pub fn game_plugin(app: &mut App) {
     app.init_resource::<Score>()
-        .add_systems(Startup, setup_text)
-        .add_plugins((JetPlugin, EnemyPlugin, EventHandlerPlugin))
+        .init_resource::<GameMode>()
+        .init_resource::<Difficulty>()
+        .init_resource::<PlayerCount>()
+        .rollback_resource::<Score>()
+        .add_systems(OnEnter(GameState::Game), reset_score)
+        .add_plugins((
+            TuningPlugin,
+            JetPlugin,
+            EnemyPlugin,
+            ParticlePlugin,
+            CameraEffectsPlugin,
+            SpacePointPlugin,
+            LevelPlugin,
+            BackgroundPlugin,
+            PlayfieldPlugin,
+            RngPlugin,
+            ReplayPlugin,
+            HighScorePlugin,
+            ScoringPlugin,
+        ))
+        .add_plugins((
+            HudPlugin,
+            ToastPlugin,
+            SfxPlugin,
+            MusicPlugin,
+            PausePlugin,
+            RollbackPlugin,
+            NetPlugin,
+            VersusPlugin,
+            EndlessPlugin,
+            AttackPlugin,
+            DailyPlugin,
+        ))
         .add_systems(OnExit(GameState::Game), despawn_game);
 }
 
+// Gameplay runs on the fixed tick, which already stops while time is paused.
+// A replay also stops it on its last recorded tick, even if the fixed loop has
+// more ticks to run before the state change lands. An online run holds when it
+// would have to guess the other player's input too far ahead.
+pub fn gameplay_running(
+    playback: Option<Res<ReplayPlayback>>,
+    net: Option<Res<NetSession>>,
+    tick: Res<FixedTick>,
+) -> bool {
+    playback.is_none_or(|playback| !playback.finished(tick.0))
+        && net.is_none_or(|net| net.can_advance(tick.0))
+}
+
 fn despawn_game(mut commands: Commands, mut query: Query<Entity, With<GameEntity>>) {
     for entity in &mut query {
-        commands.entity(entity).despawn();
+        commands.entity(entity).despawn_recursive();
     }
 }
 
-fn setup_text(mut commands: Commands, asset_server: Res<AssetServer>, score: Res<Score>) {
-    commands
-        .spawn((Text::new(format!("Score : ")), LevelText))
-        .with_child(TextSpan::new("0"));
+fn reset_score(mut score: ResMut<Score>) {
+    score.0 = 0;
 }
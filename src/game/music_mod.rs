@@ -0,0 +1,240 @@
+use bevy::{
+    app::{App, Plugin, Update},
+    asset::{AssetServer, Assets, Handle},
+    audio::{AudioPlayer, AudioSink, AudioSinkPlayback, AudioSource, PlaybackSettings, Volume},
+    prelude::{
+        Commands, Component, DespawnRecursiveExt, Entity, IntoSystemConfigs, Local, Query, Res,
+        State,
+    },
+    time::{Real, Time},
+};
+use serde::Deserialize;
+
+use crate::{
+    constants::{MAX_VOLUME, MUSIC_CROSSFADE_SECONDS, MUSIC_DUCK},
+    GameState, MusicVolume,
+};
+
+use super::{
+    level_mod::{CurrentLevel, Level, LevelHandles},
+    pause_mod::Paused,
+};
+
+pub struct MusicPlugin;
+
+// A piece of music: an optional intro played once, then a section that loops
+// for as long as the song is wanted.
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+pub struct Song {
+    #[serde(default)]
+    pub intro: Option<String>,
+    pub looped: String,
+}
+
+impl Song {
+    fn looped(looped: &str) -> Self {
+        Song {
+            intro: None,
+            looped: looped.to_string(),
+        }
+    }
+}
+
+fn menu_song() -> Song {
+    Song {
+        intro: Some("music/menu_intro.wav".to_string()),
+        looped: "music/menu_loop.wav".to_string(),
+    }
+}
+
+// A song that is playing, fading in towards `target` or out to silence.
+#[derive(Component)]
+struct MusicTrack {
+    song: Song,
+    looped: Handle<AudioSource>,
+    in_intro: bool,
+    gain: f32,
+    target: f32,
+}
+
+// Levels can bring their own song, everything else uses the one for the state.
+fn wanted_song(
+    state: GameState,
+    current_level: &CurrentLevel,
+    level_handles: Option<&LevelHandles>,
+    levels: &Assets<Level>,
+) -> Option<Song> {
+    match state {
+        GameState::Splash | GameState::Loading => None,
+        GameState::Menu | GameState::Lobby | GameState::Replays | GameState::HighScores => {
+            Some(menu_song())
+        }
+        GameState::GameOver => Some(Song::looped("music/game_over_loop.wav")),
+        GameState::Game => {
+            let level_song = level_handles
+                .and_then(|handles| current_level.handle(handles))
+                .and_then(|handle| levels.get(handle))
+                .and_then(|level| level.music.clone());
+            Some(level_song.unwrap_or_else(|| Song::looped("music/outer_rim_loop.wav")))
+        }
+    }
+}
+
+fn choose_song(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    state: Res<State<GameState>>,
+    current_level: Res<CurrentLevel>,
+    level_handles: Option<Res<LevelHandles>>,
+    levels: Res<Assets<Level>>,
+    mut tracks: Query<&mut MusicTrack>,
+) {
+    let wanted = wanted_song(
+        *state.get(),
+        &current_level,
+        level_handles.as_deref(),
+        &levels,
+    );
+    let mut found = false;
+    for mut track in &mut tracks {
+        // a song that is fading out comes back if it is wanted again
+        if Some(&track.song) == wanted.as_ref() {
+            track.target = 1.;
+            found = true;
+        } else {
+            track.target = 0.;
+        }
+    }
+    let Some(song) = wanted.filter(|_| !found) else {
+        return;
+    };
+    let looped = asset_server.load(&song.looped);
+    let (first, settings) = match &song.intro {
+        Some(intro) => (asset_server.load(intro), PlaybackSettings::ONCE),
+        None => (looped.clone(), PlaybackSettings::LOOP),
+    };
+    commands.spawn((
+        MusicTrack {
+            in_intro: song.intro.is_some(),
+            song,
+            looped,
+            gain: 0.,
+            target: 1.,
+        },
+        AudioPlayer::new(first),
+        // starts silent and fades in
+        settings.with_volume(Volume::ZERO),
+    ));
+}
+
+// Swaps a finished intro for the looping section. Taking the sink away makes
+// the audio plugin start the new source.
+fn advance_intros(
+    mut commands: Commands,
+    mut tracks: Query<(Entity, &mut MusicTrack, &AudioSink)>,
+) {
+    for (entity, mut track, sink) in &mut tracks {
+        if track.in_intro && sink.empty() {
+            track.in_intro = false;
+            commands.entity(entity).remove::<AudioSink>().insert((
+                AudioPlayer::new(track.looped.clone()),
+                PlaybackSettings::LOOP.with_volume(Volume::new(sink.volume())),
+            ));
+        }
+    }
+}
+
+// Fades run on real time so they still finish while the game is paused.
+fn fade_music(
+    mut commands: Commands,
+    mut tracks: Query<(Entity, &mut MusicTrack, Option<&AudioSink>)>,
+    paused: Option<Res<Paused>>,
+    music_volume: Option<Res<MusicVolume>>,
+    time: Res<Time<Real>>,
+    mut duck: Local<Option<f32>>,
+) {
+    let step = time.delta_secs() / MUSIC_CROSSFADE_SECONDS;
+    let duck_target = if paused.is_some_and(|paused| paused.0) {
+        MUSIC_DUCK
+    } else {
+        1.
+    };
+    let duck = duck.get_or_insert(duck_target);
+    *duck = move_towards(*duck, duck_target, step);
+    let master = music_volume.map_or(1., |volume| volume.0 as f32 / MAX_VOLUME as f32);
+    for (entity, mut track, sink) in &mut tracks {
+        track.gain = move_towards(track.gain, track.target, step);
+        if track.target == 0. && track.gain == 0. {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        if let Some(sink) = sink {
+            sink.set_volume(track.gain * *duck * master);
+        }
+    }
+}
+
+fn move_towards(current: f32, target: f32, step: f32) -> f32 {
+    if current < target {
+        (current + step).min(target)
+    } else {
+        (current - step).max(target)
+    }
+}
+
+impl Plugin for MusicPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (choose_song, advance_intros, fade_music).chain());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{app::App, prelude::NextState};
+
+    use crate::{
+        test_support::{step, test_app},
+        GameState,
+    };
+
+    use super::MusicTrack;
+
+    fn tracks(app: &mut App) -> Vec<(String, f32)> {
+        let world = app.world_mut();
+        world
+            .query::<&MusicTrack>()
+            .iter(world)
+            .map(|track| (track.song.looped.clone(), track.target))
+            .collect()
+    }
+
+    #[test]
+    fn leaving_the_run_crossfades_to_the_game_over_song() {
+        let mut app = test_app();
+        step(&mut app, 60);
+        assert_eq!(
+            tracks(&mut app),
+            vec![("music/outer_rim_loop.wav".to_string(), 1.)]
+        );
+
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::GameOver);
+        step(&mut app, 2);
+        let mut playing = tracks(&mut app);
+        playing.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            playing,
+            vec![
+                ("music/game_over_loop.wav".to_string(), 1.),
+                ("music/outer_rim_loop.wav".to_string(), 0.),
+            ]
+        );
+
+        step(&mut app, 120);
+        assert_eq!(
+            tracks(&mut app),
+            vec![("music/game_over_loop.wav".to_string(), 1.)]
+        );
+    }
+}
@@ -0,0 +1,696 @@
+use std::{
+    collections::VecDeque,
+    fmt, io,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    time::{Duration, Instant},
+};
+
+use bevy::{
+    app::{App, Plugin, PostUpdate, PreUpdate, Update},
+    input::ButtonInput,
+    prelude::{
+        in_state, resource_exists, Commands, Condition, IntoSystemConfigs, KeyCode, NextState,
+        OnExit, Res, ResMut, Resource, World,
+    },
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    constants::{
+        MAX_PLAYERS, NET_HELLO_SECONDS, NET_INPUT_DELAY_TICKS, NET_MAX_INPUTS_PER_PACKET,
+        NET_MAX_PREDICTION_TICKS, NET_PACKET_BYTES, NET_TIMEOUT_SECONDS, TUNING_FILE,
+    },
+    GameState,
+};
+
+use super::{
+    player_jet_mod::Players,
+    playfield_mod::Playfield,
+    replay_mod::{FixedTick, PlayerInput, PlayerInputs},
+    rng_mod::GameRng,
+    rollback_mod::{roll_back, Snapshots},
+    toast_mod::ErrorToast,
+    Difficulty, GameMode, PlayerCount,
+};
+
+pub struct NetPlugin;
+
+// Fakes a bad connection on everything this game sends, for trying online
+// play on one machine. Set with `--net-latency MS` and `--net-loss PERCENT`.
+#[derive(Resource, Clone, Copy, Default, Debug, PartialEq)]
+pub struct NetShim {
+    pub latency: Duration,
+    // share of packets dropped, from 0 to 1
+    pub loss: f32,
+}
+
+impl NetShim {
+    // Takes the shim's flags out of the command line and hands back the rest.
+    pub fn from_args(
+        args: impl IntoIterator<Item = String>,
+    ) -> Result<(Self, Vec<String>), String> {
+        let mut shim = NetShim::default();
+        let mut rest = Vec::new();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--net-latency" => {
+                    let value = args.next().ok_or("--net-latency needs a value")?;
+                    let millis = value
+                        .parse()
+                        .map_err(|_| format!("invalid latency '{value}', expected milliseconds"))?;
+                    shim.latency = Duration::from_millis(millis);
+                }
+                "--net-loss" => {
+                    let value = args.next().ok_or("--net-loss needs a value")?;
+                    let percent: f32 = value
+                        .parse()
+                        .ok()
+                        .filter(|percent| (0.0..=100.0).contains(percent))
+                        .ok_or(format!(
+                            "invalid packet loss '{value}', expected a percentage"
+                        ))?;
+                    shim.loss = percent / 100.;
+                }
+                _ => rest.push(arg),
+            }
+        }
+        Ok((shim, rest))
+    }
+}
+
+// What both sides have to agree on before a run starts. The host picks it.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct NetRun {
+    pub seed: u64,
+    pub mode: GameMode,
+    pub difficulty: Difficulty,
+    pub playfield: (f32, f32),
+    // `GameTuning::checksum`, both sides have to play with the same numbers
+    pub tuning: u64,
+}
+
+impl NetRun {
+    pub fn playfield(&self) -> Playfield {
+        Playfield {
+            width: self.playfield.0,
+            height: self.playfield.1,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+enum NetMessage {
+    // someone who wants to join, repeated until the host answers, with the
+    // checksum of their tuning
+    Hello {
+        tuning: u64,
+    },
+    Welcome(NetRun),
+    // the host won't play with someone on other tuning
+    Refused,
+    // the sender's inputs from `first_tick` on, and how many of the
+    // receiver's inputs it has so far
+    Inputs {
+        first_tick: u32,
+        inputs: Vec<PlayerInput>,
+        ack: u32,
+    },
+    Bye,
+}
+
+#[derive(Debug)]
+pub enum NetError {
+    Io(io::Error),
+    Address(String),
+    Tuning,
+    Unplayable,
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetError::Io(error) => write!(f, "network error: {error}"),
+            NetError::Address(address) => write!(f, "'{address}' is not an IPv4 address:port"),
+            NetError::Tuning => write!(f, "the other player's {TUNING_FILE} is different"),
+            NetError::Unplayable => write!(f, "the host offered a run this game can't play"),
+        }
+    }
+}
+
+impl std::error::Error for NetError {}
+
+impl From<io::Error> for NetError {
+    fn from(error: io::Error) -> Self {
+        NetError::Io(error)
+    }
+}
+
+// A non-blocking UDP socket whose outgoing packets go through the shim.
+struct Transport {
+    socket: UdpSocket,
+    shim: NetShim,
+    // packets the shim is holding back, with when they may go out
+    delayed: VecDeque<(Instant, SocketAddr, Vec<u8>)>,
+}
+
+impl Transport {
+    fn bind(address: SocketAddr, shim: NetShim) -> io::Result<Self> {
+        let socket = UdpSocket::bind(address)?;
+        socket.set_nonblocking(true)?;
+        Ok(Transport {
+            socket,
+            shim,
+            delayed: VecDeque::new(),
+        })
+    }
+
+    fn send(&mut self, to: SocketAddr, message: &NetMessage) {
+        let Ok(text) = ron::to_string(message) else {
+            return;
+        };
+        if rand::thread_rng().gen::<f32>() < self.shim.loss {
+            return;
+        }
+        self.delayed
+            .push_back((Instant::now() + self.shim.latency, to, text.into_bytes()));
+        self.flush();
+    }
+
+    // Sends what the shim has held back for long enough. A send that fails is
+    // a lost packet, which the protocol already copes with.
+    fn flush(&mut self) {
+        let now = Instant::now();
+        while let Some((due, to, bytes)) = self.delayed.front() {
+            if *due > now {
+                break;
+            }
+            let _ = self.socket.send_to(bytes, to);
+            self.delayed.pop_front();
+        }
+    }
+
+    fn receive(&mut self) -> Vec<(SocketAddr, NetMessage)> {
+        let mut buffer = [0; NET_PACKET_BYTES];
+        let mut received = Vec::new();
+        while let Ok((length, from)) = self.socket.recv_from(&mut buffer) {
+            // anything that isn't one of ours is ignored
+            if let Ok(message) = ron::de::from_bytes(&buffer[..length]) {
+                received.push((from, message));
+            }
+        }
+        received
+    }
+}
+
+// Either side of an online run: hosting on a port or joining an address.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum NetEndpoint {
+    Host(u16),
+    Join(String),
+}
+
+// A socket looking for the other player before the run starts.
+#[derive(Resource)]
+pub struct NetLink {
+    transport: Transport,
+    host: bool,
+    // the host doesn't know who it plays with until someone says hello
+    peer: Option<SocketAddr>,
+    next_hello: Instant,
+}
+
+impl NetLink {
+    pub fn open(endpoint: &NetEndpoint, shim: NetShim) -> Result<Self, NetError> {
+        let (bind, host, peer) = match endpoint {
+            NetEndpoint::Host(port) => (SocketAddr::from(([0, 0, 0, 0], *port)), true, None),
+            NetEndpoint::Join(address) => {
+                let peer = address
+                    .to_socket_addrs()
+                    .ok()
+                    .and_then(|mut addresses| addresses.find(SocketAddr::is_ipv4))
+                    .ok_or(NetError::Address(address.clone()))?;
+                (SocketAddr::from(([0, 0, 0, 0], 0)), false, Some(peer))
+            }
+        };
+        Ok(NetLink {
+            transport: Transport::bind(bind, shim)?,
+            host,
+            peer,
+            next_hello: Instant::now(),
+        })
+    }
+
+    pub fn is_host(&self) -> bool {
+        self.host
+    }
+
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.transport.socket.local_addr().ok()
+    }
+
+    fn send(&mut self, message: &NetMessage) {
+        if let Some(peer) = self.peer {
+            self.transport.send(peer, message);
+        }
+    }
+
+    // Moves the handshake along. Once the other side has answered it returns
+    // the run to play, which is `run` for the host and whatever the host sent
+    // for the one joining. Either side gives up if the tuning doesn't match.
+    pub fn connect(&mut self, run: &NetRun) -> Result<Option<NetRun>, NetError> {
+        for (from, message) in self.transport.receive() {
+            match message {
+                // the host stays open for someone else
+                NetMessage::Hello { tuning } if self.host && tuning != run.tuning => {
+                    self.send_now(from, &NetMessage::Refused);
+                    return Err(NetError::Tuning);
+                }
+                NetMessage::Hello { .. } if self.host => {
+                    self.peer = Some(from);
+                    self.send(&NetMessage::Welcome(*run));
+                    return Ok(Some(*run));
+                }
+                NetMessage::Welcome(offer) if !self.host && Some(from) == self.peer => {
+                    if offer.tuning != run.tuning {
+                        return Err(NetError::Tuning);
+                    }
+                    if !offer.playfield().is_playable() {
+                        return Err(NetError::Unplayable);
+                    }
+                    return Ok(Some(offer));
+                }
+                NetMessage::Refused if !self.host && Some(from) == self.peer => {
+                    return Err(NetError::Tuning);
+                }
+                _ => {}
+            }
+        }
+        if !self.host && Instant::now() >= self.next_hello {
+            self.next_hello = Instant::now() + Duration::from_secs_f32(NET_HELLO_SECONDS);
+            self.send(&NetMessage::Hello { tuning: run.tuning });
+        }
+        self.transport.flush();
+        Ok(None)
+    }
+
+    // Sent straight away rather than through the shim, for when the socket
+    // may be about to close.
+    fn send_now(&self, to: SocketAddr, message: &NetMessage) {
+        if let Ok(text) = ron::to_string(message) {
+            let _ = self.transport.socket.send_to(text.as_bytes(), to);
+        }
+    }
+
+    fn say_bye(&self) {
+        if let Some(peer) = self.peer {
+            self.send_now(peer, &NetMessage::Bye);
+        }
+    }
+}
+
+// The online run in progress. The host plays the first jet, the one who
+// joined the second, each with the first player's controls.
+#[derive(Resource)]
+pub struct NetSession {
+    link: NetLink,
+    run: NetRun,
+    pub local_player: usize,
+    // what this side held, by the tick it takes effect on
+    local_inputs: Vec<PlayerInput>,
+    // what the other side held, by tick, as far as it has arrived
+    remote_inputs: Vec<PlayerInput>,
+    // what the simulation went with for the other side, by tick
+    remote_used: Vec<PlayerInput>,
+    // how many of this side's inputs the other side has
+    remote_ack: u32,
+    // the earliest tick that was simulated with a wrong guess
+    rollback_to: Option<u32>,
+    last_heard: Instant,
+    // the simulation holds at this tick, so headless runs can compare results
+    stop_at: Option<u32>,
+    pub rollbacks: u32,
+}
+
+impl NetSession {
+    pub fn new(link: NetLink, run: NetRun) -> Self {
+        NetSession {
+            local_player: if link.host { 0 } else { 1 },
+            link,
+            run,
+            // nobody moves during the delay at the start
+            local_inputs: vec![PlayerInput::default(); NET_INPUT_DELAY_TICKS as usize],
+            remote_inputs: Vec::new(),
+            remote_used: Vec::new(),
+            remote_ack: 0,
+            rollback_to: None,
+            last_heard: Instant::now(),
+            stop_at: None,
+            rollbacks: 0,
+        }
+    }
+
+    fn remote_player(&self) -> usize {
+        MAX_PLAYERS - 1 - self.local_player
+    }
+
+    pub fn stop_at(&mut self, tick: u32) {
+        self.stop_at = Some(tick);
+    }
+
+    // Whether `tick` can be simulated without guessing the other side's input
+    // too far ahead.
+    pub fn can_advance(&self, tick: u32) -> bool {
+        tick < self.remote_inputs.len() as u32 + NET_MAX_PREDICTION_TICKS
+            && self.stop_at.is_none_or(|stop| tick < stop)
+    }
+
+    // Whether every tick before `tick` was simulated with the real inputs.
+    pub fn confirmed_through(&self, tick: u32) -> bool {
+        self.remote_inputs.len() as u32 >= tick
+    }
+
+    // Both sides have reached the stop tick with nothing left to roll back.
+    pub fn settled(&self, tick: u32) -> bool {
+        self.stop_at == Some(tick) && self.confirmed_through(tick) && self.remote_ack >= tick
+    }
+
+    // The inputs to simulate `tick` with. `sampled` is what this side holds
+    // right now, it only counts the first time a tick is simulated.
+    pub fn inputs_at(&mut self, tick: u32, sampled: PlayerInput) -> PlayerInputs {
+        let tick = tick as usize;
+        if self.local_inputs.len() == tick + NET_INPUT_DELAY_TICKS as usize {
+            self.local_inputs.push(sampled);
+        }
+        // the other side is assumed to keep holding what it held last
+        let remote = self
+            .remote_inputs
+            .get(tick)
+            .or(self.remote_inputs.last())
+            .copied()
+            .unwrap_or_default();
+        self.remote_used.truncate(tick);
+        self.remote_used.push(remote);
+        let mut inputs = PlayerInputs::default();
+        inputs.0[self.local_player] = self.local_inputs.get(tick).copied().unwrap_or_default();
+        inputs.0[self.remote_player()] = remote;
+        inputs
+    }
+
+    // Reads what arrived, returns whether the other side has left.
+    fn receive(&mut self) -> bool {
+        let mut left = false;
+        for (from, message) in self.link.transport.receive() {
+            if Some(from) != self.link.peer {
+                continue;
+            }
+            self.last_heard = Instant::now();
+            match message {
+                // the welcome got lost and the other side is still knocking
+                NetMessage::Hello { .. } => self.link.send(&NetMessage::Welcome(self.run)),
+                NetMessage::Welcome(_) | NetMessage::Refused => {}
+                NetMessage::Inputs {
+                    first_tick,
+                    inputs,
+                    ack,
+                } => self.take_inputs(first_tick, &inputs, ack),
+                NetMessage::Bye => left = true,
+            }
+        }
+        left
+    }
+
+    fn take_inputs(&mut self, first_tick: u32, inputs: &[PlayerInput], ack: u32) {
+        self.remote_ack = self.remote_ack.max(ack);
+        for (tick, input) in (first_tick as usize..).zip(inputs) {
+            // packets repeat what wasn't acknowledged yet, only the next tick is new
+            if tick != self.remote_inputs.len() {
+                continue;
+            }
+            if self.remote_used.get(tick).is_some_and(|used| used != input) {
+                let tick = tick as u32;
+                self.rollback_to = Some(self.rollback_to.map_or(tick, |first| first.min(tick)));
+            }
+            self.remote_inputs.push(*input);
+        }
+    }
+
+    // Sends every input the other side hasn't acknowledged yet, so a lost
+    // packet is made up for by the next one.
+    fn send_inputs(&mut self) {
+        let first_tick = (self.remote_ack as usize).min(self.local_inputs.len());
+        let last_tick = (first_tick + NET_MAX_INPUTS_PER_PACKET).min(self.local_inputs.len());
+        let message = NetMessage::Inputs {
+            first_tick: first_tick as u32,
+            inputs: self.local_inputs[first_tick..last_tick].to_vec(),
+            ack: self.remote_inputs.len() as u32,
+        };
+        self.link.send(&message);
+        self.link.transport.flush();
+    }
+}
+
+// Sets the world up for the run agreed on over `link`. Switching to
+// `GameState::Game` is left to the caller.
+pub fn insert_online_run(world: &mut World, link: NetLink, run: NetRun) {
+    world.insert_resource(GameRng::new(run.seed));
+    world.insert_resource(run.playfield());
+    world.insert_resource(run.mode);
+    world.insert_resource(run.difficulty);
+    world.insert_resource(PlayerCount(MAX_PLAYERS));
+    world.insert_resource(NetSession::new(link, run));
+    world.insert_resource(Snapshots::default());
+}
+
+// Takes in the other side's inputs. When one differs from the guess a tick
+// was simulated with, the run is rewound to that tick and played forward again.
+fn receive_inputs(world: &mut World) {
+    let (left, rollback_to, timed_out) = {
+        let mut session = world.resource_mut::<NetSession>();
+        let left = session.receive();
+        let timed_out = session.last_heard.elapsed().as_secs_f32() > NET_TIMEOUT_SECONDS;
+        (left, session.rollback_to.take(), timed_out)
+    };
+    if let Some(tick) = rollback_to {
+        if roll_back(world, tick) {
+            world.resource_mut::<NetSession>().rollbacks += 1;
+        } else {
+            println!("could not roll back to tick {tick}");
+            world.send_event(ErrorToast(
+                "online run out of sync, inputs arrived too late".to_string(),
+            ));
+        }
+    }
+    let ended = if left {
+        Some("the other player left")
+    } else if timed_out {
+        Some("lost the connection to the other player")
+    } else {
+        None
+    };
+    if let Some(reason) = ended {
+        println!("{reason}");
+        world.send_event(ErrorToast(reason.to_string()));
+        world
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::GameOver);
+    }
+}
+
+// Online the run doesn't end the moment everyone is down, only once no late
+// input can undo it.
+fn end_confirmed_run(
+    session: Res<NetSession>,
+    tick: Res<FixedTick>,
+    players: Res<Players>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    let all_down = !players.0.is_empty() && players.0.iter().all(|stats| stats.lives == 0);
+    if all_down && session.confirmed_through(tick.0) {
+        game_state.set(GameState::GameOver);
+    }
+}
+
+fn send_inputs(mut session: ResMut<NetSession>) {
+    session.send_inputs();
+}
+
+// There is no pausing an online run, Escape leaves it.
+fn leave_online_run(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        game_state.set(GameState::GameOver);
+    }
+}
+
+fn close_session(mut commands: Commands, session: Option<Res<NetSession>>) {
+    if let Some(session) = session {
+        println!("online run closed, {} rollbacks", session.rollbacks);
+        session.link.say_bye();
+    }
+    commands.remove_resource::<NetSession>();
+    commands.remove_resource::<Snapshots>();
+}
+
+impl Plugin for NetPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NetShim>();
+        app.add_systems(
+            PreUpdate,
+            (receive_inputs, end_confirmed_run)
+                .chain()
+                .run_if(in_state(GameState::Game).and(resource_exists::<NetSession>)),
+        );
+        app.add_systems(
+            Update,
+            leave_online_run.run_if(in_state(GameState::Game).and(resource_exists::<NetSession>)),
+        );
+        app.add_systems(
+            PostUpdate,
+            send_inputs.run_if(in_state(GameState::Game).and(resource_exists::<NetSession>)),
+        );
+        app.add_systems(OnExit(GameState::Game), close_session);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use bevy::{
+        app::App,
+        prelude::{KeyCode, Transform},
+    };
+
+    use crate::{
+        game::{
+            player_jet_mod::Jet, replay_mod::FixedTick, rollback_mod::state_checksum,
+            tuning_mod::GameTuning, Difficulty, GameMode,
+        },
+        headless::headless_app,
+        test_support::press,
+    };
+
+    use super::{insert_online_run, NetEndpoint, NetError, NetLink, NetRun, NetSession, NetShim};
+
+    const TICKS: u32 = 240;
+
+    fn settled(app: &App) -> bool {
+        let tick = app.world().resource::<FixedTick>().0;
+        app.world().resource::<NetSession>().settled(tick)
+    }
+
+    fn jet_x(app: &mut App, player: usize) -> f32 {
+        let world = app.world_mut();
+        world
+            .query::<(&Jet, &Transform)>()
+            .iter(world)
+            .find(|(jet, _)| jet.player == player)
+            .map(|(_, transform)| transform.translation.x)
+            .unwrap()
+    }
+
+    #[test]
+    fn two_peers_on_a_lossy_link_end_in_the_same_state() {
+        let shim = NetShim {
+            latency: Duration::from_millis(15),
+            loss: 0.2,
+        };
+        let host = NetLink::open(&NetEndpoint::Host(0), shim).unwrap();
+        let port = host.local_addr().unwrap().port();
+        let guest = NetLink::open(&NetEndpoint::Join(format!("127.0.0.1:{port}")), shim).unwrap();
+        let run = NetRun {
+            seed: 99,
+            mode: GameMode::Campaign,
+            difficulty: Difficulty::Normal,
+            playfield: (480., 640.),
+            tuning: GameTuning::default().checksum(),
+        };
+
+        // the host holds right, the guest left
+        let keys = [KeyCode::KeyD, KeyCode::KeyA];
+        let mut links = [Some(host), Some(guest)];
+        let mut apps: [Option<App>; 2] = [None, None];
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while !apps.iter().all(|app| app.as_ref().is_some_and(settled)) {
+            assert!(Instant::now() < deadline, "peers never settled");
+            for ((link, app), key) in links.iter_mut().zip(apps.iter_mut()).zip(keys) {
+                if let Some(app) = app {
+                    app.update();
+                    continue;
+                }
+                let Some(agreed) = link.as_mut().and_then(|link| link.connect(&run).unwrap())
+                else {
+                    continue;
+                };
+                let mut online = headless_app(agreed.playfield());
+                insert_online_run(online.world_mut(), link.take().unwrap(), agreed);
+                online
+                    .world_mut()
+                    .resource_mut::<NetSession>()
+                    .stop_at(TICKS);
+                press(&mut online, key);
+                *app = Some(online);
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        let [Some(mut host), Some(mut guest)] = apps else {
+            unreachable!();
+        };
+        assert_eq!(
+            state_checksum(host.world_mut()),
+            state_checksum(guest.world_mut())
+        );
+        assert!(jet_x(&mut host, 0) > jet_x(&mut host, 1));
+        assert_eq!(jet_x(&mut host, 0), jet_x(&mut guest, 0));
+    }
+
+    #[test]
+    fn peers_on_different_tuning_refuse_each_other() {
+        let shim = NetShim::default();
+        let mut host = NetLink::open(&NetEndpoint::Host(0), shim).unwrap();
+        let port = host.local_addr().unwrap().port();
+        let mut guest =
+            NetLink::open(&NetEndpoint::Join(format!("127.0.0.1:{port}")), shim).unwrap();
+        let run = NetRun {
+            seed: 99,
+            mode: GameMode::Campaign,
+            difficulty: Difficulty::Normal,
+            playfield: (480., 640.),
+            tuning: GameTuning::default().checksum(),
+        };
+        let mut tuning = GameTuning::default();
+        tuning.lives += 1;
+        let other = NetRun {
+            tuning: tuning.checksum(),
+            ..run
+        };
+
+        let mut results = [None, None];
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while results.iter().any(Option::is_none) {
+            assert!(Instant::now() < deadline, "peers never answered");
+            for ((link, run), result) in [(&mut host, &run), (&mut guest, &other)]
+                .into_iter()
+                .zip(results.iter_mut())
+            {
+                match link.connect(run) {
+                    Ok(None) => {}
+                    outcome if result.is_none() => *result = Some(outcome),
+                    _ => {}
+                }
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        for result in results {
+            assert!(matches!(result, Some(Err(NetError::Tuning))));
+        }
+    }
+}
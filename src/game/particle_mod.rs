@@ -0,0 +1,395 @@
+use std::f32::consts::PI;
+
+use bevy::{
+    app::{App, Plugin, Update},
+    color::{Color, Mix},
+    math::{Vec2, Vec3},
+    prelude::{
+        default, in_state, Added, Commands, Component, Entity, EventReader, IntoSystemConfigs,
+        Query, Res, ResMut, Resource, Transform, With, Without,
+    },
+    sprite::Sprite,
+    time::Time,
+};
+use rand::Rng;
+
+use crate::{
+    constants::{
+        Bullet, CollisionEvent, EnemyDestroyedEvent, GrazeEvent, PlayerHitEvent,
+        JET_SQUARE_BOX_LENGTH, MAX_PARTICLES,
+    },
+    GameState,
+};
+
+use super::{
+    player_jet_mod::{GameEntity, Jet},
+    rng_mod::{GameRng, RngStream},
+};
+
+pub struct ParticlePlugin;
+
+// Particles are simulated on the CPU and drawn as plain untextured sprites, so
+// they only need the default sprite pipeline and still render on software
+// adapters (llvmpipe, WARP, SwiftShader).
+#[derive(Resource)]
+pub struct ParticleSettings {
+    pub enabled: bool,
+    pub max_particles: usize,
+}
+
+impl Default for ParticleSettings {
+    fn default() -> Self {
+        ParticleSettings {
+            enabled: true,
+            max_particles: MAX_PARTICLES,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct EmitterConfig {
+    pub lifetime: (f32, f32),
+    pub speed: (f32, f32),
+    // base direction of travel and the half angle (radians) particles may deviate from it
+    pub direction: Vec2,
+    pub spread: f32,
+    pub drag: f32,
+    pub start_color: Color,
+    pub end_color: Color,
+    pub start_size: f32,
+    pub end_size: f32,
+    pub z: f32,
+}
+
+impl EmitterConfig {
+    pub fn explosion() -> Self {
+        EmitterConfig {
+            lifetime: (0.4, 0.9),
+            speed: (60., 220.),
+            direction: Vec2::Y,
+            spread: PI,
+            drag: 2.5,
+            start_color: Color::srgb(1.0, 0.85, 0.4),
+            end_color: Color::srgba(0.8, 0.1, 0.0, 0.0),
+            start_size: 9.,
+            end_size: 2.,
+            z: 1.,
+        }
+    }
+
+    pub fn debris() -> Self {
+        EmitterConfig {
+            lifetime: (0.8, 1.6),
+            speed: (30., 120.),
+            direction: Vec2::Y,
+            spread: PI,
+            drag: 0.8,
+            start_color: Color::srgb(0.6, 0.6, 0.65),
+            end_color: Color::srgba(0.3, 0.3, 0.35, 0.0),
+            start_size: 4.,
+            end_size: 3.,
+            z: 0.9,
+        }
+    }
+
+    pub fn sparks() -> Self {
+        EmitterConfig {
+            lifetime: (0.1, 0.3),
+            speed: (120., 260.),
+            direction: Vec2::NEG_Y,
+            spread: PI / 3.,
+            drag: 4.,
+            start_color: Color::srgb(1.0, 1.0, 0.7),
+            end_color: Color::srgba(1.0, 0.5, 0.1, 0.0),
+            start_size: 3.,
+            end_size: 1.,
+            z: 1.,
+        }
+    }
+
+    pub fn engine_trail() -> Self {
+        EmitterConfig {
+            lifetime: (0.2, 0.4),
+            speed: (80., 140.),
+            direction: Vec2::NEG_Y,
+            spread: PI / 12.,
+            drag: 1.,
+            start_color: Color::srgb(0.5, 0.8, 1.0),
+            end_color: Color::srgba(0.1, 0.2, 0.9, 0.0),
+            start_size: 5.,
+            end_size: 1.,
+            z: -0.5,
+        }
+    }
+
+    pub fn muzzle_flash() -> Self {
+        EmitterConfig {
+            lifetime: (0.05, 0.1),
+            speed: (20., 60.),
+            direction: Vec2::Y,
+            spread: PI / 4.,
+            drag: 0.,
+            start_color: Color::srgb(0.8, 0.9, 1.0),
+            end_color: Color::srgba(0.5, 0.5, 1.0, 0.0),
+            start_size: 8.,
+            end_size: 3.,
+            z: 0.5,
+        }
+    }
+}
+
+// Continuous emitter that follows the entity it is attached to.
+#[derive(Component)]
+pub struct ParticleEmitter {
+    pub config: EmitterConfig,
+    pub rate: f32,
+    pub offset: Vec2,
+    accumulator: f32,
+}
+
+impl ParticleEmitter {
+    pub fn new(config: EmitterConfig, rate: f32, offset: Vec2) -> Self {
+        ParticleEmitter {
+            config,
+            rate,
+            offset,
+            accumulator: 0.,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Particle {
+    velocity: Vec2,
+    age: f32,
+    lifetime: f32,
+    drag: f32,
+    start_color: Color,
+    end_color: Color,
+    start_size: f32,
+    end_size: f32,
+}
+
+pub fn spawn_burst(
+    commands: &mut Commands,
+    rng: &mut impl Rng,
+    config: &EmitterConfig,
+    position: Vec2,
+    count: usize,
+) {
+    let base_angle = config.direction.to_angle();
+    for _ in 0..count {
+        let angle = base_angle + rng.gen_range(-config.spread..=config.spread);
+        let speed = rng.gen_range(config.speed.0..=config.speed.1);
+        let lifetime = rng.gen_range(config.lifetime.0..=config.lifetime.1);
+        commands.spawn((
+            GameEntity,
+            Particle {
+                velocity: Vec2::from_angle(angle) * speed,
+                age: 0.,
+                lifetime,
+                drag: config.drag,
+                start_color: config.start_color,
+                end_color: config.end_color,
+                start_size: config.start_size,
+                end_size: config.end_size,
+            },
+            Sprite {
+                color: config.start_color,
+                custom_size: Some(Vec2::splat(config.start_size)),
+                ..default()
+            },
+            Transform::from_translation(position.extend(config.z)),
+        ));
+    }
+}
+
+// How many particles may still be spawned this frame without exceeding the budget.
+fn particle_budget(settings: &ParticleSettings, alive: usize, wanted: usize) -> usize {
+    if !settings.enabled {
+        return 0;
+    }
+    wanted.min(settings.max_particles.saturating_sub(alive))
+}
+
+fn emit_hit_sparks(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    settings: Res<ParticleSettings>,
+    mut rng: ResMut<GameRng>,
+    particles: Query<(), With<Particle>>,
+) {
+    let mut alive = particles.iter().count();
+    for event in collision_events.read() {
+        let count = particle_budget(&settings, alive, 6);
+        spawn_burst(
+            &mut commands,
+            rng.stream(RngStream::Particles),
+            &EmitterConfig::sparks(),
+            event.position,
+            count,
+        );
+        alive += count;
+    }
+}
+
+fn emit_enemy_explosion(
+    mut commands: Commands,
+    mut destroyed_events: EventReader<EnemyDestroyedEvent>,
+    settings: Res<ParticleSettings>,
+    mut rng: ResMut<GameRng>,
+    particles: Query<(), With<Particle>>,
+) {
+    let mut alive = particles.iter().count();
+    for event in destroyed_events.read() {
+        let count = particle_budget(&settings, alive, 40);
+        spawn_burst(
+            &mut commands,
+            rng.stream(RngStream::Particles),
+            &EmitterConfig::explosion(),
+            event.position,
+            count,
+        );
+        alive += count;
+        let count = particle_budget(&settings, alive, 12);
+        spawn_burst(
+            &mut commands,
+            rng.stream(RngStream::Particles),
+            &EmitterConfig::debris(),
+            event.position,
+            count,
+        );
+        alive += count;
+    }
+}
+
+fn emit_player_effects(
+    mut commands: Commands,
+    mut hit_events: EventReader<PlayerHitEvent>,
+    mut graze_events: EventReader<GrazeEvent>,
+    settings: Res<ParticleSettings>,
+    mut rng: ResMut<GameRng>,
+    particles: Query<(), With<Particle>>,
+) {
+    let mut alive = particles.iter().count();
+    for event in hit_events.read() {
+        let count = particle_budget(&settings, alive, 24);
+        spawn_burst(
+            &mut commands,
+            rng.stream(RngStream::Particles),
+            &EmitterConfig::debris(),
+            event.position,
+            count,
+        );
+        alive += count;
+    }
+    for event in graze_events.read() {
+        let count = particle_budget(&settings, alive, 3);
+        spawn_burst(
+            &mut commands,
+            rng.stream(RngStream::Particles),
+            &EmitterConfig::sparks(),
+            event.position,
+            count,
+        );
+        alive += count;
+    }
+}
+
+fn emit_muzzle_flash(
+    mut commands: Commands,
+    bullets: Query<&Transform, Added<Bullet>>,
+    settings: Res<ParticleSettings>,
+    mut rng: ResMut<GameRng>,
+    particles: Query<(), With<Particle>>,
+) {
+    let mut alive = particles.iter().count();
+    for transform in &bullets {
+        let count = particle_budget(&settings, alive, 4);
+        spawn_burst(
+            &mut commands,
+            rng.stream(RngStream::Particles),
+            &EmitterConfig::muzzle_flash(),
+            transform.translation.truncate(),
+            count,
+        );
+        alive += count;
+    }
+}
+
+fn attach_engine_trail(mut commands: Commands, jets: Query<Entity, Added<Jet>>) {
+    for jet in &jets {
+        commands.entity(jet).insert(ParticleEmitter::new(
+            EmitterConfig::engine_trail(),
+            40.,
+            Vec2::new(0., -JET_SQUARE_BOX_LENGTH / 2.),
+        ));
+    }
+}
+
+fn update_emitters(
+    mut commands: Commands,
+    mut emitters: Query<(&mut ParticleEmitter, &Transform), Without<Particle>>,
+    time: Res<Time>,
+    settings: Res<ParticleSettings>,
+    mut rng: ResMut<GameRng>,
+    particles: Query<(), With<Particle>>,
+) {
+    let mut alive = particles.iter().count();
+    for (mut emitter, transform) in &mut emitters {
+        emitter.accumulator += emitter.rate * time.delta_secs();
+        let wanted = emitter.accumulator.floor() as usize;
+        emitter.accumulator -= wanted as f32;
+        let count = particle_budget(&settings, alive, wanted);
+        spawn_burst(
+            &mut commands,
+            rng.stream(RngStream::Particles),
+            &emitter.config,
+            transform.translation.truncate() + emitter.offset,
+            count,
+        );
+        alive += count;
+    }
+}
+
+fn update_particles(
+    mut commands: Commands,
+    mut particles: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    let delta = time.delta_secs();
+    for (entity, mut particle, mut transform, mut sprite) in &mut particles {
+        particle.age += delta;
+        if particle.age >= particle.lifetime {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        let t = particle.age / particle.lifetime;
+        let drag = (1. - particle.drag * delta).max(0.);
+        particle.velocity *= drag;
+        transform.translation += Vec3::from((particle.velocity * delta, 0.));
+        sprite.color = particle.start_color.mix(&particle.end_color, t);
+        sprite.custom_size = Some(Vec2::splat(
+            particle.start_size + (particle.end_size - particle.start_size) * t,
+        ));
+    }
+}
+
+impl Plugin for ParticlePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ParticleSettings>();
+        app.add_systems(
+            Update,
+            (
+                attach_engine_trail,
+                emit_hit_sparks,
+                emit_enemy_explosion,
+                emit_player_effects,
+                emit_muzzle_flash,
+                update_emitters,
+                update_particles,
+            )
+                .run_if(in_state(GameState::Game)),
+        );
+    }
+}
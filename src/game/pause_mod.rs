@@ -0,0 +1,113 @@
+use bevy::{
+    app::{App, Plugin, Update},
+    color::Color,
+    input::ButtonInput,
+    prelude::{
+        in_state, not, resource_exists, BuildChildren, ChildBuild, Commands, Component, Condition,
+        DespawnRecursiveExt, Entity, FlexDirection, IntoSystemConfigs, KeyCode, NextState, Node,
+        OnEnter, Query, Res, ResMut, Resource, Text, TextColor, TextFont, Val, With,
+    },
+    time::{Time, Virtual},
+    ui::{AlignItems, BackgroundColor, JustifyContent},
+};
+
+use crate::GameState;
+
+use super::{
+    net_mod::NetSession, player_jet_mod::GameEntity, replay_mod::ReplayPlayback, MY_ORANGE,
+};
+
+pub struct PausePlugin;
+
+// Set while the pause menu is open during a run.
+#[derive(Resource, Default)]
+pub struct Paused(pub bool);
+
+#[derive(Component)]
+struct PauseMenu;
+
+fn reset_pause(mut paused: ResMut<Paused>) {
+    paused.0 = false;
+}
+
+// Escape opens and closes the pause menu, Q ends the run from it. Quitting
+// isn't part of the recorded input, a replay simply ends on its last tick.
+fn pause_controls(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut paused: ResMut<Paused>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    menus: Query<Entity, With<PauseMenu>>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    if paused.0 && keyboard_input.just_pressed(KeyCode::KeyQ) {
+        game_state.set(GameState::GameOver);
+        return;
+    }
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        paused.0 = !paused.0;
+        if paused.0 {
+            spawn_pause_menu(&mut commands);
+        } else {
+            virtual_time.unpause();
+            for menu in &menus {
+                commands.entity(menu).despawn_recursive();
+            }
+        }
+    }
+    // hit-stop unpauses time when it ends, so the pause is held every frame
+    if paused.0 {
+        virtual_time.pause();
+    }
+}
+
+fn spawn_pause_menu(commands: &mut Commands) {
+    commands
+        .spawn((
+            GameEntity,
+            PauseMenu,
+            Node {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(12.),
+                ..Default::default()
+            },
+            BackgroundColor(Color::srgba(0., 0., 0., 0.6)),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("PAUSED"),
+                TextFont {
+                    font_size: 48.,
+                    ..Default::default()
+                },
+                TextColor(MY_ORANGE),
+            ));
+            parent.spawn((
+                Text::new("esc resume   Q quit run"),
+                TextFont {
+                    font_size: 20.,
+                    ..Default::default()
+                },
+            ));
+        });
+}
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Paused>();
+        app.add_systems(OnEnter(GameState::Game), reset_pause);
+        app.add_systems(
+            Update,
+            // an online run can't stop for one side, Escape leaves it instead
+            pause_controls.run_if(
+                in_state(GameState::Game)
+                    .and(not(resource_exists::<ReplayPlayback>))
+                    .and(not(resource_exists::<NetSession>)),
+            ),
+        );
+    }
+}
@@ -1,68 +1,170 @@
+use std::time::Duration;
+
 use bevy::{
-    app::{App, Plugin, Update},
-    asset::{AssetServer, Assets},
+    app::{App, FixedUpdate, Plugin, Startup},
+    asset::{AssetServer, Assets, Handle},
     color::Color,
-    input::ButtonInput,
     math::{Vec2, Vec3},
     prelude::{
-        default, in_state, Circle, Commands, Component, Entity, IntoSystemConfigs, KeyCode, Mesh,
-        Mesh2d, NextState, OnEnter, Query, Res, ResMut, Resource, Transform, With,
+        default, in_state, Alpha, Circle, Commands, Component, Condition, Entity, EventReader,
+        EventWriter, IntoSystemConfigs, Mesh, Mesh2d, NextState, OnEnter, Query, Res, ResMut,
+        Resource, Transform, With, Without,
     },
-    sprite::{ColorMaterial, MeshMaterial2d, Sprite, SpriteBundle},
+    sprite::{ColorMaterial, MeshMaterial2d, Sprite},
     time::{Time, Timer, TimerMode},
-    window::{PrimaryWindow, Window},
 };
 
 use crate::{
     constants::{
-        Bullet, BULLET_CIRCLE_RADIUS, BULLET_CREATE_TIMER_SECONDS, BULLET_VELOCITY,
-        JET_SQUARE_BOX_LENGTH, JET_TRAVEL_DISTANCE,
+        Bullet, EnemyBullet, GrazeEvent, PlayerHitEvent, ShotFiredEvent, BULLET_CIRCLE_RADIUS,
+        ENEMY_BULLET_RADIUS, JET_HITBOX_RADIUS, JET_SPRITE_NAMES, JET_SQUARE_BOX_LENGTH,
+        MAX_PLAYERS, REVIVE_RADIUS, REVIVE_SECONDS,
     },
     GameState,
 };
 
-#[derive(Component)]
-pub struct Jet;
+use super::{
+    daily_mod::{start_attempt, DailyModifiers, Modifier},
+    gameplay_running,
+    net_mod::NetSession,
+    playfield_mod::Playfield,
+    replay_mod::{PlayerInput, PlayerInputs},
+    rollback_mod::{Rollback, RollbackApp},
+    tuning_mod::GameTuning,
+    versus_mod::lane,
+    GameMode, PlayerCount,
+};
+
+#[derive(Component, Clone)]
+pub struct Jet {
+    pub player: usize,
+}
 
-#[derive(Component)]
+#[derive(Component, Clone)]
 pub struct GameEntity;
 
-#[derive(Resource)]
+#[derive(Resource, Clone)]
 struct BulletTimer(Timer);
 
+// One mesh and material shared by every bullet the jets fire.
+#[derive(Resource)]
+struct BulletHandles {
+    mesh: Handle<Mesh>,
+    material: Handle<ColorMaterial>,
+}
+
+// What one player has earned and has left in the current run.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct PlayerStats {
+    pub score: usize,
+    pub lives: u32,
+    pub bombs: u32,
+    // power of the jet's guns, starting at 1, nothing raises it yet
+    pub weapon_level: u32,
+}
+
+impl PlayerStats {
+    fn new(tuning: &GameTuning, modifiers: &DailyModifiers) -> Self {
+        PlayerStats {
+            score: 0,
+            lives: if modifiers.has(Modifier::OneLife) {
+                1
+            } else {
+                tuning.lives
+            },
+            bombs: if modifiers.has(Modifier::NoBombs) {
+                0
+            } else {
+                tuning.bombs
+            },
+            weapon_level: 1,
+        }
+    }
+}
+
+// One entry per jet in the run, indexed by `Jet::player`. Kept after the run
+// so the game over screen can show each player's share.
+#[derive(Resource, Default, Clone)]
+pub struct Players(pub Vec<PlayerStats>);
+
+// Enemy bullets pass through the jet until this runs out.
+#[derive(Component, Clone)]
+struct Invulnerable(Timer);
+
+// A co-op jet that ran out of lives. It stays where it went down, out of the
+// fight, until a team-mate has stayed close to it for long enough.
+#[derive(Component, Clone)]
+pub struct Downed(Timer);
+
+// Set on an enemy bullet once it has scored a graze.
+#[derive(Component, Clone)]
+struct Grazed;
+
 pub struct JetPlugin;
 
-fn setup_system(mut commands: Commands, asset_server: Res<AssetServer>) {
-    commands.spawn((
-        GameEntity,
-        Jet,
-        SpriteBundle {
-            sprite: Sprite {
+#[allow(clippy::too_many_arguments)]
+fn setup_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut bullet_timer: ResMut<BulletTimer>,
+    mut players: ResMut<Players>,
+    player_count: Res<PlayerCount>,
+    tuning: Res<GameTuning>,
+    mode: Res<GameMode>,
+    playfield: Res<Playfield>,
+    modifiers: Res<DailyModifiers>,
+) {
+    // every run starts from the same state so replays line up
+    bullet_timer.0.reset();
+    players.0 = vec![PlayerStats::new(&tuning, &modifiers); player_count.0];
+    for (player, sprite_name) in JET_SPRITE_NAMES.iter().enumerate().take(player_count.0) {
+        // side by side around the middle, or in the middle of their own half in versus
+        let x = if *mode == GameMode::Versus {
+            let (left, right) = lane(*mode, &playfield, player);
+            (left + right) / 2.
+        } else {
+            (player as f32 - (player_count.0 - 1) as f32 / 2.) * JET_SQUARE_BOX_LENGTH * 1.5
+        };
+        commands.spawn((
+            GameEntity,
+            Rollback,
+            Jet { player },
+            Sprite {
                 custom_size: Some(Vec2::splat(JET_SQUARE_BOX_LENGTH)),
-                image: asset_server.load("DurrrSpaceShip.png"),
+                image: asset_server.load(*sprite_name),
                 ..default()
             },
-            ..default()
-        },
-    ));
+            Transform::from_xyz(x, 0., 0.),
+        ));
+    }
 }
 
 fn udpate_on_button_click(
-    mut query: Query<&mut Transform, With<Jet>>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    window_query: Query<&Window, With<PrimaryWindow>>,
-    mut game_state: ResMut<NextState<GameState>>,
+    mut query: Query<(&mut Transform, &Jet), Without<Downed>>,
+    inputs: Res<PlayerInputs>,
+    playfield: Res<Playfield>,
+    tuning: Res<GameTuning>,
+    mode: Res<GameMode>,
 ) {
-    // let mut jet_transform = query.single_mut();
-    // println!(
-    //     "jet pos:{},{}",
-    //     jet_transform.translation.x, jet_transform.translation.y
-    // );
-    let window = window_query.get_single().unwrap();
-    let window_height = window.height();
-    let window_width = window.width();
-    let mut jet_transform = query.single_mut();
+    for (mut jet_transform, jet) in &mut query {
+        steer_jet(
+            &mut jet_transform,
+            inputs.0[jet.player],
+            &playfield,
+            lane(*mode, &playfield, jet.player),
+            tuning.jet_speed,
+        );
+    }
+}
 
+// Every jet is kept inside its lane, the whole playfield unless in versus.
+fn steer_jet(
+    jet_transform: &mut Transform,
+    input: PlayerInput,
+    playfield: &Playfield,
+    (lane_left, lane_right): (f32, f32),
+    speed: f32,
+) {
     let (mut left, mut right) = (
         jet_transform.translation.x - (JET_SQUARE_BOX_LENGTH / 2.0),
         jet_transform.translation.x + (JET_SQUARE_BOX_LENGTH / 2.0),
@@ -72,45 +174,60 @@ fn udpate_on_button_click(
         jet_transform.translation.y - (JET_SQUARE_BOX_LENGTH / 2.0),
     );
 
-    // println!("window height {}", window_height);
-    if keyboard_input.pressed(KeyCode::KeyW) {
-        top += JET_TRAVEL_DISTANCE;
-        if top < window_height / 2. {
-            jet_transform.translation.y += JET_TRAVEL_DISTANCE;
+    if input.pressed(PlayerInput::UP) {
+        top += speed;
+        if top < playfield.half_height() {
+            jet_transform.translation.y += speed;
         }
-    } else if keyboard_input.pressed(KeyCode::KeyS) {
-        bottom -= JET_TRAVEL_DISTANCE;
-        if bottom > (-1. * window_height / 2.) {
-            jet_transform.translation.y -= JET_TRAVEL_DISTANCE;
+    } else if input.pressed(PlayerInput::DOWN) {
+        bottom -= speed;
+        if bottom > -playfield.half_height() {
+            jet_transform.translation.y -= speed;
         }
-    } else if keyboard_input.pressed(KeyCode::KeyA) {
-        left -= JET_TRAVEL_DISTANCE;
-        if left > (-1. * window_width / 2.) {
-            jet_transform.translation.x -= JET_TRAVEL_DISTANCE;
+    } else if input.pressed(PlayerInput::LEFT) {
+        left -= speed;
+        if left > lane_left {
+            jet_transform.translation.x -= speed;
         }
-    } else if keyboard_input.pressed(KeyCode::KeyD) {
-        right += JET_TRAVEL_DISTANCE;
-        if right < window_width / 2. {
-            jet_transform.translation.x += JET_TRAVEL_DISTANCE;
+    } else if input.pressed(PlayerInput::RIGHT) {
+        right += speed;
+        if right < lane_right {
+            jet_transform.translation.x += speed;
         }
-    } else if keyboard_input.pressed(KeyCode::Escape) {
-        game_state.set(GameState::Splash);
     }
 }
 
+fn setup_bullets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.insert_resource(BulletHandles {
+        mesh: meshes.add(Circle::new(BULLET_CIRCLE_RADIUS)),
+        material: materials.add(Color::srgb(0.5, 0.5, 1.)),
+    });
+}
+
 fn create_bullets(
-    mut jet_query: Query<&mut Transform, With<Jet>>,
+    jet_query: Query<(&Transform, &Jet), Without<Downed>>,
     time: Res<Time>,
     mut bullet_timer: ResMut<BulletTimer>,
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    handles: Res<BulletHandles>,
+    mut shot_events: EventWriter<ShotFiredEvent>,
+    tuning: Res<GameTuning>,
 ) {
+    let fire_rate = Duration::from_secs_f32(tuning.fire_seconds);
+    if bullet_timer.0.duration() != fire_rate {
+        bullet_timer.0.set_duration(fire_rate);
+    }
     if bullet_timer.0.tick(time.delta()).just_finished() {
-        for transform in &mut jet_query {
+        for (transform, jet) in &jet_query {
+            shot_events.send(ShotFiredEvent);
             commands.spawn((
                 GameEntity,
-                Bullet,
+                Rollback,
+                Bullet { player: jet.player },
                 // MeshMaterial2d {
                 //     mesh: meshes.add(Circle::new(BULLET_CIRCLE_RADIUS)).into(),
                 //     material: materials.add(Color::srgb(0.5, 0.5, 1.)),
@@ -121,8 +238,8 @@ fn create_bullets(
                 //     )),
                 //     ..default()
                 // },
-                Mesh2d(meshes.add(Circle::new(BULLET_CIRCLE_RADIUS))),
-                MeshMaterial2d(materials.add(Color::srgb(0.5, 0.5, 1.))),
+                Mesh2d(handles.mesh.clone()),
+                MeshMaterial2d(handles.material.clone()),
                 Transform::from_translation(Vec3::new(
                     transform.translation.x,
                     transform.translation.y + (JET_SQUARE_BOX_LENGTH / 2.),
@@ -135,37 +252,299 @@ fn create_bullets(
 
 fn update_bullets(
     mut query: Query<(&mut Transform, Entity), With<Bullet>>,
-    window_query: Query<&Window, With<PrimaryWindow>>,
+    playfield: Res<Playfield>,
     mut commands: Commands,
+    tuning: Res<GameTuning>,
 ) {
-    let window = window_query.get_single().unwrap();
-    let window_height = window.height();
-
     for (mut transform, entity) in &mut query {
-        transform.translation.y = transform.translation.y + BULLET_VELOCITY;
-        if transform.translation.y > window_height / 2. {
+        transform.translation.y = transform.translation.y + tuning.bullet_speed;
+        if transform.translation.y > playfield.half_height() {
             commands.entity(entity).despawn();
         }
     }
 }
 
+// Jets that can be hit, downed ones wait to be revived instead.
+type HittableJet = (Without<Invulnerable>, Without<Downed>);
+
+fn check_enemy_bullet_hits(
+    mut commands: Commands,
+    jet_query: Query<(&Transform, &Jet), HittableJet>,
+    bullets: Query<(Entity, &Transform, Option<&Grazed>), With<EnemyBullet>>,
+    mut hit_events: EventWriter<PlayerHitEvent>,
+    mut graze_events: EventWriter<GrazeEvent>,
+    tuning: Res<GameTuning>,
+) {
+    for (bullet_entity, bullet_transform, grazed) in &bullets {
+        let position = bullet_transform.translation.truncate();
+        for (jet_transform, jet) in &jet_query {
+            let distance = position.distance(jet_transform.translation.truncate());
+            if distance < JET_HITBOX_RADIUS + ENEMY_BULLET_RADIUS {
+                commands.entity(bullet_entity).despawn();
+                hit_events.send(PlayerHitEvent {
+                    position,
+                    player: jet.player,
+                });
+                break;
+            } else if distance < tuning.graze_radius && grazed.is_none() {
+                commands.entity(bullet_entity).insert(Grazed);
+                graze_events.send(GrazeEvent {
+                    position,
+                    player: jet.player,
+                });
+                break;
+            }
+        }
+    }
+}
+
+fn take_hits(
+    mut commands: Commands,
+    mut hit_events: EventReader<PlayerHitEvent>,
+    mut jet_query: Query<(Entity, &Jet, &mut Sprite)>,
+    mut players: ResMut<Players>,
+    mut game_state: ResMut<NextState<GameState>>,
+    tuning: Res<GameTuning>,
+    net: Option<Res<NetSession>>,
+) {
+    // a bullet fired in the same tick as the killing one can't take a second life
+    let mut hit = [false; MAX_PLAYERS];
+    for event in hit_events.read() {
+        hit[event.player] = true;
+    }
+    for (entity, jet, mut sprite) in &mut jet_query {
+        let Some(stats) = players.0.get_mut(jet.player).filter(|_| hit[jet.player]) else {
+            continue;
+        };
+        stats.lives = stats.lives.saturating_sub(1);
+        println!("player {} hit, {} lives left", jet.player + 1, stats.lives);
+        if stats.lives == 0 {
+            sprite.color.set_alpha(0.25);
+            commands
+                .entity(entity)
+                .insert(Downed(Timer::from_seconds(REVIVE_SECONDS, TimerMode::Once)));
+        } else {
+            commands
+                .entity(entity)
+                .insert(Invulnerable(Timer::from_seconds(
+                    tuning.invulnerable_seconds,
+                    TimerMode::Once,
+                )));
+        }
+    }
+    // the run is over once nobody is left to revive the others, online that
+    // waits until a late input can't undo it
+    if hit.contains(&true) && players.0.iter().all(|stats| stats.lives == 0) && net.is_none() {
+        game_state.set(GameState::GameOver);
+    }
+}
+
+// A downed jet comes back with one life while a team-mate hovers close to it.
+// Moving away lets the progress drain again. A versus rival is no team-mate.
+fn revive_downed(
+    mut commands: Commands,
+    mut downed: Query<(Entity, &Transform, &Jet, &mut Downed)>,
+    helpers: Query<&Transform, (With<Jet>, Without<Downed>)>,
+    mut players: ResMut<Players>,
+    time: Res<Time>,
+    tuning: Res<GameTuning>,
+    mode: Res<GameMode>,
+) {
+    if *mode == GameMode::Versus {
+        return;
+    }
+    for (entity, transform, jet, mut downed) in &mut downed {
+        let position = transform.translation.truncate();
+        let helped = helpers
+            .iter()
+            .any(|helper| helper.translation.truncate().distance(position) < REVIVE_RADIUS);
+        if helped {
+            downed.0.tick(time.delta());
+        } else {
+            let elapsed = downed.0.elapsed().saturating_sub(time.delta());
+            downed.0.set_elapsed(elapsed);
+        }
+        if !downed.0.finished() {
+            continue;
+        }
+        println!("player {} revived", jet.player + 1);
+        if let Some(stats) = players.0.get_mut(jet.player) {
+            stats.lives = 1;
+        }
+        commands
+            .entity(entity)
+            .remove::<Downed>()
+            .insert(Invulnerable(Timer::from_seconds(
+                tuning.invulnerable_seconds,
+                TimerMode::Once,
+            )));
+    }
+}
+
+fn update_invulnerability(
+    mut commands: Commands,
+    mut jet_query: Query<(Entity, &mut Invulnerable, &mut Sprite), With<Jet>>,
+    time: Res<Time>,
+) {
+    for (entity, mut invulnerable, mut sprite) in &mut jet_query {
+        invulnerable.0.tick(time.delta());
+        if invulnerable.0.finished() {
+            commands.entity(entity).remove::<Invulnerable>();
+            sprite.color.set_alpha(1.0);
+        } else {
+            // blink a few times a second
+            let visible = ((invulnerable.0.elapsed_secs() * 10.0) as u32).is_multiple_of(2);
+            sprite.color.set_alpha(if visible { 1.0 } else { 0.25 });
+        }
+    }
+}
+
 impl Plugin for JetPlugin {
     fn build(&self, app: &mut App) {
         println!("This is the build process now");
         //app.insert_resource(SpacePointTimer(Timer::from_seconds(5.,TimerMode::Repeating)));
-        app.insert_resource(BulletTimer(Timer::from_seconds(
-            BULLET_CREATE_TIMER_SECONDS,
-            TimerMode::Repeating,
-        )));
-        app.add_systems(OnEnter(GameState::Game), setup_system);
+        // the fire rate is set from the tuning on every tick
+        app.insert_resource(BulletTimer(Timer::from_seconds(1., TimerMode::Repeating)));
+        app.init_resource::<Players>();
+        app.add_event::<PlayerHitEvent>();
+        app.add_event::<GrazeEvent>();
+        app.add_event::<ShotFiredEvent>();
+        app.rollback_component::<GameEntity>()
+            .rollback_component::<Jet>()
+            .rollback_component::<Bullet>()
+            .rollback_component::<Invulnerable>()
+            .rollback_component::<Downed>()
+            .rollback_component::<Grazed>()
+            .rollback_resource::<BulletTimer>()
+            .rollback_resource::<Players>();
+        app.add_systems(Startup, setup_bullets);
+        app.add_systems(OnEnter(GameState::Game), setup_system.after(start_attempt));
         app.add_systems(
-            Update,
+            FixedUpdate,
             (
                 udpate_on_button_click,
                 (create_bullets, update_bullets).chain(),
+                (
+                    check_enemy_bullet_hits,
+                    take_hits,
+                    revive_downed,
+                    update_invulnerability,
+                )
+                    .chain(),
             )
-                .run_if(in_state(GameState::Game)),
+                .run_if(in_state(GameState::Game).and(gameplay_running)),
         );
         //app.add_systems(Update, update_background);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::{Entity, KeyCode, Or, Transform, Vec2, With};
+
+    use crate::{
+        constants::{
+            Bullet, EnemyBullet, EnemyDestroyedEvent, PlayerHitEvent, JET_SQUARE_BOX_LENGTH,
+        },
+        game::{eneymy_mod::EnemyKind, playfield_mod::Playfield, tuning_mod::GameTuning},
+        test_support::{entities, press, release, step, step_until, test_app, TestApp},
+    };
+
+    use super::{Jet, Players};
+
+    fn jet_xs(app: &mut bevy::app::App) -> Vec<f32> {
+        let world = app.world_mut();
+        let mut jets: Vec<(usize, f32)> = world
+            .query::<(&Jet, &Transform)>()
+            .iter(world)
+            .map(|(jet, transform)| (jet.player, transform.translation.x))
+            .collect();
+        jets.sort_by_key(|jet| jet.0);
+        jets.into_iter().map(|jet| jet.1).collect()
+    }
+
+    fn jet_x(app: &mut bevy::app::App) -> f32 {
+        let world = app.world_mut();
+        world
+            .query_filtered::<&Transform, With<Jet>>()
+            .single(world)
+            .translation
+            .x
+    }
+
+    #[test]
+    fn bullets_despawn_above_the_playfield() {
+        let mut app = test_app();
+        let half_height = app.world().resource::<Playfield>().half_height();
+        let bullet_speed = app.world().resource::<GameTuning>().bullet_speed;
+
+        assert!(step_until(&mut app, 120, |app| {
+            !entities::<With<Bullet>>(app).is_empty()
+        }));
+        let first_bullet = entities::<With<Bullet>>(&mut app)[0];
+
+        let gone = step_until(&mut app, 300, |app| {
+            let world = app.world_mut();
+            let mut bullets = world.query_filtered::<(Entity, &Transform), With<Bullet>>();
+            for (_, transform) in bullets.iter(world) {
+                assert!(transform.translation.y <= half_height + bullet_speed);
+            }
+            bullets.get(world, first_bullet).is_err()
+        });
+        assert!(gone, "bullet was never despawned");
+    }
+
+    #[test]
+    fn jet_moves_with_keyboard_and_stays_in_the_playfield() {
+        let mut app = test_app();
+        let half_width = app.world().resource::<Playfield>().half_width();
+
+        press(&mut app, KeyCode::KeyD);
+        step(&mut app, 10);
+        assert!(jet_x(&mut app) > 0.);
+
+        step(&mut app, 200);
+        release(&mut app, KeyCode::KeyD);
+        assert!(jet_x(&mut app) + JET_SQUARE_BOX_LENGTH / 2. <= half_width);
+
+        press(&mut app, KeyCode::KeyA);
+        step(&mut app, 400);
+        assert!(jet_x(&mut app) - JET_SQUARE_BOX_LENGTH / 2. >= -half_width);
+    }
+
+    #[test]
+    fn each_coop_jet_follows_its_own_keys_and_keeps_its_own_stats() {
+        let mut app = TestApp::default().players(2).build();
+        assert_eq!(app.world().resource::<Players>().0.len(), 2);
+        let start = jet_xs(&mut app);
+        assert_eq!(start.len(), 2);
+
+        press(&mut app, KeyCode::ArrowRight);
+        step(&mut app, 10);
+        let moved = jet_xs(&mut app);
+        assert_eq!(moved[0], start[0]);
+        assert!(moved[1] > start[1]);
+
+        // the first player gets hit while the second one makes a kill, nothing
+        // else is around to change either of them
+        for entity in entities::<Or<(With<EnemyKind>, With<EnemyBullet>)>>(&mut app) {
+            app.world_mut().despawn(entity);
+        }
+        let before = app.world().resource::<Players>().0.clone();
+        app.world_mut().send_event(PlayerHitEvent {
+            position: Vec2::ZERO,
+            player: 0,
+        });
+        app.world_mut().send_event(EnemyDestroyedEvent {
+            position: Vec2::ZERO,
+            points: 100,
+            player: 1,
+        });
+        step(&mut app, 2);
+        let after = app.world().resource::<Players>().0.clone();
+        assert_eq!(after[0].lives, before[0].lives - 1);
+        assert_eq!(after[0].score, before[0].score);
+        assert_eq!(after[1].lives, before[1].lives);
+        assert!(after[1].score > before[1].score);
+    }
+}
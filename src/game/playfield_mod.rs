@@ -0,0 +1,164 @@
+use bevy::{
+    app::{App, Plugin, Startup, Update},
+    color::Color,
+    core_pipeline::core_2d::Camera2d,
+    input::ButtonInput,
+    math::{UVec2, Vec2},
+    prelude::{
+        Added, Camera, Changed, ClearColorConfig, Commands, Component, DetectChanges,
+        IntoSystemConfigs, KeyCode, OrthographicProjection, Query, Res, ResMut, Resource, With,
+        Without,
+    },
+    render::{
+        camera::{ScalingMode, Viewport},
+        view::RenderLayers,
+    },
+    window::{PrimaryWindow, Window},
+};
+
+use crate::constants::{ENEMY_SQUARE_BOX_LENGTH, PLAYFIELD_HEIGHT, PLAYFIELD_WIDTH};
+
+pub struct PlayfieldPlugin;
+
+// The fixed area gameplay happens in, centred on the origin. All spawn and
+// bounds logic works in these units no matter how big the window is.
+#[derive(Resource, Clone, Copy)]
+pub struct Playfield {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Default for Playfield {
+    fn default() -> Self {
+        Playfield {
+            width: PLAYFIELD_WIDTH,
+            height: PLAYFIELD_HEIGHT,
+        }
+    }
+}
+
+impl Playfield {
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(self.width, self.height)
+    }
+
+    pub fn half_width(&self) -> f32 {
+        self.width / 2.
+    }
+
+    pub fn half_height(&self) -> f32 {
+        self.height / 2.
+    }
+
+    // Whether enemies still fit, in a lane each in versus. Sizes from a file,
+    // the command line or the other player are checked with this first.
+    pub fn is_playable(&self) -> bool {
+        self.width > 2. * ENEMY_SQUARE_BOX_LENGTH && self.height > ENEMY_SQUARE_BOX_LENGTH
+    }
+}
+
+// How the playfield is mapped onto the window.
+#[derive(Resource, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum PlayfieldScaling {
+    // largest whole-number scale that fits, for crisp pixels
+    Integer,
+    // largest scale that fits, with black bars on the sides or top and bottom
+    #[default]
+    Fit,
+    // fill the whole window, ignoring the aspect ratio
+    Stretch,
+}
+
+impl PlayfieldScaling {
+    fn next(self) -> Self {
+        match self {
+            PlayfieldScaling::Integer => PlayfieldScaling::Fit,
+            PlayfieldScaling::Fit => PlayfieldScaling::Stretch,
+            PlayfieldScaling::Stretch => PlayfieldScaling::Integer,
+        }
+    }
+}
+
+// Clears the bars around the playfield viewport.
+#[derive(Component)]
+struct LetterboxCamera;
+
+// Returns the viewport (position, size) in physical pixels, or `None` to use the whole window.
+pub fn playfield_viewport(
+    window_size: UVec2,
+    playfield: Vec2,
+    scaling: PlayfieldScaling,
+) -> Option<(UVec2, UVec2)> {
+    if scaling == PlayfieldScaling::Stretch || window_size.x == 0 || window_size.y == 0 {
+        return None;
+    }
+    let fit = (window_size.as_vec2() / playfield).min_element();
+    let scale = match scaling {
+        PlayfieldScaling::Integer if fit >= 1. => fit.floor(),
+        _ => fit,
+    };
+    let size = (playfield * scale).round().as_uvec2().min(window_size);
+    Some(((window_size - size) / 2, size))
+}
+
+fn setup_letterbox_camera(mut commands: Commands) {
+    commands.spawn((
+        LetterboxCamera,
+        Camera2d,
+        Camera {
+            order: -1,
+            clear_color: ClearColorConfig::Custom(Color::BLACK),
+            ..Default::default()
+        },
+        // renders nothing, it only exists to clear the window behind the bars
+        RenderLayers::layer(31),
+    ));
+}
+
+fn cycle_scaling(keyboard_input: Res<ButtonInput<KeyCode>>, mut scaling: ResMut<PlayfieldScaling>) {
+    if keyboard_input.just_pressed(KeyCode::F8) {
+        *scaling = scaling.next();
+        println!("playfield scaling: {:?}", *scaling);
+    }
+}
+
+fn fit_camera_to_window(
+    resized_windows: Query<(), (With<PrimaryWindow>, Changed<Window>)>,
+    playfield: Res<Playfield>,
+    scaling: Res<PlayfieldScaling>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    new_cameras: Query<(), (Added<Camera2d>, Without<LetterboxCamera>)>,
+    mut cameras: Query<(&mut Camera, &mut OrthographicProjection), Without<LetterboxCamera>>,
+) {
+    if resized_windows.is_empty()
+        && new_cameras.is_empty()
+        && !playfield.is_changed()
+        && !scaling.is_changed()
+    {
+        return;
+    }
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+    let viewport = playfield_viewport(window.physical_size(), playfield.size(), *scaling);
+    for (mut camera, mut projection) in &mut cameras {
+        camera.viewport = viewport.map(|(physical_position, physical_size)| Viewport {
+            physical_position,
+            physical_size,
+            ..Default::default()
+        });
+        projection.scaling_mode = ScalingMode::Fixed {
+            width: playfield.width,
+            height: playfield.height,
+        };
+    }
+}
+
+impl Plugin for PlayfieldPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Playfield>();
+        app.init_resource::<PlayfieldScaling>();
+        app.add_systems(Startup, setup_letterbox_camera);
+        app.add_systems(Update, (cycle_scaling, fit_camera_to_window).chain());
+    }
+}
@@ -0,0 +1,899 @@
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{
+    app::{App, FixedLast, FixedMain, FixedPreUpdate, Plugin, Update},
+    input::{
+        gamepad::{Gamepad, GamepadButton},
+        ButtonInput,
+    },
+    prelude::{
+        in_state, resource_exists, Commands, Component, Condition, DetectChanges, EventWriter,
+        IntoSystemConfigs, KeyCode, NextState, OnEnter, OnExit, Query, Res, ResMut, Resource, Text,
+        With, World,
+    },
+    time::{Fixed, Time, Virtual},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    constants::{
+        FIXED_TICK_SECONDS, GAMEPAD_DEADZONE, MAX_PLAYERS, REPLAY_FILE_VERSION, REPLAY_SPEEDS,
+        TUNING_FILE,
+    },
+    user_data::user_data_dir,
+    GameState,
+};
+
+use super::{
+    gameplay_running, net_mod::NetSession, player_jet_mod::GameEntity, playfield_mod::Playfield,
+    rng_mod::GameRng, rollback_mod::RollbackApp, toast_mod::ErrorToast, tuning_mod::GameTuning,
+    Difficulty, GameMode, PlayerCount, Score,
+};
+
+pub struct ReplayPlugin;
+
+// Buttons one player held during one fixed tick.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct PlayerInput(u8);
+
+impl PlayerInput {
+    pub const UP: PlayerInput = PlayerInput(1);
+    pub const DOWN: PlayerInput = PlayerInput(1 << 1);
+    pub const LEFT: PlayerInput = PlayerInput(1 << 2);
+    pub const RIGHT: PlayerInput = PlayerInput(1 << 3);
+
+    pub fn pressed(self, button: PlayerInput) -> bool {
+        self.0 & button.0 != 0
+    }
+
+    fn from_devices(
+        keyboard_input: &ButtonInput<KeyCode>,
+        bindings: &[(KeyCode, PlayerInput)],
+        gamepad: Option<&Gamepad>,
+    ) -> Self {
+        let mut buttons = bindings
+            .iter()
+            .filter(|(key, _)| keyboard_input.pressed(*key))
+            .fold(0, |buttons, (_, button)| buttons | button.0);
+        if let Some(gamepad) = gamepad {
+            let stick = gamepad.left_stick();
+            let held = [
+                (
+                    GamepadButton::DPadUp,
+                    stick.y > GAMEPAD_DEADZONE,
+                    PlayerInput::UP,
+                ),
+                (
+                    GamepadButton::DPadDown,
+                    stick.y < -GAMEPAD_DEADZONE,
+                    PlayerInput::DOWN,
+                ),
+                (
+                    GamepadButton::DPadLeft,
+                    stick.x < -GAMEPAD_DEADZONE,
+                    PlayerInput::LEFT,
+                ),
+                (
+                    GamepadButton::DPadRight,
+                    stick.x > GAMEPAD_DEADZONE,
+                    PlayerInput::RIGHT,
+                ),
+            ];
+            for (pad, stick_held, button) in held {
+                if stick_held || gamepad.pressed(pad) {
+                    buttons |= button.0;
+                }
+            }
+        }
+        PlayerInput(buttons)
+    }
+}
+
+// WASD for the first player, the arrow keys for the second.
+const KEY_BINDINGS: [[(KeyCode, PlayerInput); 4]; MAX_PLAYERS] = [
+    [
+        (KeyCode::KeyW, PlayerInput::UP),
+        (KeyCode::KeyS, PlayerInput::DOWN),
+        (KeyCode::KeyA, PlayerInput::LEFT),
+        (KeyCode::KeyD, PlayerInput::RIGHT),
+    ],
+    [
+        (KeyCode::ArrowUp, PlayerInput::UP),
+        (KeyCode::ArrowDown, PlayerInput::DOWN),
+        (KeyCode::ArrowLeft, PlayerInput::LEFT),
+        (KeyCode::ArrowRight, PlayerInput::RIGHT),
+    ],
+];
+
+// Which connected gamepad a player uses. Playing alone it's the first one, in
+// co-op the first one goes to the second player, whose keys share the
+// keyboard with the first.
+fn gamepad_index(player: usize, players: usize) -> usize {
+    if players == 1 {
+        0
+    } else {
+        (player + 1) % players
+    }
+}
+
+// What every player held during one fixed tick, indexed by player. Gameplay
+// reads this instead of the devices so a replay can feed it back in.
+#[derive(Resource, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct PlayerInputs(pub [PlayerInput; MAX_PLAYERS]);
+
+impl PlayerInputs {
+    // one byte per player, the first player in the lowest
+    fn packed(self) -> u16 {
+        self.0
+            .iter()
+            .enumerate()
+            .fold(0, |packed, (player, input)| {
+                packed | (input.0 as u16) << (8 * player)
+            })
+    }
+
+    fn unpacked(packed: u16) -> Self {
+        let mut inputs = PlayerInputs::default();
+        for (player, input) in inputs.0.iter_mut().enumerate() {
+            *input = PlayerInput((packed >> (8 * player)) as u8);
+        }
+        inputs
+    }
+}
+
+// Fixed ticks simulated so far in the current run.
+#[derive(Resource, Default, Clone)]
+pub struct FixedTick(pub u32);
+
+impl FixedTick {
+    // Game time played so far, not counting pauses and hit stops.
+    pub fn seconds(&self) -> f32 {
+        (self.0 as f64 * FIXED_TICK_SECONDS) as f32
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub version: u32,
+    pub seed: u64,
+    pub playfield: (f32, f32),
+    #[serde(default)]
+    pub mode: GameMode,
+    #[serde(default)]
+    pub difficulty: Difficulty,
+    #[serde(default = "one_player")]
+    pub players: usize,
+    // `GameTuning::checksum` of the values the run was played with
+    pub tuning: u64,
+    pub ticks: u32,
+    pub score: usize,
+    // (tick, buttons) every time the held buttons change, see `PlayerInputs::packed`
+    pub inputs: Vec<(u32, u16)>,
+}
+
+fn one_player() -> usize {
+    1
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    Write(ron::Error),
+    Version(u32),
+    Tuning,
+    Invalid(String),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(error) => write!(f, "could not access replay file: {error}"),
+            ReplayError::Parse(error) => write!(f, "could not parse replay file: {error}"),
+            ReplayError::Write(error) => write!(f, "could not write replay file: {error}"),
+            ReplayError::Version(version) => write!(f, "unsupported replay version {version}"),
+            ReplayError::Invalid(reason) => write!(f, "invalid replay: {reason}"),
+            ReplayError::Tuning => write!(
+                f,
+                "recorded with different tuning, {TUNING_FILE} has changed since"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<std::io::Error> for ReplayError {
+    fn from(error: std::io::Error) -> Self {
+        ReplayError::Io(error)
+    }
+}
+
+impl From<ron::error::SpannedError> for ReplayError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        ReplayError::Parse(error)
+    }
+}
+
+impl From<ron::Error> for ReplayError {
+    fn from(error: ron::Error) -> Self {
+        ReplayError::Write(error)
+    }
+}
+
+impl Replay {
+    fn new(
+        seed: u64,
+        playfield: &Playfield,
+        mode: GameMode,
+        difficulty: Difficulty,
+        players: PlayerCount,
+        tuning: &GameTuning,
+    ) -> Self {
+        Replay {
+            version: REPLAY_FILE_VERSION,
+            seed,
+            playfield: (playfield.width, playfield.height),
+            mode,
+            difficulty,
+            players: players.0,
+            tuning: tuning.checksum(),
+            ticks: 0,
+            score: 0,
+            inputs: Vec::new(),
+        }
+    }
+
+    pub fn playfield(&self) -> Playfield {
+        Playfield {
+            width: self.playfield.0,
+            height: self.playfield.1,
+        }
+    }
+
+    // What the run was set up with, for playing it back the same way.
+    pub fn settings(&self) -> RunSettings {
+        RunSettings {
+            playfield: self.playfield(),
+            mode: self.mode,
+            difficulty: self.difficulty,
+            players: PlayerCount(self.players),
+        }
+    }
+
+    // A run only plays back the same with the tuning it was recorded with.
+    pub fn check_tuning(&self, tuning: &GameTuning) -> Result<(), ReplayError> {
+        if self.tuning == tuning.checksum() {
+            Ok(())
+        } else {
+            Err(ReplayError::Tuning)
+        }
+    }
+
+    fn record(&mut self, tick: u32, inputs: PlayerInputs) {
+        // an online run can play ticks again after a late input, so what was
+        // recorded for them no longer holds
+        while self
+            .inputs
+            .last()
+            .is_some_and(|(change_tick, _)| *change_tick >= tick)
+        {
+            self.inputs.pop();
+        }
+        let held = self.inputs.last().map_or(0, |(_, buttons)| *buttons);
+        if inputs.packed() != held {
+            self.inputs.push((tick, inputs.packed()));
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Replay, ReplayError> {
+        let replay: Replay = ron::de::from_bytes(&fs::read(path)?)?;
+        if replay.version != REPLAY_FILE_VERSION {
+            return Err(ReplayError::Version(replay.version));
+        }
+        if !(1..=MAX_PLAYERS).contains(&replay.players) {
+            return Err(ReplayError::Invalid(format!(
+                "{} players, expected 1 to {MAX_PLAYERS}",
+                replay.players
+            )));
+        }
+        if !replay.playfield().is_playable() {
+            return Err(ReplayError::Invalid(format!(
+                "playfield {}x{} is too small",
+                replay.playfield.0, replay.playfield.1
+            )));
+        }
+        Ok(replay)
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<PathBuf, ReplayError> {
+        fs::create_dir_all(dir)?;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        let path = dir.join(format!("{timestamp}_{}.replay.ron", self.seed));
+        fs::write(&path, ron::to_string(self)?)?;
+        Ok(path)
+    }
+}
+
+pub fn replay_dir() -> PathBuf {
+    user_data_dir().join("replays")
+}
+
+// Saved replays, newest first.
+pub fn list_replays() -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(replay_dir()) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.to_string_lossy().ends_with(".replay.ron"))
+        .collect();
+    paths.sort();
+    paths.reverse();
+    paths
+}
+
+// Whether finished runs are written to `replay_dir`.
+#[derive(Resource)]
+pub struct SaveReplays(pub bool);
+
+impl Default for SaveReplays {
+    fn default() -> Self {
+        SaveReplays(true)
+    }
+}
+
+// The replay of the last run, whether it was played or played back.
+#[derive(Resource)]
+pub struct LastReplay {
+    pub replay: Replay,
+    pub played_back: bool,
+    // where the replay was saved, if it was
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Resource)]
+struct ReplayRecorder(Replay);
+
+// The resources a run is set up with from the menu. Playing a replay back
+// swaps them for the recorded ones for as long as it lasts.
+#[derive(Clone, Copy)]
+pub struct RunSettings {
+    pub playfield: Playfield,
+    pub mode: GameMode,
+    pub difficulty: Difficulty,
+    pub players: PlayerCount,
+}
+
+impl RunSettings {
+    pub fn insert(self, commands: &mut Commands) {
+        commands.insert_resource(self.playfield);
+        commands.insert_resource(self.mode);
+        commands.insert_resource(self.difficulty);
+        commands.insert_resource(self.players);
+    }
+}
+
+// Present while a run is being played back instead of played.
+#[derive(Resource)]
+pub struct ReplayPlayback {
+    replay: Replay,
+    next_change: usize,
+    inputs: PlayerInputs,
+    // the menu's settings to go back to once playback is over
+    previous: Option<RunSettings>,
+}
+
+impl ReplayPlayback {
+    pub fn new(replay: Replay) -> Self {
+        ReplayPlayback {
+            replay,
+            next_change: 0,
+            inputs: PlayerInputs::default(),
+            previous: None,
+        }
+    }
+
+    pub fn restoring(mut self, settings: RunSettings) -> Self {
+        self.previous = Some(settings);
+        self
+    }
+
+    pub fn finished(&self, tick: u32) -> bool {
+        tick >= self.replay.ticks
+    }
+
+    fn inputs_at(&mut self, tick: u32) -> PlayerInputs {
+        while let Some(&(change_tick, buttons)) = self.replay.inputs.get(self.next_change) {
+            if change_tick > tick {
+                break;
+            }
+            self.inputs = PlayerInputs::unpacked(buttons);
+            self.next_change += 1;
+        }
+        self.inputs
+    }
+}
+
+#[derive(Resource, Default)]
+struct ReplayControls {
+    paused: bool,
+    speed: usize,
+    step_requested: bool,
+}
+
+#[derive(Component)]
+struct ReplayStatusText;
+
+fn reset_tick(mut tick: ResMut<FixedTick>, mut controls: ResMut<ReplayControls>) {
+    tick.0 = 0;
+    *controls = ReplayControls::default();
+}
+
+#[allow(clippy::too_many_arguments)]
+fn start_run(
+    mut commands: Commands,
+    playback: Option<Res<ReplayPlayback>>,
+    rng: Res<GameRng>,
+    playfield: Res<Playfield>,
+    mode: Res<GameMode>,
+    difficulty: Res<Difficulty>,
+    players: Res<PlayerCount>,
+    tuning: Res<GameTuning>,
+) {
+    if playback.is_some() {
+        commands.spawn((GameEntity, ReplayStatusText, Text::default()));
+    } else {
+        let replay = Replay::new(
+            rng.seed(),
+            &playfield,
+            *mode,
+            *difficulty,
+            *players,
+            &tuning,
+        );
+        commands.insert_resource(ReplayRecorder(replay));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn sample_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    players: Res<PlayerCount>,
+    tick: Res<FixedTick>,
+    mut inputs: ResMut<PlayerInputs>,
+    recorder: Option<ResMut<ReplayRecorder>>,
+    playback: Option<ResMut<ReplayPlayback>>,
+    net: Option<ResMut<NetSession>>,
+) {
+    let gamepads: Vec<&Gamepad> = gamepads.iter().collect();
+    *inputs = if let Some(mut playback) = playback {
+        playback.inputs_at(tick.0)
+    } else if let Some(mut net) = net {
+        // each side plays its own jet with the first player's controls
+        let sampled =
+            PlayerInput::from_devices(&keyboard_input, &KEY_BINDINGS[0], gamepads.first().copied());
+        net.inputs_at(tick.0, sampled)
+    } else {
+        let mut sampled = PlayerInputs::default();
+        for (player, input) in sampled.0.iter_mut().enumerate().take(players.0) {
+            *input = PlayerInput::from_devices(
+                &keyboard_input,
+                &KEY_BINDINGS[player],
+                gamepads.get(gamepad_index(player, players.0)).copied(),
+            );
+        }
+        sampled
+    };
+    if let Some(mut recorder) = recorder {
+        recorder.0.record(tick.0, *inputs);
+    }
+}
+
+fn advance_tick(
+    mut tick: ResMut<FixedTick>,
+    playback: Option<Res<ReplayPlayback>>,
+    score: Res<Score>,
+    tuning: Res<GameTuning>,
+    mut toasts: EventWriter<ErrorToast>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    tick.0 += 1;
+    if let Some(playback) = playback {
+        // a reload part way through would only play out something else
+        if tuning.is_changed() {
+            if let Err(error) = playback.replay.check_tuning(&tuning) {
+                println!("replay stopped: {error}");
+                toasts.send(ErrorToast(format!("replay stopped: {error}")));
+                game_state.set(GameState::GameOver);
+                return;
+            }
+        }
+        if playback.finished(tick.0) {
+            println!(
+                "replay finished after {} ticks, score {} (recorded {})",
+                tick.0, score.0, playback.replay.score
+            );
+            game_state.set(GameState::GameOver);
+        }
+    }
+}
+
+fn finish_run(
+    mut commands: Commands,
+    recorder: Option<Res<ReplayRecorder>>,
+    playback: Option<Res<ReplayPlayback>>,
+    tick: Res<FixedTick>,
+    score: Res<Score>,
+    save_replays: Res<SaveReplays>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+) {
+    virtual_time.set_relative_speed_f64(1.0);
+    virtual_time.unpause();
+    if let Some(playback) = playback {
+        commands.remove_resource::<ReplayPlayback>();
+        if let Some(previous) = playback.previous {
+            previous.insert(&mut commands);
+        }
+        commands.insert_resource(LastReplay {
+            replay: playback.replay.clone(),
+            played_back: true,
+            path: None,
+        });
+    }
+
+    let Some(recorder) = recorder else {
+        return;
+    };
+    commands.remove_resource::<ReplayRecorder>();
+    let mut replay = recorder.0.clone();
+    replay.ticks = tick.0;
+    replay.score = score.0;
+    let mut path = None;
+    if save_replays.0 && replay.ticks > 0 {
+        match replay.save(&replay_dir()) {
+            Ok(saved) => {
+                println!("saved replay to {}", saved.display());
+                path = Some(saved);
+            }
+            Err(error) => {
+                println!("{error}");
+                commands.send_event(ErrorToast(error.to_string()));
+            }
+        }
+    }
+    commands.insert_resource(LastReplay {
+        replay,
+        played_back: false,
+        path,
+    });
+}
+
+// Space pauses, F cycles the playback speed, `.` steps one tick while paused
+// and Escape goes back to the replay list.
+fn replay_controls(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut controls: ResMut<ReplayControls>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        game_state.set(GameState::Replays);
+        return;
+    }
+    if keyboard_input.just_pressed(KeyCode::Space) {
+        controls.paused = !controls.paused;
+        if !controls.paused {
+            virtual_time.unpause();
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyF) {
+        controls.speed = (controls.speed + 1) % REPLAY_SPEEDS.len();
+        virtual_time.set_relative_speed_f64(REPLAY_SPEEDS[controls.speed]);
+    }
+    // hit-stop unpauses time when it ends, so the pause is held every frame
+    if controls.paused {
+        virtual_time.pause();
+        controls.step_requested = keyboard_input.just_pressed(KeyCode::Period);
+    }
+}
+
+fn frame_step_requested(controls: Res<ReplayControls>) -> bool {
+    controls.step_requested
+}
+
+fn step_one_tick(world: &mut World) {
+    world.resource_mut::<ReplayControls>().step_requested = false;
+    run_fixed_tick(world);
+}
+
+// Runs exactly one fixed tick outside the fixed loop, e.g. while virtual time
+// is paused, the same way the loop would.
+pub fn run_fixed_tick(world: &mut World) {
+    let fixed = world.resource::<Time<Fixed>>().as_generic();
+    *world.resource_mut::<Time>() = fixed;
+    world.run_schedule(FixedMain);
+    let virtual_time = world.resource::<Time<Virtual>>().as_generic();
+    *world.resource_mut::<Time>() = virtual_time;
+}
+
+fn update_status_text(
+    controls: Res<ReplayControls>,
+    tick: Res<FixedTick>,
+    playback: Res<ReplayPlayback>,
+    mut query: Query<&mut Text, With<ReplayStatusText>>,
+) {
+    let state = if controls.paused {
+        "paused".to_string()
+    } else {
+        format!("x{}", REPLAY_SPEEDS[controls.speed])
+    };
+    for mut text in &mut query {
+        text.0 = format!(
+            "REPLAY {state}  {}/{}\nspace pause  F speed  . step  esc back",
+            tick.0, playback.replay.ticks
+        );
+    }
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Time::<Fixed>::from_seconds(FIXED_TICK_SECONDS));
+        app.init_resource::<PlayerInputs>();
+        app.init_resource::<FixedTick>();
+        app.rollback_resource::<FixedTick>();
+        app.init_resource::<SaveReplays>();
+        app.init_resource::<ReplayControls>();
+        app.add_systems(OnEnter(GameState::Game), (reset_tick, start_run));
+        app.add_systems(
+            FixedPreUpdate,
+            sample_input.run_if(in_state(GameState::Game).and(gameplay_running)),
+        );
+        app.add_systems(
+            FixedLast,
+            advance_tick.run_if(in_state(GameState::Game).and(gameplay_running)),
+        );
+        app.add_systems(
+            Update,
+            (
+                replay_controls,
+                step_one_tick.run_if(frame_step_requested),
+                update_status_text,
+            )
+                .chain()
+                .run_if(in_state(GameState::Game).and(resource_exists::<ReplayPlayback>)),
+        );
+        app.add_systems(OnExit(GameState::Game), finish_run);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use bevy::{
+        app::App,
+        prelude::{KeyCode, NextState, State, Transform, Vec3, With},
+    };
+
+    use crate::{
+        game::{
+            player_jet_mod::Jet,
+            rng_mod::{random_seed, GameRng},
+            tuning_mod::GameTuning,
+            Score,
+        },
+        headless::headless_app,
+        test_support::{press, release, step, step_until, test_app, wait_for_sprites, TestApp},
+        GameState,
+    };
+
+    use super::{
+        Difficulty, FixedTick, GameMode, LastReplay, PlayerCount, Playfield, Replay, ReplayError,
+        ReplayPlayback, RunSettings,
+    };
+
+    fn state(app: &App) -> GameState {
+        *app.world().resource::<State<GameState>>().get()
+    }
+
+    fn jet_position(app: &mut App) -> Vec3 {
+        let world = app.world_mut();
+        world
+            .query_filtered::<&Transform, With<Jet>>()
+            .single(world)
+            .translation
+    }
+
+    fn tick(app: &App) -> u32 {
+        app.world().resource::<FixedTick>().0
+    }
+
+    fn record_and_play_back(seed: u64) {
+        let mut app = TestApp::default().seed(seed).build();
+        for (key, ticks) in [
+            (KeyCode::KeyD, 40),
+            (KeyCode::KeyW, 30),
+            (KeyCode::KeyA, 90),
+        ] {
+            press(&mut app, key);
+            step(&mut app, ticks);
+            release(&mut app, key);
+        }
+        step(&mut app, 200);
+        let recorded_tick = tick(&app);
+        let recorded_position = jet_position(&mut app);
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::GameOver);
+        step(&mut app, 2);
+        let replay = app.world().resource::<LastReplay>().replay.clone();
+        assert!(replay.ticks >= recorded_tick);
+        assert!(replay
+            .check_tuning(app.world().resource::<GameTuning>())
+            .is_ok());
+        assert_eq!(replay.inputs.len(), 4);
+
+        let mut playback = headless_app(replay.playfield());
+        playback.insert_resource(GameRng::new(replay.seed));
+        playback.insert_resource(ReplayPlayback::new(replay.clone()));
+        playback.update();
+        wait_for_sprites(&mut playback);
+        assert!(step_until(&mut playback, 1000, |app| tick(app) == recorded_tick));
+        assert_eq!(jet_position(&mut playback), recorded_position);
+
+        assert!(step_until(&mut playback, 1000, |app| {
+            !app.world().contains_resource::<ReplayPlayback>()
+        }));
+        assert_eq!(tick(&playback), replay.ticks);
+        assert_eq!(playback.world().resource::<Score>().0, replay.score);
+    }
+
+    #[test]
+    fn playback_reproduces_the_recorded_run() {
+        record_and_play_back(2024);
+    }
+
+    // The one test on a random seed, printed so a failure can be rerun.
+    #[test]
+    fn playback_reproduces_a_run_on_any_seed() {
+        let seed = random_seed();
+        println!("seed {seed}");
+        record_and_play_back(seed);
+    }
+
+    #[test]
+    fn playback_puts_the_menu_settings_back_afterwards() {
+        let mut app = test_app();
+        step(&mut app, 20);
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::GameOver);
+        step(&mut app, 2);
+        let mut replay = app.world().resource::<LastReplay>().replay.clone();
+        replay.playfield = (480., 640.);
+        replay.mode = GameMode::Versus;
+        replay.difficulty = Difficulty::Hard;
+        replay.players = 2;
+        let world = app.world();
+        let previous = RunSettings {
+            playfield: *world.resource::<Playfield>(),
+            mode: *world.resource::<GameMode>(),
+            difficulty: *world.resource::<Difficulty>(),
+            players: *world.resource::<PlayerCount>(),
+        };
+
+        let settings = replay.settings();
+        app.insert_resource(settings.playfield);
+        app.insert_resource(settings.mode);
+        app.insert_resource(settings.difficulty);
+        app.insert_resource(settings.players);
+        app.insert_resource(ReplayPlayback::new(replay).restoring(previous));
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Game);
+        step(&mut app, 2);
+        assert_eq!(app.world().resource::<Playfield>().width, 480.);
+        assert_eq!(*app.world().resource::<GameMode>(), GameMode::Versus);
+        assert!(step_until(&mut app, 100, |app| {
+            !app.world().contains_resource::<ReplayPlayback>()
+        }));
+        step(&mut app, 2);
+        let world = app.world();
+        assert_eq!(
+            world.resource::<Playfield>().size(),
+            previous.playfield.size()
+        );
+        assert_eq!(*world.resource::<GameMode>(), previous.mode);
+        assert_eq!(*world.resource::<Difficulty>(), previous.difficulty);
+        assert_eq!(*world.resource::<PlayerCount>(), previous.players);
+    }
+
+    #[test]
+    fn replays_survive_a_round_trip_through_ron() {
+        let replay = Replay {
+            version: crate::constants::REPLAY_FILE_VERSION,
+            seed: 7,
+            playfield: (480., 640.),
+            mode: GameMode::Campaign,
+            difficulty: Difficulty::Hard,
+            players: 2,
+            tuning: GameTuning::default().checksum(),
+            ticks: 120,
+            score: 3,
+            inputs: vec![(4, 8), (30, 0x0400), (31, 1)],
+        };
+        let text = ron::to_string(&replay).unwrap();
+        assert_eq!(ron::from_str::<Replay>(&text).unwrap(), replay);
+    }
+
+    #[test]
+    fn replays_the_game_cannot_play_are_refused_on_load() {
+        let dir = std::env::temp_dir().join(format!("space_fight_replays_{}", std::process::id()));
+        let replay = Replay {
+            version: crate::constants::REPLAY_FILE_VERSION,
+            seed: 7,
+            playfield: (480., 640.),
+            mode: GameMode::Campaign,
+            difficulty: Difficulty::Normal,
+            players: 1,
+            tuning: GameTuning::default().checksum(),
+            ticks: 120,
+            score: 3,
+            inputs: Vec::new(),
+        };
+        let path = replay.save(&dir).unwrap();
+        assert_eq!(Replay::load(&path).unwrap(), replay);
+        for broken in [
+            Replay {
+                players: 5,
+                ..replay.clone()
+            },
+            Replay {
+                playfield: (40., 640.),
+                ..replay.clone()
+            },
+        ] {
+            fs::write(&path, ron::to_string(&broken).unwrap()).unwrap();
+            assert!(matches!(Replay::load(&path), Err(ReplayError::Invalid(_))));
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn replays_recorded_with_other_tuning_are_refused() {
+        let mut app = test_app();
+        step(&mut app, 20);
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::GameOver);
+        step(&mut app, 2);
+        let replay = app.world().resource::<LastReplay>().replay.clone();
+        let mut tuning = app.world().resource::<GameTuning>().clone();
+        assert!(replay.check_tuning(&tuning).is_ok());
+        tuning.jet_speed += 1.0;
+        assert!(matches!(
+            replay.check_tuning(&tuning),
+            Err(ReplayError::Tuning)
+        ));
+
+        // and a reload part way through stops the playback
+        app.insert_resource(ReplayPlayback::new(replay));
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Game);
+        step(&mut app, 2);
+        assert_eq!(state(&app), GameState::Game);
+        app.insert_resource(tuning);
+        step(&mut app, 2);
+        assert_eq!(state(&app), GameState::GameOver);
+        assert!(!app.world().contains_resource::<ReplayPlayback>());
+    }
+}
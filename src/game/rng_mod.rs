@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+
+use bevy::{
+    app::{App, Plugin},
+    prelude::Resource,
+};
+use rand::{rngs::StdRng, thread_rng, Rng, SeedableRng};
+
+use super::rollback_mod::RollbackApp;
+
+pub struct RngPlugin;
+
+// Every random decision in a run draws from one of these streams. Each stream
+// is seeded from the run seed, so adding draws to one (e.g. more particles)
+// never changes what another one (e.g. enemy placement) produces.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum RngStream {
+    EnemySpawn,
+    Particles,
+    Stars,
+    Backdrop,
+    Sfx,
+}
+
+#[derive(Resource, Clone)]
+pub struct GameRng {
+    seed: u64,
+    streams: HashMap<RngStream, StdRng>,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        GameRng {
+            seed,
+            streams: HashMap::new(),
+        }
+    }
+
+    pub fn from_entropy() -> Self {
+        GameRng::new(random_seed())
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn stream(&mut self, stream: RngStream) -> &mut StdRng {
+        let seed = self.seed;
+        self.streams
+            .entry(stream)
+            .or_insert_with(|| StdRng::seed_from_u64(fork_seed(seed, stream)))
+    }
+}
+
+// Seed picked in the menu for the next run, `None` for a random one.
+#[derive(Resource, Default)]
+pub struct SeedSetting(pub Option<u64>);
+
+pub fn random_seed() -> u64 {
+    thread_rng().gen()
+}
+
+// FNV-1a, for hashes that have to come out the same on every build and
+// machine, which the std hasher doesn't promise.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+// splitmix64 finaliser, spreads the stream index over all bits of the seed
+fn fork_seed(seed: u64, stream: RngStream) -> u64 {
+    let mut z = seed.wrapping_add((stream as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SeedSetting>();
+        app.rollback_resource::<GameRng>();
+        if !app.world().contains_resource::<GameRng>() {
+            app.insert_resource(GameRng::from_entropy());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::{GameRng, RngStream};
+
+    #[test]
+    fn same_seed_gives_same_sequence() {
+        let mut a = GameRng::new(42);
+        let mut b = GameRng::new(42);
+        let draws_a: Vec<u32> = (0..8)
+            .map(|_| a.stream(RngStream::EnemySpawn).gen())
+            .collect();
+        let draws_b: Vec<u32> = (0..8)
+            .map(|_| b.stream(RngStream::EnemySpawn).gen())
+            .collect();
+        assert_eq!(draws_a, draws_b);
+    }
+
+    #[test]
+    fn streams_are_independent() {
+        let mut a = GameRng::new(7);
+        let mut b = GameRng::new(7);
+        for _ in 0..100 {
+            let _: u32 = b.stream(RngStream::Particles).gen();
+        }
+        let x: u32 = a.stream(RngStream::EnemySpawn).gen();
+        let y: u32 = b.stream(RngStream::EnemySpawn).gen();
+        assert_eq!(x, y);
+        assert_ne!(
+            GameRng::new(7).stream(RngStream::EnemySpawn).gen::<u64>(),
+            GameRng::new(7).stream(RngStream::Particles).gen::<u64>()
+        );
+    }
+}
@@ -0,0 +1,307 @@
+use std::{
+    any::Any,
+    collections::{HashMap, HashSet, VecDeque},
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+use bevy::{
+    app::{App, FixedFirst, Plugin},
+    ecs::world::{EntityRef, EntityWorldMut, Mut},
+    prelude::{
+        in_state, resource_exists, Component, Condition, DespawnRecursiveExt, Entity,
+        IntoSystemConfigs, Mesh2d, Resource, Transform, With, World,
+    },
+    sprite::{ColorMaterial, MeshMaterial2d, Sprite},
+};
+
+use crate::{constants::NET_MAX_PREDICTION_TICKS, GameState};
+
+use super::{
+    gameplay_running,
+    player_jet_mod::Players,
+    replay_mod::{run_fixed_tick, FixedTick},
+    Score,
+};
+
+pub struct RollbackPlugin;
+
+// Marks the entities that make up the simulation. While `Snapshots` is
+// present they are saved at the start of every tick, so the run can be rewound
+// when an input arrives that differs from the one it was simulated with.
+#[derive(Component, Clone)]
+pub struct Rollback;
+
+type Saved = Box<dyn Any + Send + Sync>;
+
+struct ComponentRollback {
+    save: fn(&EntityRef) -> Option<Saved>,
+    restore: fn(&mut EntityWorldMut, Option<&Saved>),
+}
+
+struct ResourceRollback {
+    save: fn(&World) -> Option<Saved>,
+    restore: fn(&mut World, Option<&Saved>),
+}
+
+// The component and resource types a snapshot holds, registered by the
+// plugins that own them.
+#[derive(Resource, Default)]
+struct RollbackRegistry {
+    components: Vec<ComponentRollback>,
+    resources: Vec<ResourceRollback>,
+}
+
+fn save_component<T: Component + Clone>(entity: &EntityRef) -> Option<Saved> {
+    entity
+        .get::<T>()
+        .map(|component| Box::new(component.clone()) as Saved)
+}
+
+fn restore_component<T: Component + Clone>(entity: &mut EntityWorldMut, saved: Option<&Saved>) {
+    match saved.and_then(|saved| saved.downcast_ref::<T>()) {
+        Some(component) => {
+            entity.insert(component.clone());
+        }
+        None => {
+            entity.remove::<T>();
+        }
+    }
+}
+
+fn save_resource<T: Resource + Clone>(world: &World) -> Option<Saved> {
+    world
+        .get_resource::<T>()
+        .map(|resource| Box::new(resource.clone()) as Saved)
+}
+
+fn restore_resource<T: Resource + Clone>(world: &mut World, saved: Option<&Saved>) {
+    match saved.and_then(|saved| saved.downcast_ref::<T>()) {
+        Some(resource) => world.insert_resource(resource.clone()),
+        None => {
+            world.remove_resource::<T>();
+        }
+    }
+}
+
+pub trait RollbackApp {
+    // Saves and restores `T` on every `Rollback` entity.
+    fn rollback_component<T: Component + Clone>(&mut self) -> &mut Self;
+    fn rollback_resource<T: Resource + Clone>(&mut self) -> &mut Self;
+}
+
+impl RollbackApp for App {
+    fn rollback_component<T: Component + Clone>(&mut self) -> &mut Self {
+        self.init_resource::<RollbackRegistry>();
+        self.world_mut()
+            .resource_mut::<RollbackRegistry>()
+            .components
+            .push(ComponentRollback {
+                save: save_component::<T>,
+                restore: restore_component::<T>,
+            });
+        self
+    }
+
+    fn rollback_resource<T: Resource + Clone>(&mut self) -> &mut Self {
+        self.init_resource::<RollbackRegistry>();
+        self.world_mut()
+            .resource_mut::<RollbackRegistry>()
+            .resources
+            .push(ResourceRollback {
+                save: save_resource::<T>,
+                restore: restore_resource::<T>,
+            });
+        self
+    }
+}
+
+// The simulation as it was at the start of `tick`. Components and resources
+// are in registry order, `None` where the entity didn't have one.
+struct Snapshot {
+    tick: u32,
+    entities: Vec<(Entity, Vec<Option<Saved>>)>,
+    resources: Vec<Option<Saved>>,
+}
+
+// The last few ticks of the run, oldest first.
+#[derive(Resource, Default)]
+pub struct Snapshots(VecDeque<Snapshot>);
+
+fn save_snapshot(world: &mut World) {
+    let tick = world.resource::<FixedTick>().0;
+    let entities: Vec<Entity> = world
+        .query_filtered::<Entity, With<Rollback>>()
+        .iter(world)
+        .collect();
+    let registry = world.resource::<RollbackRegistry>();
+    let snapshot = Snapshot {
+        tick,
+        entities: entities
+            .into_iter()
+            .map(|entity| {
+                let entity_ref = world.entity(entity);
+                let components = registry
+                    .components
+                    .iter()
+                    .map(|rollback| (rollback.save)(&entity_ref))
+                    .collect();
+                (entity, components)
+            })
+            .collect(),
+        resources: registry
+            .resources
+            .iter()
+            .map(|rollback| (rollback.save)(world))
+            .collect(),
+    };
+    let mut snapshots = world.resource_mut::<Snapshots>();
+    // a tick that is played again replaces what was saved for it before
+    while snapshots.0.back().is_some_and(|saved| saved.tick >= tick) {
+        snapshots.0.pop_back();
+    }
+    snapshots.0.push_back(snapshot);
+    while snapshots.0.len() > NET_MAX_PREDICTION_TICKS as usize + 2 {
+        snapshots.0.pop_front();
+    }
+}
+
+// Puts the simulation back the way `snapshot` has it. Entities that are gone
+// come back as new ones, returned by the id they were saved under.
+fn restore(
+    world: &mut World,
+    registry: &RollbackRegistry,
+    snapshot: &Snapshot,
+) -> HashMap<Entity, Entity> {
+    let saved: HashSet<Entity> = snapshot
+        .entities
+        .iter()
+        .map(|(entity, _)| *entity)
+        .collect();
+    let alive: Vec<Entity> = world
+        .query_filtered::<Entity, With<Rollback>>()
+        .iter(world)
+        .collect();
+    for entity in alive {
+        if !saved.contains(&entity) {
+            world.entity_mut(entity).despawn_recursive();
+        }
+    }
+    let mut respawned = HashMap::new();
+    for (entity, components) in &snapshot.entities {
+        let mut entity_mut = match world.get_entity_mut(*entity) {
+            Ok(entity_mut) => entity_mut,
+            Err(_) => {
+                let entity_mut = world.spawn_empty();
+                respawned.insert(*entity, entity_mut.id());
+                entity_mut
+            }
+        };
+        for (rollback, saved) in registry.components.iter().zip(components) {
+            (rollback.restore)(&mut entity_mut, saved.as_ref());
+        }
+    }
+    for (rollback, saved) in registry.resources.iter().zip(&snapshot.resources) {
+        (rollback.restore)(world, saved.as_ref());
+    }
+    respawned
+}
+
+// Rewinds the simulation to the start of `tick` and plays it forward again to
+// the tick it was on. Returns false if `tick` is too old to still be saved.
+pub fn roll_back(world: &mut World, tick: u32) -> bool {
+    let current = world.resource::<FixedTick>().0;
+    if tick >= current {
+        return true;
+    }
+    let restored = world.resource_scope(|world, mut snapshots: Mut<Snapshots>| {
+        world.resource_scope(|world, registry: Mut<RollbackRegistry>| {
+            let snapshot = snapshots.0.iter().find(|snapshot| snapshot.tick == tick)?;
+            let respawned = restore(world, &registry, snapshot);
+            // older snapshots still know the respawned entities by their old ids
+            for snapshot in &mut snapshots.0 {
+                for (entity, _) in &mut snapshot.entities {
+                    if let Some(new) = respawned.get(entity) {
+                        *entity = *new;
+                    }
+                }
+            }
+            Some(())
+        })
+    });
+    if restored.is_none() {
+        return false;
+    }
+    for _ in tick..current {
+        run_fixed_tick(world);
+    }
+    true
+}
+
+// Sums up the simulation, for telling whether two peers or two runs agree.
+pub fn state_checksum(world: &mut World) -> u64 {
+    let mut positions: Vec<[u32; 2]> = world
+        .query_filtered::<&Transform, With<Rollback>>()
+        .iter(world)
+        .map(|transform| {
+            [
+                transform.translation.x.to_bits(),
+                transform.translation.y.to_bits(),
+            ]
+        })
+        .collect();
+    // entity order depends on what was rolled back, so only the set counts
+    positions.sort_unstable();
+    let mut hasher = DefaultHasher::new();
+    world.resource::<FixedTick>().0.hash(&mut hasher);
+    world.resource::<Score>().0.hash(&mut hasher);
+    world.resource::<Players>().0.hash(&mut hasher);
+    positions.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl Plugin for RollbackPlugin {
+    fn build(&self, app: &mut App) {
+        // every rollback entity is placed and drawn with these
+        app.rollback_component::<Rollback>()
+            .rollback_component::<Transform>()
+            .rollback_component::<Sprite>()
+            .rollback_component::<Mesh2d>()
+            .rollback_component::<MeshMaterial2d<ColorMaterial>>();
+        app.add_systems(
+            FixedFirst,
+            save_snapshot.run_if(
+                in_state(GameState::Game)
+                    .and(resource_exists::<Snapshots>)
+                    .and(gameplay_running),
+            ),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::KeyCode;
+
+    use crate::{
+        game::replay_mod::FixedTick,
+        test_support::{press, step, TestApp},
+    };
+
+    use super::{roll_back, state_checksum, Snapshots};
+
+    #[test]
+    fn rolling_back_and_replaying_the_same_inputs_changes_nothing() {
+        let mut app = TestApp::default().seed(77).build();
+        app.insert_resource(Snapshots::default());
+        press(&mut app, KeyCode::KeyD);
+        step(&mut app, 120);
+        let tick = app.world().resource::<FixedTick>().0;
+        let before = state_checksum(app.world_mut());
+
+        assert!(roll_back(app.world_mut(), tick - 6));
+        assert_eq!(app.world().resource::<FixedTick>().0, tick);
+        assert_eq!(state_checksum(app.world_mut()), before);
+        // only the last few ticks are kept
+        assert!(!roll_back(app.world_mut(), tick - 60));
+    }
+}
@@ -0,0 +1,282 @@
+use bevy::{
+    app::{App, FixedPostUpdate, Plugin},
+    prelude::{
+        in_state, Condition, Event, EventReader, EventWriter, IntoSystemConfigs, OnEnter, Res,
+        ResMut, Resource,
+    },
+    time::Time,
+};
+
+use crate::{
+    constants::{
+        CollisionEvent, EnemyDestroyedEvent, GrazeEvent, PlayerHitEvent, ShotFiredEvent,
+        ACCURACY_BONUS_POINTS, COMBO_KILLS_PER_STEP, GRAZE_POINTS, MAX_COMBO_MULTIPLIER,
+        NO_DAMAGE_BONUS_POINTS, WAVE_KILLS,
+    },
+    GameState,
+};
+
+use super::{
+    gameplay_running, player_jet_mod::Players, rollback_mod::RollbackApp, tuning_mod::GameTuning,
+    Score,
+};
+
+pub struct ScoringPlugin;
+
+// Adds points to the run's score and to the share of the player who earned them.
+fn award(score: &mut Score, players: &mut Players, player: usize, points: usize) {
+    score.0 += points;
+    if let Some(stats) = players.0.get_mut(player) {
+        stats.score += points;
+    }
+}
+
+// Kills in quick succession build the chain, which raises the multiplier.
+// The chain drops when the window runs out or the jet is hit.
+#[derive(Resource, Default, Clone)]
+pub struct Combo {
+    pub chain: u32,
+    pub seconds_left: f32,
+}
+
+impl Combo {
+    pub fn multiplier(&self) -> usize {
+        (1 + self.chain / COMBO_KILLS_PER_STEP).min(MAX_COMBO_MULTIPLIER) as usize
+    }
+
+    fn refresh(&mut self, tuning: &GameTuning) {
+        self.seconds_left = tuning.combo_window_seconds;
+    }
+
+    fn reset(&mut self) {
+        self.chain = 0;
+        self.seconds_left = 0.;
+    }
+}
+
+// Tally for the wave in progress. A wave ends after `WAVE_KILLS` kills.
+#[derive(Resource, Clone)]
+pub struct Wave {
+    pub number: u32,
+    kills: u32,
+    shots_fired: u32,
+    shots_hit: u32,
+    damaged: bool,
+}
+
+impl Default for Wave {
+    fn default() -> Self {
+        Wave {
+            number: 1,
+            kills: 0,
+            shots_fired: 0,
+            shots_hit: 0,
+            damaged: false,
+        }
+    }
+}
+
+impl Wave {
+    fn accuracy(&self) -> f32 {
+        if self.shots_fired == 0 {
+            return 0.;
+        }
+        (self.shots_hit as f32 / self.shots_fired as f32).min(1.)
+    }
+}
+
+#[derive(Event)]
+pub struct WaveClearedEvent {
+    pub wave: u32,
+    pub accuracy: f32,
+    pub accuracy_bonus: usize,
+    pub no_damage_bonus: usize,
+}
+
+fn reset_scoring(mut combo: ResMut<Combo>, mut wave: ResMut<Wave>) {
+    combo.reset();
+    *wave = Wave::default();
+}
+
+fn count_shots(
+    mut shot_events: EventReader<ShotFiredEvent>,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut wave: ResMut<Wave>,
+) {
+    wave.shots_fired += shot_events.read().count() as u32;
+    wave.shots_hit += collision_events.read().count() as u32;
+}
+
+fn score_kills(
+    mut destroyed_events: EventReader<EnemyDestroyedEvent>,
+    mut combo: ResMut<Combo>,
+    mut wave: ResMut<Wave>,
+    mut score: ResMut<Score>,
+    mut players: ResMut<Players>,
+    mut wave_events: EventWriter<WaveClearedEvent>,
+    tuning: Res<GameTuning>,
+) {
+    for destroyed in destroyed_events.read() {
+        combo.chain += 1;
+        combo.refresh(&tuning);
+        let points = destroyed.points * combo.multiplier();
+        award(&mut score, &mut players, destroyed.player, points);
+
+        wave.kills += 1;
+        if wave.kills < WAVE_KILLS {
+            continue;
+        }
+        let accuracy = wave.accuracy();
+        let accuracy_bonus = (ACCURACY_BONUS_POINTS as f32 * accuracy) as usize;
+        let no_damage_bonus = if wave.damaged {
+            0
+        } else {
+            NO_DAMAGE_BONUS_POINTS * wave.number as usize
+        };
+        // the wave bonus belongs to the whole team
+        score.0 += accuracy_bonus + no_damage_bonus;
+        wave_events.send(WaveClearedEvent {
+            wave: wave.number,
+            accuracy,
+            accuracy_bonus,
+            no_damage_bonus,
+        });
+        *wave = Wave {
+            number: wave.number + 1,
+            ..Wave::default()
+        };
+    }
+}
+
+fn score_grazes(
+    mut graze_events: EventReader<GrazeEvent>,
+    mut combo: ResMut<Combo>,
+    mut score: ResMut<Score>,
+    mut players: ResMut<Players>,
+    tuning: Res<GameTuning>,
+) {
+    for graze in graze_events.read() {
+        // grazing keeps a chain alive but doesn't grow it
+        if combo.chain > 0 {
+            combo.refresh(&tuning);
+        }
+        let points = GRAZE_POINTS * combo.multiplier();
+        award(&mut score, &mut players, graze.player, points);
+    }
+}
+
+fn break_combo_on_hit(
+    mut hit_events: EventReader<PlayerHitEvent>,
+    mut combo: ResMut<Combo>,
+    mut wave: ResMut<Wave>,
+) {
+    if hit_events.read().count() > 0 {
+        combo.reset();
+        wave.damaged = true;
+    }
+}
+
+fn decay_combo(mut combo: ResMut<Combo>, time: Res<Time>) {
+    if combo.chain == 0 {
+        return;
+    }
+    combo.seconds_left -= time.delta_secs();
+    if combo.seconds_left <= 0. {
+        combo.reset();
+    }
+}
+
+impl Plugin for ScoringPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Combo>();
+        app.init_resource::<Wave>();
+        app.rollback_resource::<Combo>();
+        app.rollback_resource::<Wave>();
+        app.add_event::<WaveClearedEvent>();
+        app.add_systems(OnEnter(GameState::Game), reset_scoring);
+        app.add_systems(
+            FixedPostUpdate,
+            (
+                count_shots,
+                break_combo_on_hit,
+                score_kills,
+                score_grazes,
+                decay_combo,
+            )
+                .chain()
+                .run_if(in_state(GameState::Game).and(gameplay_running)),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        app::App,
+        prelude::{Or, Vec2, With},
+    };
+
+    use crate::{
+        constants::{
+            EnemyBullet, EnemyDestroyedEvent, COMBO_KILLS_PER_STEP, NO_DAMAGE_BONUS_POINTS,
+            WAVE_KILLS,
+        },
+        game::{eneymy_mod::EnemyKind, tuning_mod::GameTuning, Score},
+        test_support::{entities, step, test_app},
+    };
+
+    use super::{Combo, Wave};
+
+    // The kills are made up, so the real enemy and its bullets go. A graze
+    // would keep the combo alive and a hit would break it.
+    fn scoring_app() -> App {
+        let mut app = test_app();
+        for entity in entities::<Or<(With<EnemyKind>, With<EnemyBullet>)>>(&mut app) {
+            app.world_mut().despawn(entity);
+        }
+        app
+    }
+
+    // Kills trigger a short hit stop, so a few frames pass before the next one.
+    fn kill(app: &mut App, points: usize) {
+        app.world_mut().send_event(EnemyDestroyedEvent {
+            position: Vec2::ZERO,
+            points,
+            player: 0,
+        });
+        step(app, 10);
+    }
+
+    #[test]
+    fn rapid_kills_raise_the_multiplier_until_the_combo_decays() {
+        let mut app = scoring_app();
+        let start = app.world().resource::<Score>().0;
+
+        for _ in 0..COMBO_KILLS_PER_STEP {
+            kill(&mut app, 100);
+        }
+        assert_eq!(app.world().resource::<Combo>().multiplier(), 2);
+        kill(&mut app, 100);
+        let after_combo = app.world().resource::<Score>().0;
+        assert!(after_combo - start >= 100 * COMBO_KILLS_PER_STEP as usize + 200);
+
+        let window = app.world().resource::<GameTuning>().combo_window_seconds;
+        step(&mut app, (window * 60.) as u32 + 5);
+        let combo = app.world().resource::<Combo>();
+        assert_eq!(combo.chain, 0);
+        assert_eq!(combo.multiplier(), 1);
+    }
+
+    #[test]
+    fn clearing_a_wave_without_damage_pays_a_bonus() {
+        let mut app = scoring_app();
+        for _ in 0..WAVE_KILLS - 1 {
+            kill(&mut app, 0);
+        }
+        let before = app.world().resource::<Score>().0;
+        kill(&mut app, 0);
+        let after = app.world().resource::<Score>().0;
+        assert!(after - before >= NO_DAMAGE_BONUS_POINTS);
+        assert_eq!(app.world().resource::<Wave>().number, 2);
+    }
+}
@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+
+use bevy::{
+    app::{App, Plugin, Startup, Update},
+    asset::{AssetServer, Handle},
+    audio::{AudioPlayer, AudioSource, PlaybackSettings, SpatialListener, SpatialScale},
+    math::{Vec2, Vec3},
+    prelude::{
+        in_state, resource_changed, Added, Commands, Component, Event, EventReader, EventWriter,
+        IntoSystemConfigs, Query, Res, ResMut, Resource, Transform,
+    },
+};
+use rand::Rng;
+
+use crate::{
+    constants::{
+        Bullet, CollisionEvent, EnemyDestroyedEvent, PlayerHitEvent, MAX_VOLUME,
+        SFX_FALLOFF_DISTANCE,
+    },
+    GameState, Volume,
+};
+
+use super::{
+    playfield_mod::Playfield,
+    rng_mod::{GameRng, RngStream},
+};
+
+pub struct SfxPlugin;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Sfx {
+    Shot,
+    Hit,
+    Kill,
+    Pickup,
+    Bomb,
+    PlayerHit,
+}
+
+const ALL_SFX: [Sfx; 6] = [
+    Sfx::Shot,
+    Sfx::Hit,
+    Sfx::Kill,
+    Sfx::Pickup,
+    Sfx::Bomb,
+    Sfx::PlayerHit,
+];
+
+// The recordings one sound picks from, and how it is played. `pitch_jitter`
+// is how far the playback speed may stray from 1 either way.
+struct SfxBank {
+    files: &'static [&'static str],
+    volume: f32,
+    pitch_jitter: f32,
+    max_voices: usize,
+}
+
+impl Sfx {
+    fn bank(self) -> SfxBank {
+        match self {
+            Sfx::Shot => SfxBank {
+                files: &[
+                    "sounds/shot_1.wav",
+                    "sounds/shot_2.wav",
+                    "sounds/shot_3.wav",
+                ],
+                volume: 0.4,
+                pitch_jitter: 0.08,
+                max_voices: 4,
+            },
+            Sfx::Hit => SfxBank {
+                files: &["sounds/hitHurt.wav", "sounds/hit_2.wav"],
+                volume: 0.6,
+                pitch_jitter: 0.12,
+                max_voices: 4,
+            },
+            Sfx::Kill => SfxBank {
+                files: &["sounds/explosion_1.wav", "sounds/explosion_2.wav"],
+                volume: 0.8,
+                pitch_jitter: 0.1,
+                max_voices: 3,
+            },
+            Sfx::Pickup => SfxBank {
+                files: &["sounds/pickup_1.wav"],
+                volume: 0.7,
+                pitch_jitter: 0.05,
+                max_voices: 2,
+            },
+            Sfx::Bomb => SfxBank {
+                files: &["sounds/bomb_1.wav"],
+                volume: 1.0,
+                pitch_jitter: 0.0,
+                max_voices: 1,
+            },
+            Sfx::PlayerHit => SfxBank {
+                files: &["sounds/player_hit_1.wav"],
+                volume: 1.0,
+                pitch_jitter: 0.03,
+                max_voices: 1,
+            },
+        }
+    }
+}
+
+// Asks for a sound effect. Gameplay events are turned into these here, other
+// systems (pickups, bombs) can send them directly.
+#[derive(Event)]
+pub struct PlaySfx {
+    pub sfx: Sfx,
+    // where in the playfield it happened, `None` plays it centered
+    pub position: Option<Vec2>,
+}
+
+impl PlaySfx {
+    pub fn at(sfx: Sfx, position: Vec2) -> Self {
+        PlaySfx {
+            sfx,
+            position: Some(position),
+        }
+    }
+}
+
+#[derive(Resource)]
+struct SfxHandles(HashMap<Sfx, Vec<Handle<AudioSource>>>);
+
+// A sound effect that is still playing, counted against its bank's voice cap.
+#[derive(Component)]
+struct Voice(Sfx);
+
+fn load_banks(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let handles = ALL_SFX
+        .iter()
+        .map(|sfx| {
+            let files = sfx.bank().files;
+            (
+                *sfx,
+                files.iter().map(|file| asset_server.load(*file)).collect(),
+            )
+        })
+        .collect();
+    commands.insert_resource(SfxHandles(handles));
+    commands.spawn((SpatialListener::default(), Transform::default()));
+}
+
+// The ears sit on the playfield edges, so a sound at an edge is almost all in
+// that speaker and one in the middle is even in both.
+fn fit_listener(playfield: Res<Playfield>, mut listeners: Query<&mut SpatialListener>) {
+    for mut listener in &mut listeners {
+        *listener = SpatialListener::new(playfield.width);
+    }
+}
+
+// Where a sound is played from and how loud it is. Positions are squashed onto
+// the horizontal axis so only the pan depends on where the sound is, and
+// anything off screen fades with its distance from the edge.
+fn placement(position: Vec2, playfield: &Playfield) -> (Vec3, f32) {
+    let half_size = playfield.size() / 2.;
+    let outside = (position.abs() - half_size).max(Vec2::ZERO).length();
+    let attenuation = 1. / (1. + outside / SFX_FALLOFF_DISTANCE).powi(2);
+    let x = position.x.clamp(-half_size.x, half_size.x);
+    (Vec3::new(x, 0., 0.), attenuation)
+}
+
+fn gameplay_sfx(
+    fired: Query<&Transform, Added<Bullet>>,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut destroyed_events: EventReader<EnemyDestroyedEvent>,
+    mut hit_events: EventReader<PlayerHitEvent>,
+    mut sfx_events: EventWriter<PlaySfx>,
+) {
+    // one shot sound per volley
+    if let Some(bullet) = fired.iter().next() {
+        sfx_events.send(PlaySfx::at(Sfx::Shot, bullet.translation.truncate()));
+    }
+    for collision in collision_events.read() {
+        sfx_events.send(PlaySfx::at(Sfx::Hit, collision.position));
+    }
+    for destroyed in destroyed_events.read() {
+        sfx_events.send(PlaySfx::at(Sfx::Kill, destroyed.position));
+    }
+    for hit in hit_events.read() {
+        sfx_events.send(PlaySfx::at(Sfx::PlayerHit, hit.position));
+    }
+}
+
+fn play_sfx(
+    mut commands: Commands,
+    mut sfx_events: EventReader<PlaySfx>,
+    voices: Query<&Voice>,
+    handles: Res<SfxHandles>,
+    volume: Option<Res<Volume>>,
+    mut rng: ResMut<GameRng>,
+    playfield: Res<Playfield>,
+) {
+    let master = volume.map_or(1.0, |volume| volume.0 as f32 / MAX_VOLUME as f32);
+    let mut playing: HashMap<Sfx, usize> = HashMap::new();
+    for voice in &voices {
+        *playing.entry(voice.0).or_default() += 1;
+    }
+    for PlaySfx { sfx, position } in sfx_events.read() {
+        let bank = sfx.bank();
+        let playing = playing.entry(*sfx).or_default();
+        // too many of the same sound at once is just noise
+        if *playing >= bank.max_voices || master <= 0.0 {
+            continue;
+        }
+        let Some(variations) = handles.0.get(sfx).filter(|handles| !handles.is_empty()) else {
+            continue;
+        };
+        let rng = rng.stream(RngStream::Sfx);
+        let source = variations[rng.gen_range(0..variations.len())].clone();
+        let speed = 1.0 + rng.gen_range(-bank.pitch_jitter..=bank.pitch_jitter);
+        let settings = PlaybackSettings::DESPAWN.with_speed(speed);
+        let volume = bank.volume * master;
+        match position {
+            Some(position) => {
+                let (translation, attenuation) = placement(*position, &playfield);
+                commands.spawn((
+                    Voice(*sfx),
+                    AudioPlayer::new(source),
+                    settings
+                        .with_volume(bevy::audio::Volume::new(volume * attenuation))
+                        .with_spatial(true)
+                        .with_spatial_scale(SpatialScale::new_2d(1. / playfield.half_width())),
+                    Transform::from_translation(translation),
+                ));
+            }
+            None => {
+                commands.spawn((
+                    Voice(*sfx),
+                    AudioPlayer::new(source),
+                    settings.with_volume(bevy::audio::Volume::new(volume)),
+                ));
+            }
+        }
+        *playing += 1;
+    }
+}
+
+impl Plugin for SfxPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PlaySfx>();
+        app.add_systems(Startup, load_banks);
+        app.add_systems(Update, fit_listener.run_if(resource_changed::<Playfield>));
+        app.add_systems(
+            Update,
+            (gameplay_sfx, play_sfx)
+                .chain()
+                .run_if(in_state(GameState::Game)),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::{step, test_app};
+
+    use bevy::math::Vec2;
+
+    use crate::game::playfield_mod::Playfield;
+
+    use super::{placement, PlaySfx, Sfx, Voice};
+
+    #[test]
+    fn a_burst_of_sounds_is_capped_per_bank() {
+        let mut app = test_app();
+        // nothing plays back headless, so voices stay alive and count against the cap
+        for _ in 0..10 {
+            app.world_mut()
+                .send_event(PlaySfx::at(Sfx::Kill, Vec2::ZERO));
+        }
+        step(&mut app, 1);
+        let world = app.world_mut();
+        let kills = world
+            .query::<&Voice>()
+            .iter(world)
+            .filter(|voice| voice.0 == Sfx::Kill)
+            .count();
+        assert_eq!(kills, Sfx::Kill.bank().max_voices);
+    }
+
+    #[test]
+    fn sounds_pan_with_the_source_and_fade_off_screen() {
+        let playfield = Playfield::default();
+        let left = Vec2::new(-playfield.half_width() + 10., 100.);
+        let (translation, attenuation) = placement(left, &playfield);
+        assert!(translation.x < 0.);
+        assert_eq!(translation.y, 0.);
+        assert_eq!(attenuation, 1.);
+
+        let above = Vec2::new(0., playfield.half_height() + 200.);
+        let (_, far) = placement(above, &playfield);
+        let (_, farther) = placement(above * 2., &playfield);
+        assert!(far < 1. && farther < far);
+    }
+}
@@ -1,104 +1,242 @@
+use std::f32::consts::TAU;
+
 use bevy::{
     app::{Plugin, Startup, Update},
-    asset::Assets,
+    asset::{Assets, Handle},
     color::Color,
-    math::Vec3,
+    math::{Vec2, Vec3},
     prelude::{
-        default, App, Circle, Commands, Component, Mesh, Mesh2d, Query, Res, ResMut, Resource,
-        Transform, With,
+        App, Circle, Commands, Component, DetectChanges, Entity, Mesh, Mesh2d, Query, Res, ResMut,
+        Resource, State, Transform, With,
+    },
+    sprite::{ColorMaterial, MeshMaterial2d},
+    time::Time,
+};
+use rand::Rng;
+
+use crate::{
+    constants::{
+        SCROLL_SPEED_EASE_RATE, SCROLL_SPEED_MAX_FACTOR, SCROLL_SPEED_PER_WAVE, STAR_SCROLL_SPEED,
     },
-    sprite::{ColorMaterial, MaterialMesh2dBundle, MeshMaterial2d},
-    time::{Time, Timer, TimerMode},
-    window::{PrimaryWindow, Window},
+    GameState,
+};
+
+use super::{
+    playfield_mod::Playfield,
+    rng_mod::{GameRng, RngStream},
+    scoring_mod::Wave,
 };
 
 pub struct SpacePointPlugin;
 
+struct StarLayer {
+    // fraction of the base scroll speed, smaller layers read as further away
+    parallax: f32,
+    // stars per square unit of playfield area
+    density: f32,
+    radius: f32,
+    color: Color,
+    z: f32,
+}
+
+const STAR_LAYERS: [StarLayer; 3] = [
+    StarLayer {
+        parallax: 0.25,
+        density: 0.0005,
+        radius: 0.8,
+        color: Color::srgb(0.45, 0.45, 0.6),
+        z: -30.,
+    },
+    StarLayer {
+        parallax: 0.55,
+        density: 0.0002,
+        radius: 1.3,
+        color: Color::srgb(0.7, 0.7, 0.9),
+        z: -20.,
+    },
+    StarLayer {
+        parallax: 1.0,
+        density: 0.00006,
+        radius: 2.0,
+        color: Color::srgb(1.0, 1.0, 1.0),
+        z: -10.,
+    },
+];
+
+// Scroll speed of the nearest layer in playfield units per second. It picks up
+// as the waves of a run go by.
 #[derive(Resource)]
-struct SpacePointTimer(Timer);
+pub struct ScrollSpeed(pub f32);
+
+impl Default for ScrollSpeed {
+    fn default() -> Self {
+        ScrollSpeed(STAR_SCROLL_SPEED)
+    }
+}
+
+// The speed the field settles at on `wave`, the base speed in the menus.
+pub fn wave_scroll_speed(wave: u32) -> f32 {
+    let factor = 1. + SCROLL_SPEED_PER_WAVE * wave.saturating_sub(1) as f32;
+    STAR_SCROLL_SPEED * factor.min(SCROLL_SPEED_MAX_FACTOR)
+}
+
+// One mesh shared by every star and one material per layer.
+#[derive(Resource)]
+struct StarfieldHandles {
+    mesh: Handle<Mesh>,
+    materials: Vec<Handle<ColorMaterial>>,
+}
 
 #[derive(Component)]
-struct SpacePoint;
+struct SpacePoint {
+    layer: usize,
+    phase: f32,
+    twinkle_speed: f32,
+}
 
 fn setup_system(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
 ) {
-    for i in -50..50 {
-        for j in 0..50 {
-            commands.spawn((
-                SpacePoint,
-                // MaterialMesh2dBundle {
-                //     mesh: meshes.add(Circle::default()).into(),
-                //     material: materials.add(Color::srgb(7.5, 0.0, 7.5)),
-                //     transform: Transform::from_translation(Vec3::new(
-                //         (j * 20) as f32,
-                //         i as f32 * 20.,
-                //         0.,
-                //     )),
-                //     ..default()
-                // },
-                (
-                    Mesh2d(meshes.add(Circle::default()).into()),
-                    MeshMaterial2d(materials.add(Color::srgb(7.5, 0.0, 7.5))),
-                    Transform::from_translation(Vec3::new((j * 20) as f32, i as f32 * 20., 0.)),
-                ),
-            ));
+    let handles = StarfieldHandles {
+        mesh: meshes.add(Circle::new(1.)),
+        materials: STAR_LAYERS
+            .iter()
+            .map(|layer| materials.add(layer.color))
+            .collect(),
+    };
+    commands.insert_resource(handles);
+}
+
+fn spawn_stars(
+    commands: &mut Commands,
+    rng: &mut impl Rng,
+    handles: &StarfieldHandles,
+    size: Vec2,
+) {
+    let half = size / 2.;
+    for (index, layer) in STAR_LAYERS.iter().enumerate() {
+        let count = (size.x * size.y * layer.density) as usize;
+        for _ in 0..count {
             commands.spawn((
-                SpacePoint,
-                // MaterialMesh2dBundle {
-                //     mesh: meshes.add(Circle::default()).into(),
-                //     material: materials.add(Color::srgb(7.5, 0.0, 7.5)),
-                //     transform: Transform::from_translation(Vec3::new(
-                //         (-1 * (j * 20)) as f32,
-                //         i as f32 * 20.,
-                //         0.,
-                //     )),
-                //     ..default()
-                // },
-                (
-                    Mesh2d(meshes.add(Circle::default())),
-                    MeshMaterial2d(materials.add(Color::srgb(7.5, 0.0, 7.5))),
-                    Transform::from_translation(Vec3::new(
-                        (-1 * (j * 20)) as f32,
-                        i as f32 * 20.,
-                        0.,
-                    )),
-                ),
+                SpacePoint {
+                    layer: index,
+                    phase: rng.gen_range(0.0..TAU),
+                    twinkle_speed: rng.gen_range(1.0..4.0),
+                },
+                Mesh2d(handles.mesh.clone()),
+                MeshMaterial2d(handles.materials[index].clone()),
+                Transform {
+                    translation: Vec3::new(
+                        rng.gen_range(-half.x..half.x),
+                        rng.gen_range(-half.y..half.y),
+                        layer.z,
+                    ),
+                    scale: Vec3::splat(layer.radius),
+                    ..Transform::default()
+                },
             ));
         }
     }
 }
 
 fn update_background(
-    mut query: Query<&mut Transform, With<SpacePoint>>,
+    mut query: Query<(&mut SpacePoint, &mut Transform)>,
     time: Res<Time>,
-    window_query: Query<&Window, With<PrimaryWindow>>,
-    mut timer: ResMut<SpacePointTimer>,
+    scroll_speed: Res<ScrollSpeed>,
+    playfield: Res<Playfield>,
 ) {
-    let window = window_query.get_single().unwrap();
-    let window_height = window.height();
-
-    if timer.0.tick(time.delta()).just_finished() {
-        for mut transform in &mut query {
-            transform.translation.y = transform.translation.y + 10.0;
-            if transform.translation.y > window_height / 2. {
-                transform.translation.y = 0. + (transform.translation.y - window_height / 2.);
-            }
+    let delta = time.delta_secs();
+
+    for (mut point, mut transform) in &mut query {
+        let layer = &STAR_LAYERS[point.layer];
+        transform.translation.y += scroll_speed.0 * layer.parallax * delta;
+        if transform.translation.y > playfield.half_height() {
+            transform.translation.y -= playfield.height;
         }
+
+        point.phase = (point.phase + point.twinkle_speed * delta) % TAU;
+        let twinkle = 0.75 + 0.25 * point.phase.sin();
+        transform.scale = Vec3::splat(layer.radius * twinkle);
+    }
+}
+
+// Eases towards the speed of the wave being played so a new wave doesn't jolt
+// the backdrop, and back to the base speed once the run is over.
+fn follow_gameplay_speed(
+    mut scroll_speed: ResMut<ScrollSpeed>,
+    wave: Res<Wave>,
+    state: Res<State<GameState>>,
+    time: Res<Time>,
+) {
+    let target = if *state.get() == GameState::Game {
+        wave_scroll_speed(wave.number)
+    } else {
+        STAR_SCROLL_SPEED
+    };
+    let step = (SCROLL_SPEED_EASE_RATE * time.delta_secs()).min(1.);
+    scroll_speed.0 += (target - scroll_speed.0) * step;
+}
+
+// Fills the playfield whenever its size changes, including the first frame.
+fn refit_to_playfield(
+    mut commands: Commands,
+    playfield: Res<Playfield>,
+    handles: Res<StarfieldHandles>,
+    stars: Query<Entity, With<SpacePoint>>,
+    mut rng: ResMut<GameRng>,
+) {
+    if !playfield.is_changed() {
+        return;
+    }
+    for star in &stars {
+        commands.entity(star).despawn();
     }
+    spawn_stars(
+        &mut commands,
+        rng.stream(RngStream::Stars),
+        &handles,
+        playfield.size(),
+    );
 }
 
-// Implement methods or traits for the struct
 impl Plugin for SpacePointPlugin {
     fn build(&self, app: &mut App) {
-        println!("This is the build process now");
-        app.insert_resource(SpacePointTimer(Timer::from_seconds(
-            5.,
-            TimerMode::Repeating,
-        )));
+        app.init_resource::<ScrollSpeed>();
         app.add_systems(Startup, setup_system);
-        app.add_systems(Update, update_background);
+        app.add_systems(
+            Update,
+            (refit_to_playfield, follow_gameplay_speed, update_background),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        constants::STAR_SCROLL_SPEED,
+        game::scoring_mod::Wave,
+        test_support::{step, test_app},
+    };
+
+    use super::{wave_scroll_speed, ScrollSpeed};
+
+    #[test]
+    fn the_starfield_speeds_up_with_the_waves() {
+        let mut app = test_app();
+        step(&mut app, 10);
+        let first = app.world().resource::<ScrollSpeed>().0;
+        assert!((first - STAR_SCROLL_SPEED).abs() < 0.01);
+
+        app.world_mut().resource_mut::<Wave>().number = 5;
+        step(&mut app, 30);
+        let easing = app.world().resource::<ScrollSpeed>().0;
+        assert!(easing > first && easing < wave_scroll_speed(5));
+        step(&mut app, 600);
+        let settled = app.world().resource::<ScrollSpeed>().0;
+        assert!((settled - wave_scroll_speed(5)).abs() < 0.5);
+        // later waves stop adding speed at the cap
+        assert_eq!(wave_scroll_speed(100), wave_scroll_speed(1000));
     }
 }
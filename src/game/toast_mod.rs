@@ -0,0 +1,200 @@
+use std::collections::HashSet;
+
+use bevy::{
+    app::{App, Plugin, Startup, Update},
+    asset::{AssetId, AssetLoadFailedEvent, Assets, Handle, RenderAssetUsages},
+    audio::AudioSource,
+    color::{Alpha, Color},
+    image::Image,
+    prelude::{
+        BuildChildren, Children, Commands, Component, DespawnRecursiveExt, Entity, Event,
+        EventReader, EventWriter, FlexDirection, GlobalZIndex, IntoSystemConfigs, Local, Node,
+        PositionType, Query, Res, ResMut, Resource, Text, TextColor, TextFont, Val, With,
+    },
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+    sprite::Sprite,
+    text::Font,
+    time::{Real, Time, Timer, TimerMode},
+    ui::{AlignItems, BackgroundColor, UiRect},
+};
+
+use crate::constants::{TOAST_LIMIT, TOAST_SECONDS};
+
+use super::level_mod::Level;
+
+pub struct ToastPlugin;
+
+// Something went wrong that the player should know about, shown for a few
+// seconds at the bottom of the screen whatever state the game is in.
+#[derive(Event)]
+pub struct ErrorToast(pub String);
+
+#[derive(Component)]
+struct ToastRoot;
+
+#[derive(Component)]
+struct Toast(Timer);
+
+// Drawn in place of a sprite image that failed to load, so the object is
+// still visible and can still be hit.
+#[derive(Resource)]
+struct FallbackImage(Handle<Image>);
+
+fn setup_toasts(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    commands.spawn((
+        ToastRoot,
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(24.),
+            width: Val::Percent(100.),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            row_gap: Val::Px(4.),
+            ..Default::default()
+        },
+        GlobalZIndex(i32::MAX - 1),
+    ));
+    let fallback = Image::new_fill(
+        Extent3d {
+            width: 64,
+            height: 64,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[255, 0, 255, 255],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    commands.insert_resource(FallbackImage(images.add(fallback)));
+}
+
+fn show_toasts(
+    mut commands: Commands,
+    mut toast_events: EventReader<ErrorToast>,
+    roots: Query<(Entity, Option<&Children>), With<ToastRoot>>,
+) {
+    for (root, children) in &roots {
+        let mut shown = children.map_or(0, |children| children.len());
+        for toast in toast_events.read() {
+            println!("error: {}", toast.0);
+            // the oldest toasts make room for new ones
+            if let Some(children) = children {
+                for oldest in children
+                    .iter()
+                    .take((shown + 1).saturating_sub(TOAST_LIMIT))
+                {
+                    commands.entity(*oldest).despawn_recursive();
+                }
+            }
+            shown = (shown + 1).min(TOAST_LIMIT);
+            commands.entity(root).with_child((
+                Toast(Timer::from_seconds(TOAST_SECONDS, TimerMode::Once)),
+                Node {
+                    padding: UiRect::axes(Val::Px(10.), Val::Px(4.)),
+                    ..Default::default()
+                },
+                BackgroundColor(Color::srgba(0.35, 0.05, 0.05, 0.85)),
+                Text::new(toast.0.clone()),
+                TextFont {
+                    font_size: 16.,
+                    ..Default::default()
+                },
+                TextColor(Color::WHITE),
+            ));
+        }
+    }
+}
+
+// Toasts run on real time so they still fade while the game is paused.
+fn expire_toasts(
+    mut commands: Commands,
+    mut toasts: Query<(Entity, &mut Toast, &mut TextColor, &mut BackgroundColor)>,
+    time: Res<Time<Real>>,
+) {
+    for (entity, mut toast, mut color, mut background) in &mut toasts {
+        toast.0.tick(time.delta());
+        if toast.0.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let alpha = (toast.0.remaining_secs() / 0.5).min(1.);
+        color.0.set_alpha(alpha);
+        background.0.set_alpha(alpha * 0.85);
+    }
+}
+
+fn report_failed_assets(
+    mut images: EventReader<AssetLoadFailedEvent<Image>>,
+    mut sounds: EventReader<AssetLoadFailedEvent<AudioSource>>,
+    mut fonts: EventReader<AssetLoadFailedEvent<Font>>,
+    mut levels: EventReader<AssetLoadFailedEvent<Level>>,
+    mut toasts: EventWriter<ErrorToast>,
+) {
+    let failed = images
+        .read()
+        .map(|event| &event.path)
+        .chain(sounds.read().map(|event| &event.path))
+        .chain(fonts.read().map(|event| &event.path))
+        .chain(levels.read().map(|event| &event.path));
+    for path in failed {
+        toasts.send(ErrorToast(format!("could not load {path}")));
+    }
+}
+
+fn replace_missing_sprites(
+    mut failed_events: EventReader<AssetLoadFailedEvent<Image>>,
+    mut failed: Local<HashSet<AssetId<Image>>>,
+    fallback: Res<FallbackImage>,
+    mut sprites: Query<&mut Sprite>,
+) {
+    failed.extend(failed_events.read().map(|event| event.id));
+    if failed.is_empty() {
+        return;
+    }
+    for mut sprite in &mut sprites {
+        if failed.contains(&sprite.image.id()) {
+            sprite.image = fallback.0.clone();
+        }
+    }
+}
+
+impl Plugin for ToastPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ErrorToast>();
+        app.add_systems(Startup, setup_toasts);
+        app.add_systems(
+            Update,
+            (
+                report_failed_assets,
+                show_toasts,
+                expire_toasts,
+                replace_missing_sprites,
+            )
+                .chain(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        constants::TOAST_LIMIT,
+        test_support::{entities, step, test_app},
+    };
+
+    use super::{ErrorToast, Toast};
+
+    #[test]
+    fn only_the_newest_toasts_stay_on_screen() {
+        let mut app = test_app();
+        for n in 0..TOAST_LIMIT + 3 {
+            app.world_mut()
+                .send_event(ErrorToast(format!("failure {n}")));
+            step(&mut app, 1);
+        }
+        assert_eq!(
+            entities::<bevy::prelude::With<Toast>>(&mut app).len(),
+            TOAST_LIMIT
+        );
+    }
+}
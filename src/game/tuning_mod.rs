@@ -0,0 +1,433 @@
+use std::{fmt, fs};
+
+use bevy::{
+    app::{App, Plugin, Startup, Update},
+    asset::{
+        io::{file::FileAssetReader, Reader},
+        Asset, AssetApp, AssetEvent, AssetLoadFailedEvent, AssetLoader, AssetServer, Assets,
+        Handle, LoadContext,
+    },
+    prelude::{
+        resource_exists, Commands, EventReader, EventWriter, IntoSystemConfigs, Res, ResMut,
+        Resource,
+    },
+    reflect::TypePath,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::constants::TUNING_FILE;
+
+use super::{rng_mod::fnv1a, toast_mod::ErrorToast};
+
+pub struct TuningPlugin;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct EnemyFireSeconds {
+    pub scout: f32,
+    pub fighter: f32,
+    pub heavy: f32,
+}
+
+// How likely each kind is to be picked, relative to the others.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct EnemyMix {
+    pub scout: f32,
+    pub fighter: f32,
+    pub heavy: f32,
+}
+
+// One point on the endless difficulty curve. Between points every value is
+// eased linearly, after the last one it stays put.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct EndlessStep {
+    pub at_seconds: f32,
+    // time between new enemies, and how many can be up at once
+    pub spawn_seconds: f32,
+    pub max_enemies: u32,
+    // multiplies enemy health
+    pub health: f32,
+    // multiplies how often enemies fire
+    pub fire_rate: f32,
+    pub mix: EnemyMix,
+}
+
+impl EndlessStep {
+    fn lerp(self, to: EndlessStep, t: f32) -> EndlessStep {
+        let lerp = |from: f32, to: f32| from + (to - from) * t;
+        EndlessStep {
+            at_seconds: lerp(self.at_seconds, to.at_seconds),
+            spawn_seconds: lerp(self.spawn_seconds, to.spawn_seconds),
+            max_enemies: lerp(self.max_enemies as f32, to.max_enemies as f32).round() as u32,
+            health: lerp(self.health, to.health),
+            fire_rate: lerp(self.fire_rate, to.fire_rate),
+            mix: EnemyMix {
+                scout: lerp(self.mix.scout, to.mix.scout),
+                fighter: lerp(self.mix.fighter, to.mix.fighter),
+                heavy: lerp(self.mix.heavy, to.mix.heavy),
+            },
+        }
+    }
+}
+
+// The numbers designers tweak, read from `assets/tuning/game.tuning.ron`. The
+// resource holds the values in use, the asset is what the file last loaded as.
+#[derive(Asset, Resource, TypePath, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct GameTuning {
+    pub jet_speed: f32,
+    pub bullet_speed: f32,
+    pub fire_seconds: f32,
+    pub enemy_health: i32,
+    pub enemy_bullet_speed: f32,
+    pub enemy_fire_seconds: EnemyFireSeconds,
+    pub lives: u32,
+    pub bombs: u32,
+    pub invulnerable_seconds: f32,
+    pub graze_radius: f32,
+    pub combo_window_seconds: f32,
+    // the endless mode curve, in order of time
+    pub endless: Vec<EndlessStep>,
+}
+
+impl Default for GameTuning {
+    fn default() -> Self {
+        GameTuning {
+            jet_speed: 5.0,
+            bullet_speed: 5.0,
+            fire_seconds: 0.5,
+            enemy_health: 20,
+            enemy_bullet_speed: 3.0,
+            enemy_fire_seconds: EnemyFireSeconds {
+                scout: 2.2,
+                fighter: 1.6,
+                heavy: 1.0,
+            },
+            lives: 3,
+            bombs: 3,
+            invulnerable_seconds: 2.0,
+            graze_radius: 36.0,
+            combo_window_seconds: 2.5,
+            endless: vec![
+                EndlessStep {
+                    at_seconds: 0.0,
+                    spawn_seconds: 4.0,
+                    max_enemies: 2,
+                    health: 0.5,
+                    fire_rate: 0.6,
+                    mix: EnemyMix {
+                        scout: 8.0,
+                        fighter: 2.0,
+                        heavy: 0.0,
+                    },
+                },
+                EndlessStep {
+                    at_seconds: 90.0,
+                    spawn_seconds: 2.5,
+                    max_enemies: 4,
+                    health: 1.0,
+                    fire_rate: 1.0,
+                    mix: EnemyMix {
+                        scout: 5.0,
+                        fighter: 4.0,
+                        heavy: 1.0,
+                    },
+                },
+                EndlessStep {
+                    at_seconds: 300.0,
+                    spawn_seconds: 1.2,
+                    max_enemies: 7,
+                    health: 1.8,
+                    fire_rate: 1.8,
+                    mix: EnemyMix {
+                        scout: 3.0,
+                        fighter: 4.0,
+                        heavy: 3.0,
+                    },
+                },
+            ],
+        }
+    }
+}
+
+impl GameTuning {
+    // Where the endless curve is `seconds` into a run.
+    pub fn endless_at(&self, seconds: f32) -> EndlessStep {
+        let next = self
+            .endless
+            .iter()
+            .position(|step| step.at_seconds > seconds)
+            .unwrap_or(self.endless.len());
+        match (next.checked_sub(1), self.endless.get(next)) {
+            (Some(before), Some(after)) => {
+                let from = self.endless[before];
+                let t = (seconds - from.at_seconds) / (after.at_seconds - from.at_seconds);
+                from.lerp(*after, t)
+            }
+            (Some(last), None) => self.endless[last],
+            // validation makes sure the curve starts at 0
+            (None, _) => self.endless[0],
+        }
+    }
+
+    // Identifies these exact values, so replays and online peers can tell
+    // they were played with something else.
+    pub fn checksum(&self) -> u64 {
+        fnv1a(ron::to_string(self).unwrap_or_default().as_bytes())
+    }
+
+    pub fn validate(&self) -> Result<(), TuningError> {
+        positive("jet_speed", self.jet_speed)?;
+        positive("bullet_speed", self.bullet_speed)?;
+        positive("fire_seconds", self.fire_seconds)?;
+        positive("enemy_bullet_speed", self.enemy_bullet_speed)?;
+        positive("enemy_fire_seconds.scout", self.enemy_fire_seconds.scout)?;
+        positive(
+            "enemy_fire_seconds.fighter",
+            self.enemy_fire_seconds.fighter,
+        )?;
+        positive("enemy_fire_seconds.heavy", self.enemy_fire_seconds.heavy)?;
+        positive("invulnerable_seconds", self.invulnerable_seconds)?;
+        positive("graze_radius", self.graze_radius)?;
+        positive("combo_window_seconds", self.combo_window_seconds)?;
+        if self.enemy_health < 1 {
+            return Err(TuningError::Invalid {
+                field: "enemy_health",
+                reason: format!("must be at least 1, got {}", self.enemy_health),
+            });
+        }
+        validate_endless(&self.endless)?;
+        if self.lives < 1 {
+            return Err(TuningError::Invalid {
+                field: "lives",
+                reason: "must be at least 1, got 0".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+fn validate_endless(curve: &[EndlessStep]) -> Result<(), TuningError> {
+    let invalid = |reason: String| TuningError::Invalid {
+        field: "endless",
+        reason,
+    };
+    let Some(first) = curve.first() else {
+        return Err(invalid("needs at least one step".to_string()));
+    };
+    if first.at_seconds != 0.0 {
+        return Err(invalid(format!(
+            "the first step must be at 0 seconds, got {}",
+            first.at_seconds
+        )));
+    }
+    for pair in curve.windows(2) {
+        if pair[1].at_seconds <= pair[0].at_seconds {
+            return Err(invalid(format!(
+                "steps must be in order of time, {} comes after {}",
+                pair[1].at_seconds, pair[0].at_seconds
+            )));
+        }
+    }
+    for step in curve {
+        positive("endless.spawn_seconds", step.spawn_seconds)?;
+        positive("endless.health", step.health)?;
+        positive("endless.fire_rate", step.fire_rate)?;
+        let mix = [step.mix.scout, step.mix.fighter, step.mix.heavy];
+        if mix
+            .iter()
+            .any(|weight| !(weight.is_finite() && *weight >= 0.0))
+            || mix.iter().sum::<f32>() <= 0.0
+        {
+            return Err(TuningError::Invalid {
+                field: "endless.mix",
+                reason: format!("weights must be 0 or more and not all 0, got {mix:?}"),
+            });
+        }
+        if step.max_enemies < 1 {
+            return Err(TuningError::Invalid {
+                field: "endless.max_enemies",
+                reason: "must be at least 1, got 0".to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn positive(field: &'static str, value: f32) -> Result<(), TuningError> {
+    if value.is_finite() && value > 0.0 {
+        Ok(())
+    } else {
+        Err(TuningError::Invalid {
+            field,
+            reason: format!("must be a number above 0, got {value}"),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum TuningError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+    Invalid { field: &'static str, reason: String },
+}
+
+impl fmt::Display for TuningError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TuningError::Io(error) => write!(f, "could not read tuning file: {error}"),
+            TuningError::Ron(error) => write!(f, "could not parse tuning file: {error}"),
+            TuningError::Invalid { field, reason } => {
+                write!(f, "invalid tuning value `{field}`: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TuningError {}
+
+impl From<std::io::Error> for TuningError {
+    fn from(error: std::io::Error) -> Self {
+        TuningError::Io(error)
+    }
+}
+
+impl From<ron::error::SpannedError> for TuningError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        TuningError::Ron(error)
+    }
+}
+
+fn parse(bytes: &[u8]) -> Result<GameTuning, TuningError> {
+    let tuning: GameTuning = ron::de::from_bytes(bytes)?;
+    tuning.validate()?;
+    Ok(tuning)
+}
+
+#[derive(Default)]
+struct TuningLoader;
+
+impl AssetLoader for TuningLoader {
+    type Asset = GameTuning;
+    type Settings = ();
+    type Error = TuningError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<GameTuning, TuningError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        parse(&bytes)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tuning.ron"]
+    }
+}
+
+// Kept so the asset stays loaded and keeps being watched for changes.
+#[derive(Resource)]
+pub struct GameTuningHandle(pub Handle<GameTuning>);
+
+// Reads the file straight away so the very first tick already uses it,
+// falling back to the built-in values if it is missing or invalid.
+pub fn read_tuning_file() -> GameTuning {
+    let path = FileAssetReader::get_base_path()
+        .join("assets")
+        .join(TUNING_FILE);
+    match fs::read(&path)
+        .map_err(TuningError::from)
+        .and_then(|bytes| parse(&bytes))
+    {
+        Ok(tuning) => tuning,
+        Err(error) => {
+            println!("{error}, using the default tuning");
+            GameTuning::default()
+        }
+    }
+}
+
+fn load_tuning(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(GameTuningHandle(asset_server.load(TUNING_FILE)));
+}
+
+// A rejected edit leaves the last good values in place.
+fn apply_tuning_changes(
+    mut asset_events: EventReader<AssetEvent<GameTuning>>,
+    mut failed_events: EventReader<AssetLoadFailedEvent<GameTuning>>,
+    handle: Res<GameTuningHandle>,
+    assets: Res<Assets<GameTuning>>,
+    mut tuning: ResMut<GameTuning>,
+    mut toasts: EventWriter<ErrorToast>,
+) {
+    for event in asset_events.read() {
+        if !event.is_loaded_with_dependencies(&handle.0) && !event.is_modified(&handle.0) {
+            continue;
+        }
+        if let Some(loaded) = assets.get(&handle.0) {
+            if *loaded != *tuning {
+                println!("tuning reloaded from {TUNING_FILE}");
+                *tuning = loaded.clone();
+            }
+        }
+    }
+    for failed in failed_events.read() {
+        println!("{}, keeping the current tuning", failed.error);
+        toasts.send(ErrorToast(format!(
+            "{TUNING_FILE} rejected, keeping the current tuning"
+        )));
+    }
+}
+
+impl Plugin for TuningPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<GameTuning>();
+        app.init_asset_loader::<TuningLoader>();
+        if !app.world().contains_resource::<GameTuning>() {
+            app.insert_resource(read_tuning_file());
+        }
+        app.add_systems(Startup, load_tuning);
+        app.add_systems(
+            Update,
+            apply_tuning_changes.run_if(resource_exists::<GameTuningHandle>),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, GameTuning, TuningError};
+
+    #[test]
+    fn bundled_tuning_file_matches_the_defaults() {
+        let bytes = include_bytes!("../../assets/tuning/game.tuning.ron");
+        assert_eq!(parse(bytes).unwrap(), GameTuning::default());
+    }
+
+    #[test]
+    fn endless_curve_eases_between_steps_and_holds_after_the_last() {
+        let tuning = GameTuning::default();
+        let (first, second) = (tuning.endless[0], tuning.endless[1]);
+        let halfway = tuning.endless_at((first.at_seconds + second.at_seconds) / 2.);
+        assert_eq!(halfway.health, (first.health + second.health) / 2.);
+        assert_eq!(halfway.mix.heavy, (first.mix.heavy + second.mix.heavy) / 2.);
+        let last = *tuning.endless.last().unwrap();
+        assert_eq!(tuning.endless_at(last.at_seconds + 1000.), last);
+    }
+
+    #[test]
+    fn invalid_values_are_rejected_with_the_field_name() {
+        let bytes = include_str!("../../assets/tuning/game.tuning.ron")
+            .replace("fire_seconds: 0.5", "fire_seconds: -1.0");
+        let error = parse(bytes.as_bytes()).unwrap_err();
+        assert!(matches!(
+            error,
+            TuningError::Invalid {
+                field: "fire_seconds",
+                ..
+            }
+        ));
+        assert!(error.to_string().contains("fire_seconds"));
+    }
+}
@@ -0,0 +1,333 @@
+use bevy::{
+    app::{App, FixedPostUpdate, Plugin},
+    asset::AssetServer,
+    color::Color,
+    math::{Vec2, Vec3},
+    prelude::{
+        default, in_state, resource_equals, Commands, Condition, EventReader, IntoSystemConfigs,
+        NextState, OnEnter, Res, ResMut, Resource, Transform,
+    },
+    sprite::Sprite,
+    time::Time,
+};
+
+use crate::{
+    constants::{
+        EnemyDestroyedEvent, ENEMY_SPACE_SPRITE_NAME, MAX_PLAYERS, VERSUS_GARBAGE_CHAIN,
+        VERSUS_ROUNDS_TO_WIN, VERSUS_VOLLEY_BULLETS,
+    },
+    GameState,
+};
+
+use super::{
+    eneymy_mod::{
+        enemy_bullet, get_enemy_bundle, random_enemy_position, EnemyBulletHandles, EnemyKind,
+        GarbageEnemy,
+    },
+    gameplay_running,
+    net_mod::NetSession,
+    player_jet_mod::{GameEntity, Players},
+    playfield_mod::Playfield,
+    replay_mod::FixedTick,
+    rng_mod::GameRng,
+    rollback_mod::RollbackApp,
+    tuning_mod::GameTuning,
+    Difficulty, GameMode,
+};
+
+pub struct VersusPlugin;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RoundResult {
+    Won(usize),
+    // both went down on the same tick
+    Draw,
+}
+
+// Rounds won so far in a versus match. A new match starts once someone has
+// won this one, or from the menu.
+#[derive(Resource, Default, Clone, Copy, Debug)]
+pub struct VersusMatch {
+    pub wins: [u32; MAX_PLAYERS],
+    pub round: u32,
+    // how the round being played ended, once it has
+    pub result: Option<RoundResult>,
+}
+
+impl VersusMatch {
+    pub fn winner(&self) -> Option<usize> {
+        self.wins
+            .iter()
+            .position(|wins| *wins >= VERSUS_ROUNDS_TO_WIN)
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct Chain {
+    kills: u32,
+    seconds_left: f32,
+}
+
+// Each player's own run of kills, apart from the shared combo.
+#[derive(Resource, Default, Clone)]
+struct Chains([Chain; MAX_PLAYERS]);
+
+// The stretch of x that a player's jet and enemies keep to. Outside versus
+// everyone shares the whole width.
+pub fn lane(mode: GameMode, playfield: &Playfield, player: usize) -> (f32, f32) {
+    match (mode, player) {
+        (GameMode::Versus, 0) => (-playfield.half_width(), 0.),
+        (GameMode::Versus, _) => (0., playfield.half_width()),
+        _ => (-playfield.half_width(), playfield.half_width()),
+    }
+}
+
+// Whose half of a versus playfield `x` is on.
+pub fn lane_owner(x: f32) -> usize {
+    if x < 0. {
+        0
+    } else {
+        1
+    }
+}
+
+fn start_round(
+    mut commands: Commands,
+    mut versus: ResMut<VersusMatch>,
+    mut chains: ResMut<Chains>,
+    mode: Res<GameMode>,
+    playfield: Res<Playfield>,
+) {
+    *chains = Chains::default();
+    if *mode != GameMode::Versus {
+        return;
+    }
+    if versus.winner().is_some() {
+        *versus = VersusMatch::default();
+    }
+    versus.round += 1;
+    versus.result = None;
+    println!(
+        "versus round {}, P1 {} - {} P2",
+        versus.round, versus.wins[0], versus.wins[1]
+    );
+    // the line between the two halves
+    commands.spawn((
+        GameEntity,
+        Sprite {
+            color: Color::srgba(1., 1., 1., 0.25),
+            custom_size: Some(Vec2::new(2., playfield.height)),
+            ..default()
+        },
+        Transform::from_xyz(0., 0., -1.),
+    ));
+}
+
+fn reset_match(mut versus: ResMut<VersusMatch>) {
+    *versus = VersusMatch::default();
+}
+
+// Every few kills in a row land something on the rival's side, an extra
+// enemy first, then a volley of bullets, and so on while the chain lasts.
+#[allow(clippy::too_many_arguments)]
+fn send_garbage(
+    mut commands: Commands,
+    mut destroyed_events: EventReader<EnemyDestroyedEvent>,
+    mut chains: ResMut<Chains>,
+    mut rng: ResMut<GameRng>,
+    asset_server: Res<AssetServer>,
+    handles: Res<EnemyBulletHandles>,
+    playfield: Res<Playfield>,
+    difficulty: Res<Difficulty>,
+    tuning: Res<GameTuning>,
+    time: Res<Time>,
+) {
+    for chain in &mut chains.0 {
+        chain.seconds_left -= time.delta_secs();
+        if chain.seconds_left <= 0. {
+            chain.kills = 0;
+        }
+    }
+    for destroyed in destroyed_events.read() {
+        let Some(chain) = chains.0.get_mut(destroyed.player) else {
+            continue;
+        };
+        chain.kills += 1;
+        chain.seconds_left = tuning.combo_window_seconds;
+        if chain.kills % VERSUS_GARBAGE_CHAIN != 0 {
+            continue;
+        }
+        let rival = MAX_PLAYERS - 1 - destroyed.player;
+        let (left, right) = lane(GameMode::Versus, &playfield, rival);
+        if (chain.kills / VERSUS_GARBAGE_CHAIN) % 2 == 1 {
+            println!("P{} sends an enemy over", destroyed.player + 1);
+            let kind = EnemyKind::random(&mut rng);
+            commands.spawn((
+                get_enemy_bundle(
+                    asset_server.load(ENEMY_SPACE_SPRITE_NAME),
+                    kind,
+                    *difficulty,
+                    &tuning,
+                ),
+                GarbageEnemy,
+                Transform::from_translation(random_enemy_position(
+                    &mut rng,
+                    &playfield,
+                    (left, right),
+                )),
+            ));
+        } else {
+            println!("P{} sends a volley over", destroyed.player + 1);
+            let spacing = (right - left) / VERSUS_VOLLEY_BULLETS as f32;
+            for bullet in 0..VERSUS_VOLLEY_BULLETS {
+                let x = left + (bullet as f32 + 0.5) * spacing;
+                commands.spawn(enemy_bullet(
+                    &handles,
+                    Vec3::new(x, playfield.half_height(), 0.),
+                ));
+            }
+        }
+    }
+}
+
+// A round goes to whoever is still flying once the other is out of lives.
+fn end_round(
+    players: Res<Players>,
+    mut versus: ResMut<VersusMatch>,
+    net: Option<Res<NetSession>>,
+    tick: Res<FixedTick>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    if versus.result.is_some() || players.0.len() < MAX_PLAYERS {
+        return;
+    }
+    // online only once no late input can undo it
+    if net.is_some_and(|net| !net.confirmed_through(tick.0 + 1)) {
+        return;
+    }
+    let standing: Vec<usize> = players
+        .0
+        .iter()
+        .enumerate()
+        .filter(|(_, stats)| stats.lives > 0)
+        .map(|(player, _)| player)
+        .collect();
+    let result = match standing.as_slice() {
+        [] => RoundResult::Draw,
+        [winner] => RoundResult::Won(*winner),
+        _ => return,
+    };
+    if let RoundResult::Won(winner) = result {
+        versus.wins[winner] += 1;
+        println!("P{} takes round {}", winner + 1, versus.round);
+    } else {
+        println!("round {} is a draw", versus.round);
+    }
+    versus.result = Some(result);
+    game_state.set(GameState::GameOver);
+}
+
+impl Plugin for VersusPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VersusMatch>();
+        app.init_resource::<Chains>();
+        app.rollback_resource::<Chains>();
+        app.add_systems(OnEnter(GameState::Game), start_round);
+        app.add_systems(OnEnter(GameState::Menu), reset_match);
+        app.add_systems(
+            FixedPostUpdate,
+            (send_garbage, end_round).chain().run_if(
+                in_state(GameState::Game)
+                    .and(resource_equals(GameMode::Versus))
+                    .and(gameplay_running),
+            ),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::{KeyCode, NextState, State, Transform, Vec2, With};
+
+    use crate::{
+        constants::{EnemyDestroyedEvent, PlayerHitEvent, JET_SQUARE_BOX_LENGTH, MAX_PLAYERS},
+        game::{
+            eneymy_mod::GarbageEnemy,
+            player_jet_mod::{Jet, Players},
+            GameMode,
+        },
+        test_support::{press, step, TestApp},
+        GameState,
+    };
+
+    use super::{RoundResult, VersusMatch};
+
+    #[test]
+    fn each_player_keeps_to_their_half_and_chains_send_enemies_across() {
+        let mut app = TestApp::default()
+            .players(MAX_PLAYERS)
+            .mode(GameMode::Versus)
+            .build();
+        // both head for the middle
+        press(&mut app, KeyCode::KeyD);
+        press(&mut app, KeyCode::ArrowLeft);
+        step(&mut app, 200);
+        let world = app.world_mut();
+        for (transform, jet) in world.query::<(&Transform, &Jet)>().iter(world) {
+            let x = transform.translation.x;
+            match jet.player {
+                0 => assert!(x + JET_SQUARE_BOX_LENGTH / 2. <= 0., "P1 crossed to {x}"),
+                _ => assert!(x - JET_SQUARE_BOX_LENGTH / 2. >= 0., "P2 crossed to {x}"),
+            }
+        }
+
+        for _ in 0..3 {
+            app.world_mut().send_event(EnemyDestroyedEvent {
+                position: Vec2::ZERO,
+                points: 0,
+                player: 0,
+            });
+            step(&mut app, 10);
+        }
+        let world = app.world_mut();
+        let garbage: Vec<f32> = world
+            .query_filtered::<&Transform, With<GarbageEnemy>>()
+            .iter(world)
+            .map(|transform| transform.translation.x)
+            .collect();
+        assert_eq!(garbage.len(), 1);
+        assert!(garbage[0] > 0., "garbage landed on the sender's side");
+    }
+
+    #[test]
+    fn the_last_one_flying_takes_the_round_and_two_rounds_take_the_match() {
+        let mut app = TestApp::default()
+            .players(MAX_PLAYERS)
+            .mode(GameMode::Versus)
+            .build();
+        for round in 1..=2 {
+            app.world_mut().resource_mut::<Players>().0[1].lives = 1;
+            app.world_mut().send_event(PlayerHitEvent {
+                position: Vec2::ZERO,
+                player: 1,
+            });
+            step(&mut app, 3);
+            assert_eq!(
+                *app.world().resource::<State<GameState>>().get(),
+                GameState::GameOver
+            );
+            let versus = *app.world().resource::<VersusMatch>();
+            assert_eq!(versus.round, round);
+            assert_eq!(versus.result, Some(RoundResult::Won(0)));
+            assert_eq!(versus.wins, [round, 0]);
+            app.world_mut()
+                .resource_mut::<NextState<GameState>>()
+                .set(GameState::Game);
+            step(&mut app, 2);
+        }
+        // the match was won, so the next round starts a new one
+        let versus = *app.world().resource::<VersusMatch>();
+        assert_eq!(versus.wins, [0, 0]);
+        assert_eq!(versus.round, 1);
+    }
+}
@@ -0,0 +1,274 @@
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use bevy::{
+    app::{App, PluginsState},
+    asset::{AssetApp, AssetPlugin},
+    audio::{AudioLoader, AudioSource},
+    image::{CompressedImageFormats, Image, ImageLoader},
+    input::InputPlugin,
+    prelude::{Mesh, MinimalPlugins},
+    sprite::{ColorMaterial, TextureAtlasLayout},
+    state::{app::AppExtStates, app::StatesPlugin},
+    text::TextPlugin,
+    time::TimeUpdateStrategy,
+};
+
+use crate::{
+    constants::{FIXED_TICK_SECONDS, HEADLESS_DEFAULT_TICKS},
+    game::{
+        camera_effects_mod::CameraEffectsSettings,
+        game_plugin,
+        highscore_mod::HighScores,
+        net_mod::{insert_online_run, NetEndpoint, NetLink, NetRun, NetSession, NetShim},
+        playfield_mod::Playfield,
+        replay_mod::{FixedTick, Replay, ReplayPlayback, SaveReplays},
+        rng_mod::{random_seed, GameRng},
+        rollback_mod::state_checksum,
+        tuning_mod::{read_tuning_file, GameTuning},
+        Difficulty, GameMode, PlayerCount, Score,
+    },
+    GameState,
+};
+
+// `--headless [--ticks N] [--playfield WIDTHxHEIGHT] [--seed N] [--replay FILE]
+//  [--host PORT | --join ADDRESS]`
+pub struct HeadlessOptions {
+    pub ticks: u32,
+    pub playfield: Playfield,
+    pub seed: Option<u64>,
+    // plays a saved replay to the end and checks it reaches the recorded score
+    pub replay: Option<PathBuf>,
+    // plays an online run against another headless instance
+    pub net: Option<NetEndpoint>,
+}
+
+impl HeadlessOptions {
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Option<Self>, String> {
+        let mut headless = false;
+        let mut options = HeadlessOptions {
+            ticks: HEADLESS_DEFAULT_TICKS,
+            playfield: Playfield::default(),
+            seed: None,
+            replay: None,
+            net: None,
+        };
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--headless" => headless = true,
+                "--ticks" => {
+                    let value = args.next().ok_or("--ticks needs a value")?;
+                    options.ticks = value
+                        .parse()
+                        .map_err(|_| format!("invalid tick count '{value}'"))?;
+                }
+                "--playfield" => {
+                    let value = args.next().ok_or("--playfield needs a value")?;
+                    let playfield = value
+                        .split_once('x')
+                        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+                        .map(|(width, height)| Playfield { width, height })
+                        .filter(Playfield::is_playable)
+                        .ok_or(format!(
+                            "invalid playfield size '{value}', expected WIDTHxHEIGHT"
+                        ))?;
+                    options.playfield = playfield;
+                }
+                "--seed" => {
+                    let value = args.next().ok_or("--seed needs a value")?;
+                    let seed = value
+                        .parse()
+                        .map_err(|_| format!("invalid seed '{value}'"))?;
+                    options.seed = Some(seed);
+                }
+                "--replay" => {
+                    let value = args.next().ok_or("--replay needs a file")?;
+                    options.replay = Some(PathBuf::from(value));
+                }
+                "--host" => {
+                    let value = args.next().ok_or("--host needs a port")?;
+                    let port = value
+                        .parse()
+                        .map_err(|_| format!("invalid port '{value}'"))?;
+                    options.net = Some(NetEndpoint::Host(port));
+                }
+                "--join" => {
+                    let value = args.next().ok_or("--join needs an address")?;
+                    options.net = Some(NetEndpoint::Join(value));
+                }
+                _ => return Err(format!("unknown argument '{arg}'")),
+            }
+        }
+        Ok(headless.then_some(options))
+    }
+}
+
+// Builds the game without a window or renderer. Time advances by a fixed
+// step on every `update`, so a run only depends on the inputs it is given.
+pub fn headless_app(playfield: Playfield) -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        StatesPlugin,
+        // nobody edits files during a headless run
+        AssetPlugin {
+            watch_for_changes_override: Some(false),
+            ..Default::default()
+        },
+        InputPlugin,
+        TextPlugin,
+    ))
+    // the asset types the game spawns, normally registered by the render and audio plugins
+    .init_asset::<Image>()
+    .init_asset::<Mesh>()
+    .init_asset::<ColorMaterial>()
+    .init_asset::<AudioSource>()
+    .init_asset::<TextureAtlasLayout>()
+    .register_asset_loader(ImageLoader::new(CompressedImageFormats::NONE))
+    .init_asset_loader::<AudioLoader>()
+    .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+        FIXED_TICK_SECONDS,
+    )))
+    .insert_resource(playfield)
+    .insert_resource(SaveReplays(false))
+    .insert_resource(HighScores::default())
+    .insert_resource(CameraEffectsSettings::default())
+    .insert_state(GameState::Game)
+    .add_plugins(game_plugin);
+
+    while app.plugins_state() == PluginsState::Adding {
+        bevy::tasks::tick_global_task_pools_on_main_thread();
+    }
+    app.finish();
+    app.cleanup();
+    app
+}
+
+pub fn run(options: HeadlessOptions, shim: NetShim) {
+    if let Some(path) = options.replay {
+        return verify_replay(&path);
+    }
+    if let Some(endpoint) = &options.net {
+        return run_online(&options, endpoint, shim);
+    }
+    let mut app = headless_app(options.playfield);
+    if let Some(seed) = options.seed {
+        app.insert_resource(GameRng::new(seed));
+    }
+    for _ in 0..options.ticks {
+        app.update();
+    }
+    let score = app.world().resource::<Score>();
+    let seed = app.world().resource::<GameRng>().seed();
+    println!(
+        "headless run finished after {} ticks, score {}, seed {}",
+        options.ticks, score.0, seed
+    );
+}
+
+fn run_online(options: &HeadlessOptions, endpoint: &NetEndpoint, shim: NetShim) {
+    let mut link = match NetLink::open(endpoint, shim) {
+        Ok(link) => link,
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(2);
+        }
+    };
+    if let (true, Some(address)) = (link.is_host(), link.local_addr()) {
+        println!("waiting for a player on {address}");
+    }
+    let offer = NetRun {
+        seed: options.seed.unwrap_or_else(random_seed),
+        mode: GameMode::default(),
+        difficulty: Difficulty::default(),
+        playfield: (options.playfield.width, options.playfield.height),
+        tuning: read_tuning_file().checksum(),
+    };
+    let deadline = Instant::now() + Duration::from_secs(30);
+    let run = loop {
+        match link.connect(&offer) {
+            Ok(Some(run)) => break run,
+            Ok(None) => {}
+            Err(error) => {
+                eprintln!("{error}");
+                std::process::exit(1);
+            }
+        }
+        if Instant::now() > deadline {
+            eprintln!("nobody to play with");
+            std::process::exit(1);
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    };
+
+    let mut app = headless_app(run.playfield());
+    insert_online_run(app.world_mut(), link, run);
+    app.world_mut()
+        .resource_mut::<NetSession>()
+        .stop_at(options.ticks);
+    let deadline = Instant::now() + Duration::from_secs(60);
+    let mut rollbacks = 0;
+    // the session goes away early if the run ends before the stop tick
+    while let Some(session) = app.world().get_resource::<NetSession>() {
+        let tick = app.world().resource::<FixedTick>().0;
+        rollbacks = session.rollbacks;
+        if session.settled(tick) {
+            break;
+        }
+        if Instant::now() > deadline {
+            eprintln!("online run stalled at tick {tick}");
+            std::process::exit(1);
+        }
+        app.update();
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    let tick = app.world().resource::<FixedTick>().0;
+    let score = app.world().resource::<Score>().0;
+    let checksum = state_checksum(app.world_mut());
+    // keep answering for a moment so the other side gets our last inputs too
+    for _ in 0..60 {
+        app.update();
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    println!(
+        "online run finished after {} ticks, score {}, seed {}, checksum {:016x}, {} rollbacks",
+        tick, score, run.seed, checksum, rollbacks
+    );
+}
+
+fn verify_replay(path: &std::path::Path) {
+    let replay = match Replay::load(path) {
+        Ok(replay) => replay,
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(2);
+        }
+    };
+    let mut app = headless_app(replay.playfield());
+    if let Err(error) = replay.check_tuning(app.world().resource::<GameTuning>()) {
+        eprintln!("{error}");
+        std::process::exit(2);
+    }
+    app.insert_resource(GameRng::new(replay.seed));
+    app.insert_resource(replay.mode);
+    app.insert_resource(replay.difficulty);
+    app.insert_resource(PlayerCount(replay.players));
+    app.insert_resource(ReplayPlayback::new(replay.clone()));
+    // one extra update for the start of the run and one for the state change at the end
+    for _ in 0..replay.ticks + 2 {
+        app.update();
+    }
+    let score = app.world().resource::<Score>().0;
+    if score == replay.score {
+        println!("replay verified: seed {}, score {}", replay.seed, score);
+    } else {
+        println!(
+            "replay mismatch: recorded score {}, played back {}",
+            replay.score, score
+        );
+        std::process::exit(1);
+    }
+}
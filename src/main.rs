@@ -1,10 +1,13 @@
 mod constants;
 mod game;
+mod headless;
 mod menu;
+#[cfg(test)]
+mod test_support;
+mod user_data;
 mod utils;
 
-use constants::CollisionSound;
-use game::game_plugin;
+use game::{game_plugin, net_mod::NetShim};
 use menu::menu_plugin;
 
 use bevy::{core_pipeline::tonemapping::Tonemapping, prelude::*};
@@ -12,22 +15,52 @@ use bevy::{core_pipeline::tonemapping::Tonemapping, prelude::*};
 #[derive(Resource, Debug, Component, PartialEq, Eq, Clone, Copy)]
 struct Volume(u32);
 
+// Separate from `Volume`, which is for sound effects.
+#[derive(Resource, Debug, PartialEq, Eq, Clone, Copy)]
+struct MusicVolume(u32);
+
 //public states
 #[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
 enum GameState {
     #[default]
     Splash,
+    Loading,
     Menu,
+    Lobby,
     Game,
+    GameOver,
+    Replays,
+    HighScores,
 }
 
 fn setup_camera(mut commands: Commands) {
     commands.spawn(Camera2dBundle::default());
 }
 
+fn exit_with(error: String) -> ! {
+    eprintln!("{error}");
+    std::process::exit(2);
+}
+
 fn main() {
+    let (shim, args) =
+        NetShim::from_args(std::env::args().skip(1)).unwrap_or_else(|e| exit_with(e));
+    match headless::HeadlessOptions::from_args(args) {
+        Ok(Some(options)) => return headless::run(options, shim),
+        Ok(None) => {}
+        Err(error) => exit_with(error),
+    }
+
     App::new()
-        .add_plugins(DefaultPlugins)
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                title: "Space Fight".to_string(),
+                resolution: (600., 800.).into(),
+                ..default()
+            }),
+            ..default()
+        }))
+        .insert_resource(shim)
         .add_systems(Startup, setup_camera)
         .init_state::<GameState>()
         .add_plugins((menu_plugin, game_plugin))
@@ -0,0 +1,371 @@
+use bevy::{
+    input::keyboard::{Key, KeyboardInput},
+    prelude::*,
+};
+
+use super::{
+    despawn_screen, menu_button, menu_text, screen_root, start_run, ButtonActions, GameState,
+};
+use crate::{
+    constants::{FIXED_TICK_SECONDS, HIGH_SCORE_NAME_LENGTH},
+    game::{
+        attack_mod::{format_ticks, AttackRun, TIME_ATTACK_WAVES},
+        daily_mod::{DailyAttempt, DailyModifiers},
+        highscore_mod::{today, HighScoreEntry, HighScores},
+        level_mod::CurrentLevel,
+        player_jet_mod::Players,
+        replay_mod::LastReplay,
+        rng_mod::GameRng,
+        toast_mod::ErrorToast,
+        versus_mod::{RoundResult, VersusMatch},
+        Difficulty, GameMode, Score,
+    },
+};
+
+// Tag component used to tag entities added on the game over screen
+#[derive(Component)]
+struct OnGameOverScreen;
+
+#[derive(Component)]
+struct NameEntryText;
+
+#[derive(Component)]
+enum GameOverAction {
+    Retry,
+    MainMenu,
+}
+
+// A run that made the high score table, waiting for the player's name.
+#[derive(Resource)]
+struct PendingHighScore(HighScoreEntry);
+
+pub fn game_over_plugin(app: &mut App) {
+    app.add_systems(
+        OnEnter(GameState::GameOver),
+        (check_for_high_score, setup_game_over).chain(),
+    )
+    .add_systems(
+        Update,
+        (game_over_action, enter_name, update_name_text)
+            .chain()
+            .run_if(in_state(GameState::GameOver)),
+    )
+    .add_systems(
+        OnExit(GameState::GameOver),
+        (save_pending_high_score, despawn_screen::<OnGameOverScreen>),
+    );
+}
+
+// Played-back runs are already on the table, or weren't good enough.
+#[allow(clippy::too_many_arguments)]
+fn check_for_high_score(
+    mut commands: Commands,
+    score: Res<Score>,
+    high_scores: Res<HighScores>,
+    mode: Res<GameMode>,
+    difficulty: Res<Difficulty>,
+    level: Res<CurrentLevel>,
+    attack: Res<AttackRun>,
+    last_replay: Option<Res<LastReplay>>,
+) {
+    commands.remove_resource::<PendingHighScore>();
+    let Some(last_replay) = last_replay else {
+        return;
+    };
+    if !mode.has_high_scores() {
+        return;
+    }
+    if last_replay.played_back {
+        return;
+    }
+    let entry = HighScoreEntry {
+        name: high_scores.last_name().to_string(),
+        score: score.0,
+        date: today(),
+        seed: last_replay.replay.seed,
+        stage: level.0 + 1,
+        replay: last_replay
+            .path
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .map(|name| name.to_string_lossy().into_owned()),
+        ticks: attack.finished.filter(|_| mode.ranks_by_time()),
+    };
+    if high_scores.qualifies(*mode, *difficulty, &entry) {
+        commands.insert_resource(PendingHighScore(entry));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn setup_game_over(
+    mut commands: Commands,
+    score: Res<Score>,
+    players: Res<Players>,
+    high_scores: Res<HighScores>,
+    mode: Res<GameMode>,
+    difficulty: Res<Difficulty>,
+    last_replay: Option<Res<LastReplay>>,
+    pending: Option<Res<PendingHighScore>>,
+    versus: Res<VersusMatch>,
+    attack: Res<AttackRun>,
+    modifiers: Res<DailyModifiers>,
+    attempt: Res<DailyAttempt>,
+) {
+    let best = if mode.ranks_by_time() {
+        high_scores
+            .best_ticks(*mode, *difficulty)
+            .map_or("--".to_string(), format_ticks)
+    } else {
+        high_scores
+            .best(*mode, *difficulty)
+            .unwrap_or(0)
+            .to_string()
+    };
+    let versus_mode = *mode == GameMode::Versus;
+    commands
+        .spawn(screen_root(OnGameOverScreen))
+        .with_children(|parent| {
+            if versus_mode {
+                parent.spawn(menu_text(round_headline(&versus), 48.0));
+                parent.spawn(menu_text(
+                    format!("Rounds : P1 {} - {} P2", versus.wins[0], versus.wins[1]),
+                    32.0,
+                ));
+            } else {
+                parent.spawn(menu_text(headline(*mode, &attack), 56.0));
+                parent.spawn(menu_text(format!("Score : {}", score.0), 32.0));
+                if *mode == GameMode::Daily {
+                    parent.spawn(menu_text(
+                        format!("Modifiers : {}", modifiers.names()),
+                        24.0,
+                    ));
+                    parent.spawn(menu_text(daily_status(&attempt, &high_scores), 20.0));
+                }
+                // where the run stood after each wave, or every half minute
+                for (index, split) in attack.splits.iter().enumerate() {
+                    let time = format_ticks(split.ticks);
+                    let line = if mode.ranks_by_time() {
+                        format!("Wave {}  {time}  {} pts", index + 1, split.score)
+                    } else {
+                        format!("{time}  {} pts", split.score)
+                    };
+                    parent.spawn(menu_text(line, 18.0));
+                }
+            }
+            if players.0.len() > 1 {
+                let shares: Vec<String> = players
+                    .0
+                    .iter()
+                    .enumerate()
+                    .map(|(player, stats)| format!("P{} {}", player + 1, stats.score))
+                    .collect();
+                parent.spawn(menu_text(shares.join("   "), 24.0));
+            }
+            if mode.has_high_scores() {
+                parent.spawn(menu_text(
+                    format!("{} {} best : {best}", mode.name(), difficulty.name()),
+                    20.0,
+                ));
+            }
+            if let Some(last_replay) = last_replay {
+                parent.spawn(menu_text(
+                    format!("Seed : {}", last_replay.replay.seed),
+                    24.0,
+                ));
+                let seconds = last_replay.replay.ticks as f64 * FIXED_TICK_SECONDS;
+                parent.spawn(menu_text(format!("Time : {seconds:.0}s"), 24.0));
+            }
+            if pending.is_some() {
+                parent.spawn(menu_text(
+                    "New high score! Type your name, Enter to save",
+                    20.0,
+                ));
+                parent.spawn((menu_text("", 32.0), NameEntryText));
+            }
+            let retry = match (versus_mode, versus.winner()) {
+                (false, _) => "Retry seed",
+                (true, None) => "Next round",
+                (true, Some(_)) => "Rematch",
+            };
+            parent
+                .spawn(menu_button(GameOverAction::Retry))
+                .with_child(menu_text(retry, 28.0));
+            parent
+                .spawn(menu_button(GameOverAction::MainMenu))
+                .with_child(menu_text("Main Menu", 28.0));
+        });
+}
+
+fn headline(mode: GameMode, attack: &AttackRun) -> String {
+    match (mode.ranks_by_time(), attack.finished) {
+        (true, Some(ticks)) => format!("Cleared in {}", format_ticks(ticks)),
+        (true, None) => format!(
+            "Shot down on wave {}/{}",
+            attack.wave,
+            TIME_ATTACK_WAVES.len()
+        ),
+        (false, Some(_)) => "Time Up".to_string(),
+        (false, None) => "Game Over".to_string(),
+    }
+}
+
+fn daily_status(attempt: &DailyAttempt, high_scores: &HighScores) -> String {
+    if let Some(result) = attempt.0.as_ref().and_then(|date| high_scores.daily(date)) {
+        return format!(
+            "Daily challenge {} on {}, saved",
+            result.date,
+            result.played_on()
+        );
+    }
+    match high_scores.daily(&today()) {
+        Some(result) => format!(
+            "Practice run, today's score : {} ({})",
+            result.score,
+            result.played_on()
+        ),
+        None => "Practice run".to_string(),
+    }
+}
+
+fn round_headline(versus: &VersusMatch) -> String {
+    match versus.result {
+        Some(RoundResult::Won(player)) if versus.winner() == Some(player) => {
+            format!("P{} wins the match", player + 1)
+        }
+        Some(RoundResult::Won(player)) => format!("P{} takes round {}", player + 1, versus.round),
+        Some(RoundResult::Draw) => format!("Round {} is a draw", versus.round),
+        None => "Round abandoned".to_string(),
+    }
+}
+
+// Retrying replays the exact same seed, Escape goes back to the menu. The
+// keys are left to the name entry while a high score is pending. A versus
+// round is followed by one on a fresh seed.
+fn game_over_action(
+    mut commands: Commands,
+    interaction_query: ButtonActions<GameOverAction>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    rng: Res<GameRng>,
+    mode: Res<GameMode>,
+    pending: Option<Res<PendingHighScore>>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    let mut action = interaction_query
+        .iter()
+        .find(|(interaction, _)| **interaction == Interaction::Pressed)
+        .map(|(_, action)| action);
+    if pending.is_none() {
+        if keyboard_input.just_pressed(KeyCode::Enter) {
+            action = Some(&GameOverAction::Retry);
+        } else if keyboard_input.just_pressed(KeyCode::Escape) {
+            action = Some(&GameOverAction::MainMenu);
+        }
+    }
+    match action {
+        Some(GameOverAction::Retry) => {
+            let seed = (*mode != GameMode::Versus).then(|| rng.seed());
+            start_run(&mut commands, seed, &mut game_state)
+        }
+        Some(GameOverAction::MainMenu) => game_state.set(GameState::Menu),
+        None => {}
+    }
+}
+
+fn enter_name(
+    mut commands: Commands,
+    mut keyboard_events: EventReader<KeyboardInput>,
+    pending: Option<ResMut<PendingHighScore>>,
+    mut high_scores: ResMut<HighScores>,
+    mode: Res<GameMode>,
+    difficulty: Res<Difficulty>,
+) {
+    let Some(mut pending) = pending else {
+        keyboard_events.clear();
+        return;
+    };
+    for event in keyboard_events.read() {
+        if !event.state.is_pressed() {
+            continue;
+        }
+        let name = &mut pending.0.name;
+        match &event.logical_key {
+            Key::Character(text) => {
+                for c in text.chars() {
+                    if (c.is_alphanumeric() || c == ' ')
+                        && name.chars().count() < HIGH_SCORE_NAME_LENGTH
+                    {
+                        name.push(c);
+                    }
+                }
+            }
+            Key::Space if name.chars().count() < HIGH_SCORE_NAME_LENGTH => name.push(' '),
+            Key::Backspace => {
+                name.pop();
+            }
+            Key::Enter => {
+                save_high_score(
+                    &mut commands,
+                    &mut high_scores,
+                    *mode,
+                    *difficulty,
+                    pending.0.clone(),
+                );
+                commands.remove_resource::<PendingHighScore>();
+                return;
+            }
+            _ => {}
+        }
+    }
+}
+
+fn update_name_text(
+    pending: Option<Res<PendingHighScore>>,
+    mut query: Query<&mut Text, With<NameEntryText>>,
+) {
+    for mut text in &mut query {
+        text.0 = match &pending {
+            Some(pending) => format!("{}_", pending.0.name),
+            None => "saved".to_string(),
+        };
+    }
+}
+
+// Leaving the screen without pressing Enter still keeps the score.
+fn save_pending_high_score(
+    mut commands: Commands,
+    pending: Option<Res<PendingHighScore>>,
+    mut high_scores: ResMut<HighScores>,
+    mode: Res<GameMode>,
+    difficulty: Res<Difficulty>,
+) {
+    if let Some(pending) = pending {
+        save_high_score(
+            &mut commands,
+            &mut high_scores,
+            *mode,
+            *difficulty,
+            pending.0.clone(),
+        );
+        commands.remove_resource::<PendingHighScore>();
+    }
+}
+
+fn save_high_score(
+    commands: &mut Commands,
+    high_scores: &mut HighScores,
+    mode: GameMode,
+    difficulty: Difficulty,
+    mut entry: HighScoreEntry,
+) {
+    entry.name = entry.name.trim().to_string();
+    if entry.name.is_empty() {
+        entry.name = "PLAYER".to_string();
+    }
+    if let Some(rank) = high_scores.insert(mode, difficulty, entry) {
+        println!("new high score, rank {rank}");
+    }
+    if let Err(error) = high_scores.save() {
+        println!("{error}");
+        commands.send_event(ErrorToast(error.to_string()));
+    }
+}
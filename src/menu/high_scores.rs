@@ -0,0 +1,162 @@
+use bevy::prelude::*;
+
+use super::{despawn_screen, menu_button, menu_text, screen_root, ButtonActions, GameState};
+use crate::{
+    constants::HUD_FONT,
+    game::{attack_mod::format_ticks, highscore_mod::HighScores, Difficulty, GameMode},
+};
+
+// Tag component used to tag entities added on the high scores screen
+#[derive(Component)]
+struct OnHighScoresScreen;
+
+#[derive(Component)]
+struct HighScoreRows;
+
+#[derive(Component)]
+enum HighScoresAction {
+    NextMode,
+    NextDifficulty,
+    Back,
+}
+
+// Which table is on screen, starting on the one the player last picked.
+#[derive(Resource)]
+struct ShownTable {
+    mode: GameMode,
+    difficulty: Difficulty,
+}
+
+pub fn high_scores_plugin(app: &mut App) {
+    app.add_systems(OnEnter(GameState::HighScores), setup_high_scores)
+        .add_systems(
+            Update,
+            (high_scores_action, fill_rows)
+                .chain()
+                .run_if(in_state(GameState::HighScores)),
+        )
+        .add_systems(
+            OnExit(GameState::HighScores),
+            despawn_screen::<OnHighScoresScreen>,
+        );
+}
+
+fn setup_high_scores(mut commands: Commands, mode: Res<GameMode>, difficulty: Res<Difficulty>) {
+    commands.insert_resource(ShownTable {
+        // versus has no table of its own
+        mode: if mode.has_high_scores() {
+            *mode
+        } else {
+            GameMode::Campaign
+        },
+        difficulty: *difficulty,
+    });
+    commands
+        .spawn(screen_root(OnHighScoresScreen))
+        .with_children(|parent| {
+            parent.spawn(menu_text("High Scores", 48.0));
+            parent.spawn((
+                Node {
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Start,
+                    row_gap: Val::Px(4.0),
+                    ..default()
+                },
+                HighScoreRows,
+            ));
+            parent
+                .spawn(menu_button(HighScoresAction::NextMode))
+                .with_child(menu_text("Mode", 24.0));
+            parent
+                .spawn(menu_button(HighScoresAction::NextDifficulty))
+                .with_child(menu_text("Difficulty", 24.0));
+            parent
+                .spawn(menu_button(HighScoresAction::Back))
+                .with_child(menu_text("Back", 28.0));
+        });
+}
+
+fn high_scores_action(
+    interaction_query: ButtonActions<HighScoresAction>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut shown: ResMut<ShownTable>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    let mut action = interaction_query
+        .iter()
+        .find(|(interaction, _)| **interaction == Interaction::Pressed)
+        .map(|(_, action)| action);
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        action = Some(&HighScoresAction::Back);
+    } else if keyboard_input.just_pressed(KeyCode::ArrowRight) {
+        action = Some(&HighScoresAction::NextDifficulty);
+    } else if keyboard_input.just_pressed(KeyCode::ArrowDown) {
+        action = Some(&HighScoresAction::NextMode);
+    }
+    match action {
+        Some(HighScoresAction::NextMode) => {
+            shown.mode = shown.mode.next();
+            while !shown.mode.has_high_scores() {
+                shown.mode = shown.mode.next();
+            }
+        }
+        Some(HighScoresAction::NextDifficulty) => shown.difficulty = shown.difficulty.next(),
+        Some(HighScoresAction::Back) => game_state.set(GameState::Menu),
+        None => {}
+    }
+}
+
+// Rebuilds the rows whenever another table is picked.
+fn fill_rows(
+    mut commands: Commands,
+    shown: Res<ShownTable>,
+    high_scores: Res<HighScores>,
+    asset_server: Res<AssetServer>,
+    rows: Query<Entity, With<HighScoreRows>>,
+) {
+    if !shown.is_changed() {
+        return;
+    }
+    let font = asset_server.load(HUD_FONT);
+    let entries = high_scores.entries(shown.mode, shown.difficulty);
+    for rows in &rows {
+        commands
+            .entity(rows)
+            .despawn_descendants()
+            .with_children(|parent| {
+                parent.spawn(menu_text(
+                    format!("{} - {}", shown.mode.name(), shown.difficulty.name()),
+                    24.0,
+                ));
+                if entries.is_empty() {
+                    parent.spawn(menu_text("no scores yet", 18.0));
+                }
+                for (rank, entry) in entries.iter().enumerate() {
+                    // time attack tables rank by the clear time
+                    let result = match entry.ticks {
+                        Some(ticks) if shown.mode.ranks_by_time() => format_ticks(ticks),
+                        _ => entry.score.to_string(),
+                    };
+                    parent.spawn((
+                        menu_text(
+                            format!(
+                                "{:>2}. {:<12} {:>8}  stage {}  {}  seed {}",
+                                rank + 1,
+                                entry.name,
+                                result,
+                                entry.stage,
+                                entry.date,
+                                entry.seed
+                            ),
+                            16.0,
+                        ),
+                        TextFont {
+                            font: font.clone(),
+                            font_size: 16.0,
+                            ..default()
+                        },
+                    ));
+                }
+            });
+    }
+}
@@ -0,0 +1,322 @@
+use std::fmt;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext, LoadState, UntypedHandle},
+    prelude::*,
+};
+use serde::Deserialize;
+
+use super::{despawn_screen, menu_text, screen_root, GameState};
+use crate::{
+    constants::ASSET_MANIFEST,
+    game::{level_mod::Level, tuning_mod::GameTuning},
+};
+
+// Tag component used to tag entities added on the loading screen
+#[derive(Component)]
+struct OnLoadingScreen;
+
+#[derive(Component)]
+struct ProgressFill;
+
+#[derive(Component)]
+struct ProgressText;
+
+#[derive(Component)]
+struct FailedList;
+
+// The assets preloaded before the menu, grouped by type so each can be
+// loaded with the right loader.
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct AssetManifest {
+    #[serde(default)]
+    images: Vec<String>,
+    #[serde(default)]
+    sounds: Vec<String>,
+    #[serde(default)]
+    fonts: Vec<String>,
+    #[serde(default)]
+    levels: Vec<String>,
+    #[serde(default)]
+    tuning: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum ManifestLoaderError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+}
+
+impl fmt::Display for ManifestLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ManifestLoaderError::Io(error) => write!(f, "could not read asset manifest: {error}"),
+            ManifestLoaderError::Ron(error) => {
+                write!(f, "could not parse asset manifest: {error}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ManifestLoaderError {}
+
+impl From<std::io::Error> for ManifestLoaderError {
+    fn from(error: std::io::Error) -> Self {
+        ManifestLoaderError::Io(error)
+    }
+}
+
+impl From<ron::error::SpannedError> for ManifestLoaderError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        ManifestLoaderError::Ron(error)
+    }
+}
+
+#[derive(Default)]
+struct ManifestLoader;
+
+impl AssetLoader for ManifestLoader {
+    type Asset = AssetManifest;
+    type Settings = ();
+    type Error = ManifestLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<AssetManifest, ManifestLoaderError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes::<AssetManifest>(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["manifest.ron"]
+    }
+}
+
+// Handles to everything in the manifest. Kept for the whole session so the
+// assets stay in memory and gameplay never waits on them.
+#[derive(Resource)]
+struct PreloadedAssets {
+    manifest: Handle<AssetManifest>,
+    handles: Vec<(String, UntypedHandle)>,
+    requested: bool,
+    failed: Vec<String>,
+}
+
+pub fn loading_plugin(app: &mut App) {
+    app.init_asset::<AssetManifest>()
+        .init_asset_loader::<ManifestLoader>()
+        .add_systems(OnEnter(GameState::Loading), setup_loading)
+        .add_systems(
+            Update,
+            (request_assets, track_loading, list_failed_assets)
+                .chain()
+                .run_if(in_state(GameState::Loading)),
+        )
+        .add_systems(
+            OnExit(GameState::Loading),
+            despawn_screen::<OnLoadingScreen>,
+        );
+}
+
+fn setup_loading(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(PreloadedAssets {
+        manifest: asset_server.load(ASSET_MANIFEST),
+        handles: Vec::new(),
+        requested: false,
+        failed: Vec::new(),
+    });
+    commands
+        .spawn(screen_root(OnLoadingScreen))
+        .with_children(|parent| {
+            parent.spawn(menu_text("Loading", 48.0));
+            parent
+                .spawn((
+                    Node {
+                        width: Val::Px(320.0),
+                        height: Val::Px(16.0),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgb(0.15, 0.15, 0.15)),
+                ))
+                .with_child((
+                    ProgressFill,
+                    Node {
+                        width: Val::Percent(0.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgb(0.35, 0.75, 0.35)),
+                ));
+            parent.spawn((menu_text("", 20.0), ProgressText));
+            parent.spawn((
+                Node {
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(4.0),
+                    ..default()
+                },
+                FailedList,
+            ));
+        });
+}
+
+// Starts loading the listed assets once the manifest itself is in.
+fn request_assets(
+    asset_server: Res<AssetServer>,
+    manifests: Res<Assets<AssetManifest>>,
+    mut preloaded: ResMut<PreloadedAssets>,
+) {
+    if preloaded.requested {
+        return;
+    }
+    let Some(manifest) = manifests.get(&preloaded.manifest) else {
+        return;
+    };
+    let mut handles = Vec::new();
+    for path in &manifest.images {
+        handles.push((path.clone(), asset_server.load::<Image>(path).untyped()));
+    }
+    for path in &manifest.sounds {
+        handles.push((
+            path.clone(),
+            asset_server.load::<AudioSource>(path).untyped(),
+        ));
+    }
+    for path in &manifest.fonts {
+        handles.push((path.clone(), asset_server.load::<Font>(path).untyped()));
+    }
+    for path in &manifest.levels {
+        handles.push((path.clone(), asset_server.load::<Level>(path).untyped()));
+    }
+    for path in &manifest.tuning {
+        handles.push((
+            path.clone(),
+            asset_server.load::<GameTuning>(path).untyped(),
+        ));
+    }
+    preloaded.handles = handles;
+    preloaded.requested = true;
+}
+
+// Moves on to the menu once everything has loaded. Anything that failed is
+// listed with its path, and Enter carries on without it.
+fn track_loading(
+    asset_server: Res<AssetServer>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut preloaded: ResMut<PreloadedAssets>,
+    mut fill: Query<&mut Node, With<ProgressFill>>,
+    mut progress_text: Query<&mut Text, With<ProgressText>>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    let mut failed = Vec::new();
+    if let Some(LoadState::Failed(error)) = asset_server.get_load_state(&preloaded.manifest) {
+        failed.push(format!("{ASSET_MANIFEST}: {error}"));
+    }
+    let mut loaded = 0;
+    for (path, handle) in &preloaded.handles {
+        match asset_server.get_load_state(handle.id()) {
+            Some(LoadState::Loaded) => loaded += 1,
+            Some(LoadState::Failed(error)) => failed.push(format!("{path}: {error}")),
+            _ => {}
+        }
+    }
+    let total = preloaded.handles.len();
+    let settled = !failed.is_empty() && (!preloaded.requested || loaded + failed.len() == total);
+
+    for mut node in &mut fill {
+        node.width = Val::Percent(if total == 0 {
+            0.0
+        } else {
+            loaded as f32 / total as f32 * 100.0
+        });
+    }
+    for mut text in &mut progress_text {
+        text.0 = if settled {
+            format!(
+                "{} of {} assets could not be loaded, press Enter to continue anyway",
+                failed.len(),
+                total.max(failed.len())
+            )
+        } else {
+            format!("{loaded} / {total}")
+        };
+    }
+
+    if failed != preloaded.failed {
+        for line in &failed {
+            println!("failed to load {line}");
+        }
+        preloaded.failed = failed;
+    }
+
+    let ready = preloaded.requested && loaded == total;
+    if ready || (settled && keyboard_input.just_pressed(KeyCode::Enter)) {
+        game_state.set(GameState::Menu);
+    }
+}
+
+fn list_failed_assets(
+    mut commands: Commands,
+    preloaded: Res<PreloadedAssets>,
+    failed_list: Query<Entity, With<FailedList>>,
+) {
+    if !preloaded.is_changed() {
+        return;
+    }
+    for list in &failed_list {
+        commands
+            .entity(list)
+            .despawn_descendants()
+            .with_children(|parent| {
+                for line in &preloaded.failed {
+                    parent.spawn((
+                        menu_text(line.clone(), 14.0),
+                        TextColor(Color::srgb(1.0, 0.4, 0.4)),
+                    ));
+                }
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::constants::{
+        ASSET_MANIFEST, ENEMY_SPACE_SPRITE_NAME, HUD_FONT, JET_SPRITE_NAMES, LEVEL_FILES,
+        TUNING_FILE,
+    };
+
+    use super::AssetManifest;
+
+    #[test]
+    fn manifest_lists_files_that_exist_and_covers_the_game_assets() {
+        let assets = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+        let manifest: AssetManifest =
+            ron::de::from_bytes(&std::fs::read(assets.join(ASSET_MANIFEST)).unwrap()).unwrap();
+        let paths: Vec<&String> = manifest
+            .images
+            .iter()
+            .chain(&manifest.sounds)
+            .chain(&manifest.fonts)
+            .chain(&manifest.levels)
+            .chain(&manifest.tuning)
+            .collect();
+        for path in &paths {
+            assert!(assets.join(path).is_file(), "{path} is missing");
+        }
+        let required = [ENEMY_SPACE_SPRITE_NAME, HUD_FONT, TUNING_FILE];
+        for path in required
+            .iter()
+            .chain(&JET_SPRITE_NAMES)
+            .chain(LEVEL_FILES.iter())
+        {
+            assert!(
+                paths.iter().any(|listed| listed == path),
+                "{path} not listed"
+            );
+        }
+    }
+}
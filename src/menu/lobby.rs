@@ -0,0 +1,230 @@
+use bevy::{
+    input::keyboard::{Key, KeyboardInput},
+    prelude::*,
+};
+
+use super::{
+    despawn_screen, menu_button, menu_text, screen_root, start_online_run, ButtonActions, GameState,
+};
+use crate::{
+    constants::{MAX_ADDRESS_LENGTH, NET_DEFAULT_PORT},
+    game::{
+        net_mod::{NetEndpoint, NetLink, NetRun, NetShim},
+        playfield_mod::Playfield,
+        rng_mod::{random_seed, SeedSetting},
+        tuning_mod::GameTuning,
+        Difficulty, GameMode,
+    },
+};
+
+// Tag component used to tag entities added on the lobby screen
+#[derive(Component)]
+struct OnLobbyScreen;
+
+#[derive(Component)]
+struct AddressText;
+
+#[derive(Component)]
+struct LobbyStatusText;
+
+#[derive(Component)]
+enum LobbyButtonAction {
+    Host,
+    Join,
+    Back,
+}
+
+// The host to join, typed in on the lobby screen.
+#[derive(Resource)]
+struct JoinAddress(String);
+
+impl Default for JoinAddress {
+    fn default() -> Self {
+        JoinAddress(format!("127.0.0.1:{NET_DEFAULT_PORT}"))
+    }
+}
+
+#[derive(Resource, Default)]
+struct LobbyStatus(String);
+
+pub fn lobby_plugin(app: &mut App) {
+    app.init_resource::<JoinAddress>()
+        .init_resource::<LobbyStatus>()
+        .add_systems(OnEnter(GameState::Lobby), setup_lobby)
+        .add_systems(
+            Update,
+            (
+                lobby_action,
+                enter_address,
+                wait_for_player,
+                update_lobby_text,
+            )
+                .chain()
+                .run_if(in_state(GameState::Lobby)),
+        )
+        .add_systems(
+            OnExit(GameState::Lobby),
+            (despawn_screen::<OnLobbyScreen>, close_link),
+        );
+}
+
+fn address_label(address: &JoinAddress) -> String {
+    format!("Address : {}", address.0)
+}
+
+fn setup_lobby(mut commands: Commands, address: Res<JoinAddress>) {
+    commands.insert_resource(LobbyStatus::default());
+    commands
+        .spawn(screen_root(OnLobbyScreen))
+        .with_children(|parent| {
+            parent.spawn(menu_text("Online", 48.0));
+            parent
+                .spawn(menu_button(LobbyButtonAction::Host))
+                .with_child(menu_text("Host", 28.0));
+            parent
+                .spawn(menu_button(LobbyButtonAction::Join))
+                .with_child(menu_text("Join", 28.0));
+            parent.spawn((menu_text(address_label(&address), 24.0), AddressText));
+            parent.spawn(menu_text(
+                "type the host's address, backspace to delete",
+                16.0,
+            ));
+            parent.spawn((menu_text("", 20.0), LobbyStatusText));
+            parent
+                .spawn(menu_button(LobbyButtonAction::Back))
+                .with_child(menu_text("Back", 28.0));
+        });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn lobby_action(
+    mut commands: Commands,
+    interaction_query: ButtonActions<LobbyButtonAction>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    address: Res<JoinAddress>,
+    shim: Res<NetShim>,
+    link: Option<Res<NetLink>>,
+    mut status: ResMut<LobbyStatus>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        game_state.set(GameState::Menu);
+        return;
+    }
+    for (interaction, action) in &interaction_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let endpoint = match action {
+            LobbyButtonAction::Host if link.as_ref().is_some_and(|link| link.is_host()) => continue,
+            LobbyButtonAction::Host => NetEndpoint::Host(NET_DEFAULT_PORT),
+            LobbyButtonAction::Join => NetEndpoint::Join(address.0.clone()),
+            LobbyButtonAction::Back => {
+                game_state.set(GameState::Menu);
+                continue;
+            }
+        };
+        // a new link replaces the old one, which closes its socket
+        match NetLink::open(&endpoint, *shim) {
+            Ok(link) => {
+                status.0 = match &endpoint {
+                    NetEndpoint::Host(port) => format!("waiting for a player on port {port}"),
+                    NetEndpoint::Join(address) => format!("joining {address}"),
+                };
+                commands.insert_resource(link);
+            }
+            Err(error) => {
+                println!("{error}");
+                status.0 = error.to_string();
+                commands.remove_resource::<NetLink>();
+            }
+        }
+    }
+}
+
+fn enter_address(
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mut address: ResMut<JoinAddress>,
+) {
+    for event in keyboard_events.read() {
+        if !event.state.is_pressed() {
+            continue;
+        }
+        match &event.logical_key {
+            Key::Character(text) => {
+                for c in text.chars().filter(|c| !c.is_control()) {
+                    if address.0.len() < MAX_ADDRESS_LENGTH {
+                        address.0.push(c);
+                    }
+                }
+            }
+            Key::Backspace => {
+                address.0.pop();
+            }
+            _ => {}
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn wait_for_player(
+    mut commands: Commands,
+    link: Option<ResMut<NetLink>>,
+    seed: Res<SeedSetting>,
+    difficulty: Res<Difficulty>,
+    mode: Res<GameMode>,
+    playfield: Res<Playfield>,
+    tuning: Res<GameTuning>,
+    mut offer: Local<Option<NetRun>>,
+    mut status: ResMut<LobbyStatus>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    let Some(mut link) = link else {
+        *offer = None;
+        return;
+    };
+    // the host keeps offering the same run until someone takes it
+    let offer = offer.get_or_insert_with(|| NetRun {
+        seed: seed.0.unwrap_or_else(random_seed),
+        mode: *mode,
+        difficulty: *difficulty,
+        playfield: (playfield.width, playfield.height),
+        tuning: tuning.checksum(),
+    });
+    match link.connect(offer) {
+        Ok(Some(run)) => start_online_run(&mut commands, run, &mut game_state),
+        Ok(None) => {}
+        Err(error) => {
+            println!("{error}");
+            status.0 = error.to_string();
+            // the host keeps waiting for someone else
+            if !link.is_host() {
+                commands.remove_resource::<NetLink>();
+            }
+        }
+    }
+}
+
+fn update_lobby_text(
+    address: Res<JoinAddress>,
+    status: Res<LobbyStatus>,
+    mut address_query: Query<&mut Text, (With<AddressText>, Without<LobbyStatusText>)>,
+    mut status_query: Query<&mut Text, With<LobbyStatusText>>,
+) {
+    if address.is_changed() {
+        for mut text in &mut address_query {
+            text.0 = address_label(&address);
+        }
+    }
+    if status.is_changed() {
+        for mut text in &mut status_query {
+            text.0 = status.0.clone();
+        }
+    }
+}
+
+// Leaving the lobby without a run closes the socket. Starting one has already
+// moved the link into the session.
+fn close_link(mut commands: Commands) {
+    commands.remove_resource::<NetLink>();
+}
@@ -0,0 +1,319 @@
+use bevy::{
+    app::AppExit,
+    input::keyboard::{Key, KeyboardInput},
+    prelude::*,
+};
+
+use super::{
+    despawn_screen, menu_button, menu_text, screen_root, start_run, ButtonActions, GameState,
+};
+use crate::{
+    constants::MAX_PLAYERS,
+    game::{
+        camera_effects_mod::CameraEffectsSettings,
+        daily_mod::daily_seed,
+        highscore_mod::{today, HighScores},
+        rng_mod::SeedSetting,
+        Difficulty, GameMode, PlayerCount,
+    },
+};
+
+// Tag component used to tag entities added on the main menu screen
+#[derive(Component)]
+struct OnMainMenuScreen;
+
+#[derive(Component)]
+struct SeedText;
+
+#[derive(Component)]
+struct ModeText;
+
+#[derive(Component)]
+struct DifficultyText;
+
+#[derive(Component)]
+struct PlayersText;
+
+#[derive(Component)]
+struct EffectsText;
+
+#[derive(Component)]
+struct FlashesText;
+
+#[derive(Component)]
+enum MenuButtonAction {
+    Play,
+    Daily,
+    Mode,
+    Difficulty,
+    Players,
+    Effects,
+    Flashes,
+    Online,
+    HighScores,
+    Replays,
+    Quit,
+}
+
+// any 19 digit number fits in a u64
+const MAX_SEED_DIGITS: usize = 19;
+
+pub fn main_menu_plugin(app: &mut App) {
+    app.add_systems(OnEnter(GameState::Menu), setup_main_menu)
+        .add_systems(
+            Update,
+            (
+                menu_action,
+                enter_seed,
+                update_seed_text,
+                update_mode_text,
+                update_difficulty_text,
+                update_players_text,
+                update_effects_text,
+            )
+                .chain()
+                .run_if(in_state(GameState::Menu)),
+        )
+        .add_systems(OnExit(GameState::Menu), despawn_screen::<OnMainMenuScreen>);
+}
+
+fn seed_label(seed: &SeedSetting) -> String {
+    match seed.0 {
+        Some(seed) => format!("Seed : {seed}"),
+        None => "Seed : random".to_string(),
+    }
+}
+
+fn mode_label(mode: GameMode) -> String {
+    format!("Mode : {}", mode.name())
+}
+
+fn daily_label(high_scores: &HighScores) -> String {
+    match high_scores.daily(&today()) {
+        Some(result) => format!("Daily : {} pts ({})", result.score, result.played_on()),
+        None => "Daily Challenge".to_string(),
+    }
+}
+
+fn difficulty_label(difficulty: Difficulty) -> String {
+    format!("Difficulty : {}", difficulty.name())
+}
+
+fn players_label(players: PlayerCount, mode: GameMode) -> String {
+    match (players.0, mode) {
+        (count, GameMode::Versus) => format!("Players : {count} versus"),
+        (1, _) => "Players : 1".to_string(),
+        (count, _) => format!("Players : {count} co-op"),
+    }
+}
+
+fn effects_label(effects: &CameraEffectsSettings) -> String {
+    format!("Effects : {}", effects.intensity_name())
+}
+
+fn flashes_label(effects: &CameraEffectsSettings) -> String {
+    let state = if effects.flashes { "On" } else { "Off" };
+    format!("Flashes : {state}")
+}
+
+fn setup_main_menu(
+    mut commands: Commands,
+    seed: Res<SeedSetting>,
+    difficulty: Res<Difficulty>,
+    players: Res<PlayerCount>,
+    mut mode: ResMut<GameMode>,
+    high_scores: Res<HighScores>,
+    effects: Res<CameraEffectsSettings>,
+) {
+    // a daily challenge only starts from its own button
+    if *mode == GameMode::Daily {
+        *mode = GameMode::Campaign;
+    }
+    commands
+        .spawn(screen_root(OnMainMenuScreen))
+        .with_children(|parent| {
+            parent.spawn(menu_text("Space Fight", 56.0));
+            parent
+                .spawn(menu_button(MenuButtonAction::Play))
+                .with_child(menu_text("New Game", 28.0));
+            parent
+                .spawn(menu_button(MenuButtonAction::Daily))
+                .with_child(menu_text(daily_label(&high_scores), 24.0));
+            parent
+                .spawn(menu_button(MenuButtonAction::Mode))
+                .with_child((menu_text(mode_label(*mode), 24.0), ModeText));
+            parent
+                .spawn(menu_button(MenuButtonAction::Difficulty))
+                .with_child((
+                    menu_text(difficulty_label(*difficulty), 24.0),
+                    DifficultyText,
+                ));
+            parent
+                .spawn(menu_button(MenuButtonAction::Players))
+                .with_child((menu_text(players_label(*players, *mode), 24.0), PlayersText));
+            // screen shake, hit stop and flashes, side by side to save room
+            parent
+                .spawn(Node {
+                    column_gap: Val::Px(16.0),
+                    ..default()
+                })
+                .with_children(|row| {
+                    row.spawn(menu_button(MenuButtonAction::Effects))
+                        .with_child((menu_text(effects_label(&effects), 24.0), EffectsText));
+                    row.spawn(menu_button(MenuButtonAction::Flashes))
+                        .with_child((menu_text(flashes_label(&effects), 24.0), FlashesText));
+                });
+            parent
+                .spawn(menu_button(MenuButtonAction::Online))
+                .with_child(menu_text("Online", 28.0));
+            parent
+                .spawn(menu_button(MenuButtonAction::HighScores))
+                .with_child(menu_text("High Scores", 28.0));
+            parent
+                .spawn(menu_button(MenuButtonAction::Replays))
+                .with_child(menu_text("Replays", 28.0));
+            parent
+                .spawn(menu_button(MenuButtonAction::Quit))
+                .with_child(menu_text("Quit", 28.0));
+            parent.spawn((menu_text(seed_label(&seed), 24.0), SeedText));
+            parent.spawn(menu_text(
+                "type digits to fix the seed, backspace to clear",
+                16.0,
+            ));
+            parent.spawn(menu_text(
+                "P1 WASD, P2 arrow keys, gamepads work for either",
+                16.0,
+            ));
+        });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn menu_action(
+    mut commands: Commands,
+    interaction_query: ButtonActions<MenuButtonAction>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    seed: Res<SeedSetting>,
+    mut game_state: ResMut<NextState<GameState>>,
+    mut difficulty: ResMut<Difficulty>,
+    mut players: ResMut<PlayerCount>,
+    mut mode: ResMut<GameMode>,
+    mut effects: ResMut<CameraEffectsSettings>,
+    mut app_exit_events: EventWriter<AppExit>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Enter) {
+        start_run(&mut commands, seed.0, &mut game_state);
+        return;
+    }
+    for (interaction, action) in &interaction_query {
+        if *interaction == Interaction::Pressed {
+            match action {
+                MenuButtonAction::Play => start_run(&mut commands, seed.0, &mut game_state),
+                // every attempt after the day's first is practice
+                MenuButtonAction::Daily => {
+                    *mode = GameMode::Daily;
+                    start_run(&mut commands, Some(daily_seed(&today())), &mut game_state);
+                }
+                MenuButtonAction::Mode => {
+                    *mode = mode.next();
+                    // versus always takes both players
+                    if *mode == GameMode::Versus {
+                        *players = PlayerCount(MAX_PLAYERS);
+                    }
+                }
+                MenuButtonAction::Difficulty => *difficulty = difficulty.next(),
+                MenuButtonAction::Players if *mode == GameMode::Versus => {}
+                MenuButtonAction::Players => *players = players.next(),
+                MenuButtonAction::Effects => effects.next_intensity(),
+                MenuButtonAction::Flashes => effects.flashes = !effects.flashes,
+                MenuButtonAction::Online => game_state.set(GameState::Lobby),
+                MenuButtonAction::HighScores => game_state.set(GameState::HighScores),
+                MenuButtonAction::Replays => game_state.set(GameState::Replays),
+                MenuButtonAction::Quit => {
+                    app_exit_events.send(AppExit::Success);
+                }
+            }
+        }
+    }
+}
+
+fn enter_seed(mut keyboard_events: EventReader<KeyboardInput>, mut seed: ResMut<SeedSetting>) {
+    for event in keyboard_events.read() {
+        if !event.state.is_pressed() {
+            continue;
+        }
+        match &event.logical_key {
+            Key::Character(text) => {
+                let Some(digit) = text.chars().next().and_then(|c| c.to_digit(10)) else {
+                    continue;
+                };
+                let current = seed.0.unwrap_or(0);
+                if seed.0.is_none() || current.to_string().len() < MAX_SEED_DIGITS {
+                    seed.0 = Some(current * 10 + digit as u64);
+                }
+            }
+            Key::Backspace => {
+                seed.0 = seed.0.map(|seed| seed / 10).filter(|seed| *seed != 0);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn update_seed_text(seed: Res<SeedSetting>, mut query: Query<&mut Text, With<SeedText>>) {
+    if !seed.is_changed() {
+        return;
+    }
+    for mut text in &mut query {
+        text.0 = seed_label(&seed);
+    }
+}
+
+fn update_mode_text(mode: Res<GameMode>, mut query: Query<&mut Text, With<ModeText>>) {
+    if !mode.is_changed() {
+        return;
+    }
+    for mut text in &mut query {
+        text.0 = mode_label(*mode);
+    }
+}
+
+fn update_difficulty_text(
+    difficulty: Res<Difficulty>,
+    mut query: Query<&mut Text, With<DifficultyText>>,
+) {
+    if !difficulty.is_changed() {
+        return;
+    }
+    for mut text in &mut query {
+        text.0 = difficulty_label(*difficulty);
+    }
+}
+
+fn update_players_text(
+    players: Res<PlayerCount>,
+    mode: Res<GameMode>,
+    mut query: Query<&mut Text, With<PlayersText>>,
+) {
+    if !players.is_changed() && !mode.is_changed() {
+        return;
+    }
+    for mut text in &mut query {
+        text.0 = players_label(*players, *mode);
+    }
+}
+
+fn update_effects_text(
+    effects: Res<CameraEffectsSettings>,
+    mut effects_query: Query<&mut Text, (With<EffectsText>, Without<FlashesText>)>,
+    mut flashes_query: Query<&mut Text, With<FlashesText>>,
+) {
+    if !effects.is_changed() {
+        return;
+    }
+    for mut text in &mut effects_query {
+        text.0 = effects_label(&effects);
+    }
+    for mut text in &mut flashes_query {
+        text.0 = flashes_label(&effects);
+    }
+}
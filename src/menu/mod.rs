@@ -1,14 +1,46 @@
+mod game_over;
+mod high_scores;
+mod loading;
+mod lobby;
+mod main_menu;
+mod replays;
 mod splash_screen;
 
-use super::{GameState, Volume};
+use super::{GameState, MusicVolume, Volume};
+use crate::game::{
+    net_mod::{insert_online_run, NetLink, NetRun},
+    replay_mod::{Replay, ReplayPlayback, RunSettings},
+    rng_mod::{random_seed, GameRng},
+};
 use bevy::prelude::*;
+use game_over::game_over_plugin;
+use high_scores::high_scores_plugin;
+use loading::loading_plugin;
+use lobby::lobby_plugin;
+use main_menu::main_menu_plugin;
+use replays::replays_plugin;
 use splash_screen::splash_plugin;
 //public setting resources
 
+const TEXT_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
+const NORMAL_BUTTON: Color = Color::srgb(0.15, 0.15, 0.15);
+const HOVERED_BUTTON: Color = Color::srgb(0.25, 0.25, 0.25);
+const PRESSED_BUTTON: Color = Color::srgb(0.35, 0.75, 0.35);
+
 pub fn menu_plugin(app: &mut App) {
     app.insert_resource(Volume(7))
+        .insert_resource(MusicVolume(5))
         .init_state::<GameState>()
-        .add_plugins(splash_plugin);
+        .add_plugins((
+            splash_plugin,
+            loading_plugin,
+            main_menu_plugin,
+            game_over_plugin,
+            replays_plugin,
+            high_scores_plugin,
+            lobby_plugin,
+        ))
+        .add_systems(Update, button_colors);
 }
 
 fn despawn_screen<T: Component>(to_despawn: Query<Entity, With<T>>, mut commands: Commands) {
@@ -16,3 +48,102 @@ fn despawn_screen<T: Component>(to_despawn: Query<Entity, With<T>>, mut commands
         commands.entity(entity).despawn_recursive();
     }
 }
+
+// Buttons whose interaction changed this frame, with the colour to update.
+type ButtonColors<'w, 's> = Query<
+    'w,
+    's,
+    (&'static Interaction, &'static mut BackgroundColor),
+    (Changed<Interaction>, With<Button>),
+>;
+
+fn button_colors(mut interaction_query: ButtonColors) {
+    for (interaction, mut background_color) in &mut interaction_query {
+        *background_color = match *interaction {
+            Interaction::Pressed => PRESSED_BUTTON.into(),
+            Interaction::Hovered => HOVERED_BUTTON.into(),
+            Interaction::None => NORMAL_BUTTON.into(),
+        };
+    }
+}
+
+// Buttons whose interaction changed this frame, with the action they trigger.
+type ButtonActions<'w, 's, A> =
+    Query<'w, 's, (&'static Interaction, &'static A), (Changed<Interaction>, With<Button>)>;
+
+// A full-screen column that the menu screens put their text and buttons in.
+fn screen_root(marker: impl Component) -> impl Bundle {
+    (
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            row_gap: Val::Px(16.0),
+            ..default()
+        },
+        marker,
+    )
+}
+
+fn menu_button(action: impl Component) -> impl Bundle {
+    (
+        Button,
+        Node {
+            width: Val::Px(260.0),
+            height: Val::Px(56.0),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        BackgroundColor(NORMAL_BUTTON),
+        action,
+    )
+}
+
+fn menu_text(text: impl Into<String>, font_size: f32) -> impl Bundle {
+    (
+        Text::new(text),
+        TextFont {
+            font_size,
+            ..default()
+        },
+        TextColor(TEXT_COLOR),
+    )
+}
+
+// Every run starts here so the seed it is played with is always known.
+fn start_run(commands: &mut Commands, seed: Option<u64>, game_state: &mut NextState<GameState>) {
+    let seed = seed.unwrap_or_else(random_seed);
+    println!("starting run with seed {seed}");
+    commands.insert_resource(GameRng::new(seed));
+    game_state.set(GameState::Game);
+}
+
+// Plays a recorded run back on the seed and settings it was recorded with.
+// The menu's own settings are put back once playback is over.
+fn start_replay(
+    commands: &mut Commands,
+    replay: Replay,
+    previous: RunSettings,
+    game_state: &mut NextState<GameState>,
+) {
+    println!("playing back replay with seed {}", replay.seed);
+    commands.insert_resource(GameRng::new(replay.seed));
+    replay.settings().insert(commands);
+    commands.insert_resource(ReplayPlayback::new(replay).restoring(previous));
+    game_state.set(GameState::Game);
+}
+
+// Starts the run both sides agreed on in the lobby, handing the lobby's link
+// over to the session.
+fn start_online_run(commands: &mut Commands, run: NetRun, game_state: &mut NextState<GameState>) {
+    println!("starting online run with seed {}", run.seed);
+    commands.queue(move |world: &mut World| {
+        if let Some(link) = world.remove_resource::<NetLink>() {
+            insert_online_run(world, link, run);
+        }
+    });
+    game_state.set(GameState::Game);
+}
@@ -0,0 +1,112 @@
+use bevy::prelude::*;
+
+use super::{
+    despawn_screen, menu_button, menu_text, screen_root, start_replay, ButtonActions, GameState,
+};
+use crate::{
+    constants::{FIXED_TICK_SECONDS, REPLAY_MENU_ENTRIES},
+    game::{
+        playfield_mod::Playfield,
+        replay_mod::{list_replays, Replay, RunSettings},
+        tuning_mod::GameTuning,
+        Difficulty, GameMode, PlayerCount,
+    },
+};
+
+// Tag component used to tag entities added on the replays screen
+#[derive(Component)]
+struct OnReplaysScreen;
+
+#[derive(Component)]
+enum ReplayAction {
+    Play(Replay),
+    Back,
+}
+
+pub fn replays_plugin(app: &mut App) {
+    app.add_systems(OnEnter(GameState::Replays), setup_replays)
+        .add_systems(Update, replay_action.run_if(in_state(GameState::Replays)))
+        .add_systems(
+            OnExit(GameState::Replays),
+            despawn_screen::<OnReplaysScreen>,
+        );
+}
+
+fn replay_label(replay: &Replay) -> String {
+    let seconds = replay.ticks as f64 * FIXED_TICK_SECONDS;
+    format!(
+        "seed {}  score {}  {:.0}s",
+        replay.seed, replay.score, seconds
+    )
+}
+
+fn setup_replays(mut commands: Commands, tuning: Res<GameTuning>) {
+    let paths = list_replays();
+    commands
+        .spawn(screen_root(OnReplaysScreen))
+        .with_children(|parent| {
+            parent.spawn(menu_text("Replays", 48.0));
+            if paths.is_empty() {
+                parent.spawn(menu_text("no replays recorded yet", 20.0));
+            }
+            for path in paths.iter().take(REPLAY_MENU_ENTRIES) {
+                match Replay::load(path)
+                    .and_then(|replay| replay.check_tuning(&tuning).map(|_| replay))
+                {
+                    Ok(replay) => {
+                        let label = replay_label(&replay);
+                        parent
+                            .spawn(menu_button(ReplayAction::Play(replay)))
+                            .insert(Node {
+                                width: Val::Px(420.0),
+                                height: Val::Px(40.0),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            })
+                            .with_child(menu_text(label, 20.0));
+                    }
+                    Err(error) => {
+                        let name = path.file_name().unwrap_or_default().to_string_lossy();
+                        parent.spawn(menu_text(format!("{name}: {error}"), 14.0));
+                    }
+                }
+            }
+            parent
+                .spawn(menu_button(ReplayAction::Back))
+                .with_child(menu_text("Back", 28.0));
+        });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn replay_action(
+    mut commands: Commands,
+    interaction_query: ButtonActions<ReplayAction>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    playfield: Res<Playfield>,
+    mode: Res<GameMode>,
+    difficulty: Res<Difficulty>,
+    players: Res<PlayerCount>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        game_state.set(GameState::Menu);
+        return;
+    }
+    for (interaction, action) in &interaction_query {
+        if *interaction == Interaction::Pressed {
+            match action {
+                ReplayAction::Play(replay) => {
+                    let previous = RunSettings {
+                        playfield: *playfield,
+                        mode: *mode,
+                        difficulty: *difficulty,
+                        players: *players,
+                    };
+                    start_replay(&mut commands, replay.clone(), previous, &mut game_state)
+                }
+                ReplayAction::Back => game_state.set(GameState::Menu),
+            }
+        }
+    }
+}
@@ -49,6 +49,6 @@ fn countdown(
     mut timer: ResMut<SplashTimer>,
 ) {
     if timer.tick(time.delta()).finished() {
-        game_state.set(GameState::Game);
+        game_state.set(GameState::Loading);
     }
 }
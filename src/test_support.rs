@@ -0,0 +1,140 @@
+use std::time::{Duration, Instant};
+
+use bevy::{
+    app::App,
+    asset::{AssetServer, LoadState},
+    ecs::query::QueryFilter,
+    input::ButtonInput,
+    prelude::{Entity, KeyCode},
+    sprite::Sprite,
+};
+
+use crate::{
+    game::{
+        player_jet_mod::Players, playfield_mod::Playfield, rng_mod::GameRng, GameMode, PlayerCount,
+    },
+    headless::headless_app,
+};
+
+// Tests run on a fixed seed so a failure can be reproduced.
+pub const TEST_SEED: u64 = 1234;
+
+// The run a test starts on, one jet in the campaign on `TEST_SEED` unless
+// told otherwise.
+pub struct TestApp {
+    seed: u64,
+    players: usize,
+    mode: GameMode,
+}
+
+impl Default for TestApp {
+    fn default() -> Self {
+        TestApp {
+            seed: TEST_SEED,
+            players: 1,
+            mode: GameMode::Campaign,
+        }
+    }
+}
+
+impl TestApp {
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn players(mut self, players: usize) -> Self {
+        self.players = players;
+        self
+    }
+
+    pub fn mode(mut self, mode: GameMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    // A headless game that has already run its first frame, so the jets and
+    // the first enemy exist, and whose sprites have finished loading.
+    pub fn build(self) -> App {
+        let mut app = headless_app(Playfield::default());
+        app.insert_resource(GameRng::new(self.seed));
+        app.insert_resource(PlayerCount(self.players));
+        app.insert_resource(self.mode);
+        first_frame(app)
+    }
+}
+
+pub fn test_app() -> App {
+    TestApp::default().build()
+}
+
+fn first_frame(mut app: App) -> App {
+    app.update();
+    // enemies shoot back, keep stray bullets from ending a test run early
+    for stats in &mut app.world_mut().resource_mut::<Players>().0 {
+        stats.lives = u32::MAX;
+    }
+    wait_for_sprites(&mut app);
+    app
+}
+
+pub fn step(app: &mut App, ticks: u32) {
+    for _ in 0..ticks {
+        app.update();
+    }
+}
+
+// Steps until `done` returns true, giving up after `max_ticks`.
+pub fn step_until(app: &mut App, max_ticks: u32, mut done: impl FnMut(&mut App) -> bool) -> bool {
+    for _ in 0..max_ticks {
+        if done(app) {
+            return true;
+        }
+        app.update();
+    }
+    done(app)
+}
+
+pub fn press(app: &mut App, key: KeyCode) {
+    app.world_mut()
+        .resource_mut::<ButtonInput<KeyCode>>()
+        .press(key);
+}
+
+pub fn release(app: &mut App, key: KeyCode) {
+    app.world_mut()
+        .resource_mut::<ButtonInput<KeyCode>>()
+        .release(key);
+}
+
+pub fn entities<F: QueryFilter>(app: &mut App) -> Vec<Entity> {
+    let world = app.world_mut();
+    world.query_filtered::<Entity, F>().iter(world).collect()
+}
+
+// Asset loading runs on background threads, so frames are stepped until every
+// sprite image is available. The game clock only moves a few frames meanwhile.
+pub fn wait_for_sprites(app: &mut App) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        let world = app.world_mut();
+        let images: Vec<_> = world
+            .query::<&Sprite>()
+            .iter(world)
+            .map(|sprite| sprite.image.id())
+            .collect();
+        let asset_server = world.resource::<AssetServer>();
+        let pending = images.iter().any(|id| {
+            matches!(
+                asset_server.get_load_state(*id),
+                Some(LoadState::Loading | LoadState::NotLoaded)
+            )
+        });
+        if !pending {
+            return;
+        }
+        assert!(Instant::now() < deadline, "sprites did not finish loading");
+        std::thread::sleep(Duration::from_millis(1));
+        app.update();
+    }
+}
@@ -0,0 +1,9 @@
+use std::path::PathBuf;
+
+// Where saves live: the platform data directory (e.g. ~/.local/share on Linux),
+// or the working directory when the platform has none.
+pub fn user_data_dir() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("space_fight")
+}
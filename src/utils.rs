@@ -1,7 +1,5 @@
 use bevy::math::bounding::{Aabb2d, BoundingCircle, IntersectsVolume};
 
-pub fn ball_collision(bullet_circle : BoundingCircle, 
-    bounding_box : Aabb2d
-) -> bool {
+pub fn ball_collision(bullet_circle: BoundingCircle, bounding_box: Aabb2d) -> bool {
     return bullet_circle.intersects(&bounding_box);
-}
\ No newline at end of file
+}